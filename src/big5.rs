@@ -0,0 +1,93 @@
+/// The Big5 module handles the legacy encoding of traditional Chinese, with
+/// the extensions of the Hong Kong Supplementary Character Set, as in the
+/// WHATWG Encoding Standard.
+
+use crate::multi_byte::{self, MultiByteCodec, MultiByteEncoding, SEQUENCE_BASE};
+use crate::unicode_encoding::UnicodeEncodingError;
+use crate::cjk_indexes::BIG5;
+
+/// The Big5 codec, extended with HKSCS.
+pub struct Big5Codec;
+
+/// A wrapper for Big5 encoded bytes.
+pub type Big5 = MultiByteEncoding<Big5Codec>;
+
+impl MultiByteCodec for Big5Codec {
+    const NAME: &'static str = "Big5";
+
+    /// The letters of Vietnamese with a circumflex and a macron or a caron,
+    /// added by HKSCS, have no precomposed code-point.
+    const SEQUENCES: &'static [[u32; 2]] = &[[0xCA, 0x304], [0xCA, 0x30C], [0xEA, 0x304], [0xEA, 0x30C]];
+
+    fn decode_character(data: &[u8], start: usize) -> Result<(u32, usize), UnicodeEncodingError> {
+        let lead = data[start];
+        match lead {
+            0x00..=0x7F => return Ok((lead as u32, 1)),
+            0x81..=0xFE => {},
+            _ => return Err(multi_byte::invalid_byte(data, start)),
+        }
+        if start + 1 == data.len() {
+            return Err(multi_byte::missing_bytes(data, start));
+        }
+        let trail = data[start+1];
+        if !matches!(trail, 0x40..=0x7E | 0xA1..=0xFE) {
+            return Err(multi_byte::invalid_pair(data, start));
+        }
+        let trail_offset = if trail < 0x7F {0x40} else {0x62};
+        let pointer = (lead - 0x81) as usize * 157 + (trail - trail_offset) as usize;
+        if let Some(index) = SEQUENCE_POINTERS.iter().position(|x| *x == pointer) {
+            return Ok((SEQUENCE_BASE + index as u32, 2));
+        }
+        return match BIG5.code_point(pointer) {
+            Some(glyph) => Ok((glyph, 2)),
+            None => Err(multi_byte::invalid_pair(data, start)),
+        };
+    }
+
+    fn encode_character(glyph: u32, bytes: &mut Vec<u8>) -> bool {
+        if glyph < 0x80 {
+            bytes.push(glyph as u8);
+            return true;
+        }
+        let pointer = if LAST_POINTER_GLYPHS.contains(&glyph) {
+            BIG5.last_pointer(glyph)
+        } else {
+            BIG5.pointer(glyph)
+        };
+        return match pointer {
+            Some(pointer) => {
+                let trail = (pointer % 157) as u8;
+                bytes.push((pointer / 157) as u8 + 0x81);
+                bytes.push(trail + if trail < 0x3F {0x40} else {0x62});
+                true
+            },
+            None => false,
+        };
+    }
+}
+
+/* ---------------------------- Helper functions ---------------------------- */
+
+/// The pointers of the characters in `Big5Codec::SEQUENCES`.
+const SEQUENCE_POINTERS: [usize; 4] = [1133, 1135, 1164, 1166];
+
+/// The code-points encoded twice in Big5 for which the last pointer is used,
+/// as done by the encoders of the browsers.
+const LAST_POINTER_GLYPHS: [u32; 6] = [0x2550, 0x255E, 0x2561, 0x256A, 0x5341, 0x5345];
+
+/* --------------------------------- Testing -------------------------------- */
+
+#[test]
+fn test_big5() {
+    use crate::unicode_encoding::UnicodeEncoding;
+    use crate::unicode_encoding::UnicodeEncodingErrorKind::*;
+    assert_eq!(Big5::from_bytes(&[0xA4, 0xA4, 0xA4, 0xE5], ()).unwrap().to_string().unwrap(), "中文");
+    assert_eq!(Big5::from_string("中文").unwrap().data, vec![0xA4, 0xA4, 0xA4, 0xE5]);
+    assert_eq!(Big5::from_string("═卅").unwrap().data, vec![0xF9, 0xF9, 0xA4, 0xCA]);
+    let decoded = Big5::from_bytes(&[0x88, 0x62, 0x88, 0x64, 0x88, 0xA3, 0x88, 0xA5, 0x88, 0x66], ()).unwrap();
+    assert_eq!(decoded.to_string().unwrap(), "Ê̄Ê̌ê̄ê̌Ê");
+    // The extensions placed before Big5 are only decoded.
+    assert_eq!(Big5::from_bytes(&[0x87, 0x40], ()).unwrap().to_string().unwrap(), "䏰");
+    assert_eq!(Big5::from_string("䏰").err().unwrap().kind, UnmappableCodepoint);
+    assert_eq!(Big5::from_bytes(&[0xA4, 0x80], ()).err().unwrap().kind, InvalidMultiByteSequence);
+}
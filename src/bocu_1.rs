@@ -0,0 +1,382 @@
+/// The BOCU-1 module handles the __Binary Ordered Compression for Unicode__
+/// described in the Unicode Technical Note #6.
+
+use crate::unicode_encoding::UnicodeEncodingErrorKind::*;
+use crate::unicode_encoding::UnicodeEncodingError;
+use crate::unicode_encoding::UnicodeEncoding;
+use crate::utf_32::Utf32;
+use crate::incremental::{Decoder, Encoder, SanityChecker};
+use crate::lossy::{self, DecodingPolicy};
+
+/// A wrapper for BOCU-1 encoded bytes. Each code-point is encoded as its
+/// difference with the previous one, so text in a small script takes about
+/// one byte per character. The binary order of the encoded data is the order
+/// of the code-points, so encoded strings can be sorted without decoding them.
+pub struct Bocu1 {
+    pub data: Vec<u8>
+}
+
+impl UnicodeEncoding for Bocu1 {
+    const NAME: &'static str = "BOCU-1";
+    type Options = ();
+
+    /// Convert UTF-32 data to BOCU-1.
+    fn from_utf_32(data_utf_32: &Utf32) -> Self {
+        let mut prev = ASCII_PREV;
+        let mut data: Vec<u8> = Vec::new();
+        for glyph in &data_utf_32.data {
+            prev = encode_glyph(*glyph, prev, &mut data);
+        }
+        return Bocu1{data: data};
+    }
+
+    /// Convert BOCU-1 data to UTF-32.
+    fn try_to_utf_32(&self) -> Result<Utf32, UnicodeEncodingError> {
+        let mut prev = ASCII_PREV;
+        let mut data: Vec<u32> = Vec::new();
+        let mut index: usize = 0;
+        while index < self.data.len() {
+            let (glyph, new_prev, len) = decode_glyph(&self.data[index..], prev).map_err(|x| x.at_byte(index).in_encoding(Self::NAME))?;
+            data.extend(glyph);
+            prev = new_prev;
+            index += len;
+        }
+        return Ok(Utf32{data: data});
+    }
+
+    /// Convert the instance of `Bocu1` to a vector of bytes.
+    /// No transformation is needed.
+    fn to_bytes(&self, _options: ()) -> Vec<u8> {
+        return self.data.clone();
+    }
+
+    /// Consider a stream of BOCU-1 encoded bytes and turn it into a `Bocu1`
+    /// type. The bytes are decoded to ensure they are valid.
+    fn from_bytes_no_check(bytes: &[u8], _options: ()) -> Result<Self, UnicodeEncodingError> {
+        let ret = Bocu1{data: bytes.to_vec()};
+        ret.try_to_utf_32()?;
+        return Ok(ret);
+    }
+
+    /// Decodes the data glyph by glyph, replacing the invalid sequences. The
+    /// previous code-point is kept across the invalid sequences, so the
+    /// differences following one are read as they would have been.
+    fn from_bytes_lossy(bytes: &[u8], _options: (), policy: DecodingPolicy) -> Result<(Self, usize), UnicodeEncodingError> {
+        let mut prev = ASCII_PREV;
+        let mut data: Vec<u32> = Vec::new();
+        let mut count: usize = 0;
+        let mut index: usize = 0;
+        while index < bytes.len() {
+            match decode_glyph(&bytes[index..], prev) {
+                Ok((glyph, new_prev, len)) => {
+                    data.extend(glyph);
+                    prev = new_prev;
+                    index += len;
+                },
+                Err(err) => {
+                    let err = err.at_byte(index).in_encoding(Self::NAME);
+                    data.extend(policy.handle(&err)?);
+                    count += 1;
+                    index += std::cmp::max(1, err.code_units.len());
+                },
+            }
+        }
+        let (data, sanitized) = lossy::sanitize(&data, policy).map_err(|x| x.in_encoding(Self::NAME))?;
+        return Ok((Self::from_utf_32(&Utf32{data: data}), count + sanitized));
+    }
+
+    fn decoder(_options: ()) -> Box<dyn Decoder> {
+        return Box::new(Bocu1Decoder{prev: ASCII_PREV, pending_bytes: Vec::new(), consumed: 0, checker: SanityChecker::new()});
+    }
+
+    fn encoder(_options: ()) -> Box<dyn Encoder> {
+        return Box::new(Bocu1Encoder{prev: ASCII_PREV, checker: SanityChecker::new()});
+    }
+}
+
+/* --------------------------- Incremental coding --------------------------- */
+
+/// Decodes BOCU-1 data chunk by chunk. The previous code-point is kept
+/// between the chunks, as well as the bytes of an incomplete difference.
+pub struct Bocu1Decoder {
+    prev: i32,
+    pending_bytes: Vec<u8>,
+    consumed: usize,
+    checker: SanityChecker,
+}
+
+impl Decoder for Bocu1Decoder {
+    fn decode(&mut self, bytes: &[u8]) -> Result<Utf32, UnicodeEncodingError> {
+        self.pending_bytes.extend_from_slice(bytes);
+        let mut data: Vec<u32> = Vec::new();
+        let mut index: usize = 0;
+        while index < self.pending_bytes.len() {
+            match decode_glyph(&self.pending_bytes[index..], self.prev) {
+                Ok((glyph, prev, len)) => {
+                    data.extend(glyph);
+                    self.prev = prev;
+                    index += len;
+                },
+                Err(x) if x.kind == MissingEncodedBytes => break,
+                Err(x) => return Err(x.at_byte(self.consumed + index).in_encoding(Bocu1::NAME)),
+            }
+        }
+        self.pending_bytes.drain(..index);
+        self.consumed += index;
+        let ret = Utf32{data: data};
+        self.checker.push_all(&ret).map_err(|x| x.in_encoding(Bocu1::NAME))?;
+        return Ok(ret);
+    }
+
+    fn finish(&mut self) -> Result<Utf32, UnicodeEncodingError> {
+        if !self.pending_bytes.is_empty() {
+            return Err(UnicodeEncodingError::new(MissingEncodedBytes).at_byte(self.consumed).with_code_units(&self.pending_bytes).in_encoding(Bocu1::NAME));
+        }
+        self.checker.finish()?;
+        return Ok(Utf32{data: Vec::new()});
+    }
+}
+
+/// Encodes BOCU-1 data chunk by chunk. The previous code-point is kept
+/// between the chunks, so the result is the same as when the data is encoded
+/// at once.
+pub struct Bocu1Encoder {
+    prev: i32,
+    checker: SanityChecker,
+}
+
+impl Encoder for Bocu1Encoder {
+    fn encode(&mut self, data: &Utf32) -> Result<Vec<u8>, UnicodeEncodingError> {
+        self.checker.push_all(data)?;
+        let mut bytes: Vec<u8> = Vec::new();
+        for glyph in &data.data {
+            self.prev = encode_glyph(*glyph, self.prev, &mut bytes);
+        }
+        return Ok(bytes);
+    }
+
+    fn finish(&mut self) -> Result<Vec<u8>, UnicodeEncodingError> {
+        self.checker.finish()?;
+        return Ok(Vec::new());
+    }
+}
+
+/* ---------------------------- Helper functions ---------------------------- */
+
+/// The previous code-point at the beginning of the data and after a control
+/// character, in the middle of ASCII.
+const ASCII_PREV: i32 = 0x40;
+
+/// The lead byte of the differences encoded as a single byte, for a
+/// difference of 0.
+const MIDDLE: i32 = 0x90;
+/// The lowest byte value used for lead bytes and most of the trail bytes.
+const MIN: i32 = 0x21;
+/// A byte resetting the previous code-point, never written by the encoder.
+const RESET: u8 = 0xFF;
+
+/// Number of possible values of a trail byte: the bytes from `MIN` to 0xFF
+/// and some control characters.
+const TRAIL_COUNT: i32 = 0x100 - MIN + TRAIL_CONTROLS.len() as i32;
+/// The control characters that can be trail bytes.
+const TRAIL_CONTROLS: [u8; 20] = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18, 0x19, 0x1C, 0x1D, 0x1E, 0x1F];
+
+// Number of lead bytes for each length of the encoded differences.
+const SINGLE: i32 = 64;
+const LEAD_2: i32 = 43;
+const LEAD_3: i32 = 3;
+
+// The largest differences encoded with a given number of bytes.
+const REACH_POS_1: i32 = SINGLE - 1;
+const REACH_NEG_1: i32 = -SINGLE;
+const REACH_POS_2: i32 = REACH_POS_1 + LEAD_2 * TRAIL_COUNT;
+const REACH_NEG_2: i32 = REACH_NEG_1 - LEAD_2 * TRAIL_COUNT;
+const REACH_POS_3: i32 = REACH_POS_2 + LEAD_3 * TRAIL_COUNT * TRAIL_COUNT;
+const REACH_NEG_3: i32 = REACH_NEG_2 - LEAD_3 * TRAIL_COUNT * TRAIL_COUNT;
+
+// The first lead byte for each length of the encoded differences.
+const START_POS_2: i32 = MIDDLE + REACH_POS_1 + 1;
+const START_POS_3: i32 = START_POS_2 + LEAD_2;
+const START_POS_4: i32 = START_POS_3 + LEAD_3;
+const START_NEG_2: i32 = MIDDLE + REACH_NEG_1;
+const START_NEG_3: i32 = START_NEG_2 - LEAD_2;
+const START_NEG_4: i32 = START_NEG_3 - LEAD_3;
+
+/// Gives the previous code-point used to encode the glyph after `glyph`. It
+/// is the middle of its block of 128 code-points, except for the scripts
+/// that are bigger than a block.
+fn next_prev(glyph: i32) -> i32 {
+    return match glyph {
+        // Hiragana.
+        0x3040..=0x309F => 0x3070,
+        // Unihan, from which the whole block can be reached with two bytes.
+        0x4E00..=0x9FA5 => 0x4E00 - REACH_NEG_2,
+        // Hangul.
+        0xAC00..=0xD7A3 => (0xD7A3 + 0xAC00) / 2,
+        _ => (glyph & !0x7F) + ASCII_PREV,
+    };
+}
+
+fn value_to_trail_byte(value: i32) -> u8 {
+    if value < TRAIL_CONTROLS.len() as i32 {
+        return TRAIL_CONTROLS[value as usize];
+    }
+    return (value - TRAIL_CONTROLS.len() as i32 + MIN) as u8;
+}
+
+fn trail_byte_to_value(byte: u8) -> Option<i32> {
+    if byte as i32 >= MIN {
+        return Some(byte as i32 - MIN + TRAIL_CONTROLS.len() as i32);
+    }
+    return TRAIL_CONTROLS.iter().position(|x| *x == byte).map(|x| x as i32);
+}
+
+/// Encodes a glyph after the previous code-point `prev`. Returns the previous
+/// code-point for the next glyph.
+fn encode_glyph(glyph: u32, prev: i32, bytes: &mut Vec<u8>) -> i32 {
+    let glyph = glyph as i32;
+    // The controls and the space are written as themselves, the space does
+    // not change the previous code-point so it can be used between words.
+    if glyph <= 0x20 {
+        bytes.push(glyph as u8);
+        return if glyph == 0x20 {prev} else {ASCII_PREV};
+    }
+    let mut diff = glyph - prev;
+    if (REACH_NEG_1..=REACH_POS_1).contains(&diff) {
+        bytes.push((MIDDLE + diff) as u8);
+        return next_prev(glyph);
+    }
+    let (mut lead, count) = if diff > REACH_POS_1 {
+        if diff <= REACH_POS_2 {
+            diff -= REACH_POS_1 + 1;
+            (START_POS_2, 1)
+        } else if diff <= REACH_POS_3 {
+            diff -= REACH_POS_2 + 1;
+            (START_POS_3, 2)
+        } else {
+            diff -= REACH_POS_3 + 1;
+            (START_POS_4, 3)
+        }
+    } else if diff >= REACH_NEG_2 {
+        diff -= REACH_NEG_1;
+        (START_NEG_2, 1)
+    } else if diff >= REACH_NEG_3 {
+        diff -= REACH_NEG_2;
+        (START_NEG_3, 2)
+    } else {
+        diff -= REACH_NEG_3;
+        (START_NEG_4, 3)
+    };
+    // The trail bytes are the digits of the difference in base `TRAIL_COUNT`
+    // and the remaining quotient moves the lead byte, downward for the
+    // negative differences.
+    let mut trail_bytes: Vec<u8> = vec![0; count];
+    for i in (0..count).rev() {
+        trail_bytes[i] = value_to_trail_byte(diff.rem_euclid(TRAIL_COUNT));
+        diff = diff.div_euclid(TRAIL_COUNT);
+    }
+    lead += diff;
+    bytes.push(lead as u8);
+    bytes.extend(trail_bytes);
+    return next_prev(glyph);
+}
+
+/// Decodes the glyph at the beginning of some BOCU-1 data, after the previous
+/// code-point `prev`. Returns the glyph, if the bytes are not a reset, the
+/// previous code-point for the next glyph and the number of bytes read.
+fn decode_glyph(data: &[u8], prev: i32) -> Result<(Option<u32>, i32, usize), UnicodeEncodingError> {
+    let lead = data[0] as i32;
+    if lead <= 0x20 {
+        return Ok((Some(lead as u32), if lead == 0x20 {prev} else {ASCII_PREV}, 1));
+    }
+    if data[0] == RESET {
+        return Ok((None, ASCII_PREV, 1));
+    }
+    let (count, base, offset) = match lead {
+        START_NEG_2..START_POS_2 => (0, lead - MIDDLE, 0),
+        START_POS_2..START_POS_3 => (1, lead - START_POS_2, REACH_POS_1 + 1),
+        START_POS_3..START_POS_4 => (2, lead - START_POS_3, REACH_POS_2 + 1),
+        START_POS_4.. => (3, lead - START_POS_4, REACH_POS_3 + 1),
+        START_NEG_3..START_NEG_2 => (1, lead - START_NEG_2, REACH_NEG_1),
+        START_NEG_4..START_NEG_3 => (2, lead - START_NEG_3, REACH_NEG_2),
+        _ => (3, lead - START_NEG_4, REACH_NEG_3),
+    };
+    if data.len() < count + 1 {
+        return Err(UnicodeEncodingError::new(MissingEncodedBytes).with_code_units(data));
+    }
+    let mut diff = base;
+    for i in 1..=count {
+        match trail_byte_to_value(data[i]) {
+            Some(value) => diff = diff * TRAIL_COUNT + value,
+            None => return Err(UnicodeEncodingError::new(InvalidBocu1TrailByte).with_code_units(&data[..=i])),
+        }
+    }
+    let glyph = prev + diff + offset;
+    if !(0..=0x10FFFF).contains(&glyph) {
+        return Err(UnicodeEncodingError::new(CodepointAboveUnicodeRange).with_code_units(&data[..=count]));
+    }
+    return Ok((Some(glyph as u32), next_prev(glyph), count + 1));
+}
+
+/* --------------------------------- Testing -------------------------------- */
+
+#[test]
+fn test_bocu_1_signature() {
+    assert_eq!(Bocu1::bom(()), vec![0xFB, 0xEE, 0x28]);
+    assert_eq!(Bocu1::from_bytes(&[0xFB, 0xEE, 0x28, 0x20], ()).unwrap().to_utf_32().data, vec![0xFEFF, 0x20]);
+}
+
+#[test]
+fn test_bocu_1_round_trip() {
+    for s in ["Hello, world!\r\n", "aé€—ü", "Москва", "日本語とEnglish", "ひらがな 한국어", "😎🐤 a 😎\u{10FFFF}\u{1}"] {
+        let bocu1 = Bocu1::from_string(s).unwrap();
+        assert_eq!(Bocu1::from_bytes(&bocu1.data, ()).unwrap().to_string().unwrap(), s);
+    }
+    // Text in a small script takes a byte per character.
+    assert_eq!(Bocu1::from_string("Москва").unwrap().data.len(), 7);
+}
+
+#[test]
+fn test_bocu_1_binary_order() {
+    let mut strings = vec!["", "a", "ab", "\u{1}", "z z", "é", "Ā", "Ꭰ", "日本", "日", "한", "\u{FFFF}", "😎", "\u{10FFFF}", "a\u{10FFFF}", "a\u{1}"];
+    let mut encoded: Vec<Vec<u8>> = strings.iter().map(|x| Bocu1::from_string(x).unwrap().data).collect();
+    strings.sort();
+    encoded.sort();
+    let decoded: Vec<String> = encoded.iter().map(|x| Bocu1::from_bytes(x, ()).unwrap().to_string().unwrap()).collect();
+    assert_eq!(decoded, strings);
+}
+
+#[test]
+fn test_invalid_bocu_1() {
+    // A trail byte can't be a space.
+    assert_eq!(Bocu1::from_bytes(&[0xB1, 0xD0, 0x20], ()).err().unwrap().kind, InvalidBocu1TrailByte);
+    let err = Bocu1::from_bytes(&[0xB1, 0xFB, 0xEE], ()).err().unwrap();
+    assert_eq!(err.kind, MissingEncodedBytes);
+    assert_eq!(err.position, Some(crate::unicode_encoding::ErrorPosition::Byte(1)));
+    assert_eq!(Bocu1::from_bytes(&[0xFE, 0xFF, 0xFF, 0xFF], ()).err().unwrap().kind, CodepointAboveUnicodeRange);
+    // The reset byte is accepted, but not written.
+    assert_eq!(Bocu1::from_bytes(&[0xB1, 0xFF, 0xB1], ()).unwrap().to_string().unwrap(), "aa");
+}
+
+#[test]
+fn test_bocu_1_lossy() {
+    // The previous code-point is kept after an error.
+    let (decoded, count) = Bocu1::from_bytes_lossy(&[0xD3, 0xCA, 0xFE, 0xFF, 0xFF, 0xFF, 0x66], (), DecodingPolicy::Replace).unwrap();
+    assert_eq!(count, 1);
+    assert_eq!(decoded.try_to_utf_32().unwrap().data, vec![0x0416, 0xFFFD, 0x0416]);
+}
+
+#[test]
+fn test_bocu_1_incremental() {
+    let s = "Москва 日本語 😎";
+    let bytes = Bocu1::from_string(s).unwrap().data;
+    let mut decoder = Bocu1::decoder(());
+    let decoded = crate::incremental::decode_byte_per_byte(decoder.as_mut(), &bytes).unwrap();
+    assert_eq!(decoded.to_string().unwrap(), s);
+
+    let mut encoder = Bocu1::encoder(());
+    let mut encoded = encoder.encode(&Utf32::from_string("Москва ").unwrap()).unwrap();
+    encoded.extend(encoder.encode(&Utf32::from_string("日本語 😎").unwrap()).unwrap());
+    encoded.extend(encoder.finish().unwrap());
+    assert_eq!(encoded, bytes);
+}
@@ -1,12 +1,15 @@
 /// The CESU-8 module handle the __Compatibility Encoding Scheme for
 /// UTF-16: 8-Bit__.
 
-use crate::unicode_encoding::UnicodeEncodingError::*;
+use crate::unicode_encoding::UnicodeEncodingErrorKind::*;
 use crate::unicode_encoding::UnicodeEncodingError;
 use crate::unicode_encoding::UnicodeEncoding;
 use crate::utf_32::Utf32;
 use crate::utf_16::*;
-use crate::utf_8::Utf8;
+use crate::utf_8::{Utf8, Utf8Conformance};
+use crate::utf_8;
+use crate::incremental::{Decoder, Encoder, SanityChecker};
+use crate::lossy::{self, DecodingPolicy, GlyphDecoder};
 
 /// A wrapper for CESU-8 encoded bytes
 pub struct Cesu8 {
@@ -16,6 +19,9 @@ pub struct Cesu8 {
 }
 
 impl UnicodeEncoding for Cesu8 {
+    const NAME: &'static str = "CESU-8";
+    type Options = ();
+
     /// Convert UTF-32 data to CESU-8.
     fn from_utf_32(data_utf32: &Utf32)-> Self {
         let mut data: Vec<u8> = Vec::new();
@@ -25,73 +31,153 @@ impl UnicodeEncoding for Cesu8 {
                 data.push(byte);
             }
         }
-        return Cesu8{data: Utf8::from_bytes_no_check(&data, false).unwrap()};
+        return Cesu8{data: Utf8::from_bytes_lenient_no_check(&data).unwrap()};
     }
 
     /// Convert CESU-8 to UTF-32.
-    fn to_utf_32(&self) -> Utf32 {
-        let tmp_utf32 = self.data.to_utf_32();
-        let mut ret: Vec<u32> = Vec::new();
-        let mut loop_pointer = 0;
-        while loop_pointer < tmp_utf32.data.len() {
-            if loop_pointer < tmp_utf32.data.len() - 1 {
-                match compatible_codepoints(tmp_utf32.data[loop_pointer], tmp_utf32.data[loop_pointer+1]) {
-                    NoError => {
-                        ret.push(tmp_utf32.data[loop_pointer]);
-                        loop_pointer = loop_pointer + 1;
-                    },
-                    AmbiguousUnpairedSurrogates => {
-                        let surrogate_pair = vec![tmp_utf32.data[loop_pointer] as u16, tmp_utf32.data[loop_pointer+1] as u16];
-                        let utf16_bit = Utf16{data: surrogate_pair};
-                        ret.push(utf16_bit.to_utf_32().data[0]);
-                        loop_pointer = loop_pointer + 2;
-                    },
-                    x => {
-                        eprintln!("[UNICODE ENCODING ERROR] {:?}.", x);
-                        panic!("This should not have happen if the source was safely generated with from_string or from_bytes. This could happen if from_string_no_check was used. This need to be corrected from the library's user side.");
-                    }
-                }
-            } else {
-                ret.push(tmp_utf32.data[loop_pointer]);
-                loop_pointer = loop_pointer + 1;
-            }
-        }
-        return Utf32{data: ret};
+    fn try_to_utf_32(&self) -> Result<Utf32, UnicodeEncodingError> {
+        let tmp_utf32 = self.data.decode_with_conformance(Utf8Conformance::Lenient).map_err(|x| x.in_encoding(Self::NAME))?;
+        return Ok(Utf32{data: pair_surrogates(&tmp_utf32.data)});
     }
 
     /// Convert the instance of `Cesu8` to a vector of bytes, all the heavy
     /// lifting in made in the UTF-8 module.
-    fn to_bytes(&self, big_endian: bool) -> Vec<u8> {
-        return self.data.to_bytes(big_endian);
+    fn to_bytes(&self, _options: ()) -> Vec<u8> {
+        return self.data.to_bytes(());
     }
 
     /// Convert a stream of bytes encoded as CESU-8 into an instance of the
     /// `Cesu8` type. All the heavy lifting in made in the UTF-8 module.
-    fn from_bytes_no_check(bytes: &[u8], big_endian: bool) -> Result<Self, UnicodeEncodingError> {
-        match Utf8::from_bytes_no_check(bytes, big_endian) {
+    fn from_bytes_no_check(bytes: &[u8], _options: ()) -> Result<Self, UnicodeEncodingError> {
+        match Utf8::from_bytes_lenient_no_check(bytes) {
             Ok(x) => Ok(Cesu8{data: x}),
-            Err(y) => Err(y),
+            Err(y) => Err(y.in_encoding(Self::NAME)),
+        }
+    }
+
+    /// Decodes CESU-8 glyph by glyph, replacing the maximal subparts of the
+    /// invalid sequences as in UTF-8.
+    fn from_bytes_lossy(bytes: &[u8], _options: (), policy: DecodingPolicy) -> Result<(Self, usize), UnicodeEncodingError> {
+        let (glyphs, count) = lossy::decode_glyphs_lossy(bytes, utf_8::utf_8_glyph_to_utf_32, policy).map_err(|x| x.in_encoding(Self::NAME))?;
+        let (data, sanitized) = lossy::sanitize(&pair_surrogates(&glyphs), policy).map_err(|x| x.in_encoding(Self::NAME))?;
+        return Ok((Self::from_utf_32(&Utf32{data: data}), count + sanitized));
+    }
+
+    fn decoder(_options: ()) -> Box<dyn Decoder> {
+        return Box::new(Cesu8Decoder::with_glyph_decoder(utf_8::utf_8_glyph_to_utf_32, Self::NAME));
+    }
+
+    fn encoder(_options: ()) -> Box<dyn Encoder> {
+        return Box::new(Cesu8Encoder{checker: SanityChecker::new()});
+    }
+}
+
+/* --------------------------- Incremental coding --------------------------- */
+
+/// Decodes CESU-8 data chunk by chunk. A surrogate at the end of a chunk is
+/// kept until the next one to see if it is part of a pair.
+pub struct Cesu8Decoder {
+    glyph_decoder: GlyphDecoder,
+    /// Name of the encoding decoded, as the decoder is shared with the
+    /// encodings derived from CESU-8.
+    name: &'static str,
+    pending_bytes: Vec<u8>,
+    pending_surrogate: Option<u32>,
+    consumed: usize,
+    checker: SanityChecker,
+}
+
+impl Cesu8Decoder {
+    /// Creates a decoder reading the glyphs with `glyph_decoder` before
+    /// pairing their surrogates.
+    pub(crate) fn with_glyph_decoder(glyph_decoder: GlyphDecoder, name: &'static str) -> Self {
+        return Cesu8Decoder{glyph_decoder: glyph_decoder, name: name, pending_bytes: Vec::new(), pending_surrogate: None, consumed: 0, checker: SanityChecker::new()};
+    }
+}
+
+impl Decoder for Cesu8Decoder {
+    fn decode(&mut self, bytes: &[u8]) -> Result<Utf32, UnicodeEncodingError> {
+        self.pending_bytes.extend_from_slice(bytes);
+        let mut glyphs: Vec<u32> = Vec::new();
+        if let Some(surrogate) = self.pending_surrogate.take() {
+            glyphs.push(surrogate);
         }
+        glyphs.extend(utf_8::decode_available_glyphs(&mut self.pending_bytes, &mut self.consumed, self.glyph_decoder).map_err(|x| x.in_encoding(self.name))?);
+        // A high surrogate at the end of the chunk might be paired with a low
+        // surrogate at the beginning of the next one.
+        if glyphs.last().is_some_and(|x| is_high_surrogate(*x)) {
+            self.pending_surrogate = glyphs.pop();
+        }
+        let ret = Utf32{data: pair_surrogates(&glyphs)};
+        self.checker.push_all(&ret).map_err(|x| x.in_encoding(self.name))?;
+        return Ok(ret);
     }
 
+    fn finish(&mut self) -> Result<Utf32, UnicodeEncodingError> {
+        if !self.pending_bytes.is_empty() {
+            return Err(UnicodeEncodingError::new(MissingEncodedBytes).at_byte(self.consumed).with_code_units(&self.pending_bytes).in_encoding(self.name));
+        }
+        let ret = match self.pending_surrogate.take() {
+            Some(surrogate) => Utf32{data: vec![surrogate]},
+            None => Utf32{data: Vec::new()},
+        };
+        self.checker.push_all(&ret).map_err(|x| x.in_encoding(self.name))?;
+        self.checker.finish()?;
+        return Ok(ret);
+    }
+}
 
+/// Encodes CESU-8 data chunk by chunk.
+pub struct Cesu8Encoder {
+    checker: SanityChecker,
+}
 
+impl Encoder for Cesu8Encoder {
+    fn encode(&mut self, data: &Utf32) -> Result<Vec<u8>, UnicodeEncodingError> {
+        self.checker.push_all(data)?;
+        return Ok(Cesu8::from_utf_32(data).to_bytes(()));
+    }
+
+    fn finish(&mut self) -> Result<Vec<u8>, UnicodeEncodingError> {
+        self.checker.finish()?;
+        return Ok(Vec::new());
+    }
 }
 
 /* ---------------------------- Helper functions ---------------------------- */
 
 const SMALL_DATA_LIMIT: u32 = 0xFFFF;
 
-fn utf_32_glyph_to_cesu_8(glyph: u32) -> Vec<u8> {
+/// Turns the pairs of surrogates in a list of code-points decoded as UTF-8
+/// into the code-points they encode. Only a high surrogate followed by a low
+/// surrogate makes a pair.
+pub(crate) fn pair_surrogates(glyphs: &[u32]) -> Vec<u32> {
+    let mut ret: Vec<u32> = Vec::new();
+    let mut loop_pointer = 0;
+    while loop_pointer < glyphs.len() {
+        if loop_pointer + 1 < glyphs.len() && is_high_surrogate(glyphs[loop_pointer]) && is_low_surrogate(glyphs[loop_pointer+1]) {
+            let surrogate_pair = vec![glyphs[loop_pointer] as u16, glyphs[loop_pointer+1] as u16];
+            let utf16_bit = Utf16{data: surrogate_pair};
+            ret.push(utf16_bit.to_utf_32().data[0]);
+            loop_pointer = loop_pointer + 2;
+        } else {
+            ret.push(glyphs[loop_pointer]);
+            loop_pointer = loop_pointer + 1;
+        }
+    }
+    return ret;
+}
+
+pub(crate) fn utf_32_glyph_to_cesu_8(glyph: u32) -> Vec<u8> {
     let glyph_in_vec = Utf32{data: vec![glyph]};
     if glyph <= SMALL_DATA_LIMIT {
-        return glyph_in_vec.convert_to::<Utf8>().to_bytes(false);
+        return Utf8::from_utf_32(&glyph_in_vec).to_bytes(());
     } else {
-        let utf16 = glyph_in_vec.convert_to::<Utf16>();
+        let utf16 = Utf16::from_utf_32(&glyph_in_vec);
         let mut ret: Vec<u8> = Vec::new();
         for surrogate in utf16.data {
             let surrogate_in_vec = Utf32{data: vec![surrogate as u32]};
-            for byte in surrogate_in_vec.convert_to::<Utf8>().to_bytes(false) {
+            for byte in Utf8::from_utf_32(&surrogate_in_vec).to_bytes(()) {
                 ret.push(byte);
             }
         }
@@ -120,7 +206,7 @@ fn test_utf_32_glyph_to_cesu_8() {
 fn test_utf32_to_cesu_8_and_back() {
     fn conv_two_ways(glyph: u32) {
         let v = utf_32_glyph_to_cesu_8(glyph);
-        let cesu = Cesu8::from_bytes_no_check(&v, false).unwrap();
+        let cesu = Cesu8::from_bytes_no_check(&v, ()).unwrap();
         let utf32 = cesu.to_utf_32();
         let glyph_back = utf32.data[0];
         assert_eq!(glyph_back, glyph);
@@ -131,3 +217,25 @@ fn test_utf32_to_cesu_8_and_back() {
     conv_two_ways(0x10400);
 }
 
+
+#[test]
+fn test_cesu_8_incremental() {
+    let v: Vec<u8> = vec![0x45, 0xED, 0xA0, 0x81, 0xED, 0xB0, 0x80];
+    let mut decoder = Cesu8::decoder(());
+    let decoded = crate::incremental::decode_byte_per_byte(decoder.as_mut(), &v).unwrap();
+    assert_eq!(decoded.data, vec![0x45, 0x10400]);
+
+    let mut encoder = Cesu8::encoder(());
+    let mut encoded = encoder.encode(&Utf32{data: vec![0x45]}).unwrap();
+    encoded.extend(encoder.encode(&Utf32{data: vec![0x10400]}).unwrap());
+    encoded.extend(encoder.finish().unwrap());
+    assert_eq!(encoded, v);
+}
+
+#[test]
+fn test_cesu_8_lossy() {
+    let v: Vec<u8> = vec![0x45, 0xED, 0xA0, 0x81, 0xED, 0xB0, 0x80, 0xFF];
+    let (cesu, count) = Cesu8::from_bytes_lossy(&v, (), DecodingPolicy::Replace).unwrap();
+    assert_eq!(cesu.to_utf_32().data, vec![0x45, 0x10400, 0xFFFD]);
+    assert_eq!(count, 1);
+}
@@ -0,0 +1,161 @@
+/// The `detection` module guesses the encoding of data whose encoding is not
+/// known.
+
+use crate::encoding::Encoding;
+
+/// An encoding that could be the one of some data, with the confidence that
+/// it is the right one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Candidate {
+    /// The encoding that could be the one of the data.
+    pub encoding: Encoding,
+
+    /// How much the encoding is likely to be the right one, from 0 to 1.
+    pub confidence: f64,
+
+    /// Tells if the data starts with the BOM of the encoding. If so, it
+    /// should be removed with `Encoding::strip_bom` before decoding the data.
+    pub has_bom: bool,
+}
+
+/// The encodings that can be detected, with how much they are likely to be
+/// used when nothing else tells them apart.
+const PRIORS: [(Encoding, f64); 8] = [(Encoding::Utf8, 1.0),
+                                      (Encoding::Cesu8, 0.9),
+                                      (Encoding::Utf16Le, 0.8),
+                                      (Encoding::Utf16Be, 0.8),
+                                      (Encoding::Utf32Le, 0.8),
+                                      (Encoding::Utf32Be, 0.8),
+                                      (Encoding::Utf1, 0.5),
+                                      (Encoding::UtfEbcdic, 0.5)];
+
+/// Guesses the encoding of the first bytes of some data. The encodings in
+/// which the bytes are valid are returned, the most likely first. The bytes
+/// can stop in the middle of a glyph. The guess is made from the BOM, if
+/// there is one, from the position of the NUL bytes, which tells the size of
+/// the code units of text mostly made of ASCII, as described in the
+/// RFC 4627, and from how much the decoded code-points look like text.
+pub fn detect(bytes: &[u8]) -> Vec<Candidate> {
+    let bom = detect_bom(bytes);
+    let nul_pattern = detect_nul_pattern(bytes);
+    let mut ret: Vec<Candidate> = Vec::new();
+    for (encoding, prior) in PRIORS {
+        let has_bom = bom == Some(encoding);
+        let data = if has_bom {
+            encoding.strip_bom(bytes).1
+        } else {
+            bytes
+        };
+        let glyphs = match encoding.decoder().decode(data) {
+            Ok(x) => x.data,
+            Err(_) => continue,
+        };
+        let confidence = if has_bom {
+            1.0
+        } else if nul_pattern == Some(encoding) {
+            plausibility(&glyphs)
+        } else {
+            prior * plausibility(&glyphs)
+        };
+        ret.push(Candidate{encoding: encoding, confidence: confidence, has_bom: has_bom});
+    }
+    // The sort is stable so the order of the priors breaks the ties.
+    ret.sort_by(|x, y| y.confidence.total_cmp(&x.confidence));
+    return ret;
+}
+
+/* ---------------------------- Helper functions ---------------------------- */
+
+/// Tells which encoding has its BOM at the beginning of the bytes. The BOM of
+/// UTF-32LE starts with the one of UTF-16LE so UTF-32 is checked first.
+fn detect_bom(bytes: &[u8]) -> Option<Encoding> {
+    for encoding in [Encoding::Utf32, Encoding::Utf16, Encoding::Utf8, Encoding::Utf1, Encoding::UtfEbcdic] {
+        let (detected, data) = encoding.strip_bom(bytes);
+        if data.len() < bytes.len() {
+            return Some(detected);
+        }
+    }
+    return None;
+}
+
+/// Guesses the encoding from the position of the NUL bytes in the first code
+/// units. As ASCII characters are the most common, they tell the size of the
+/// code units and their endianness.
+fn detect_nul_pattern(bytes: &[u8]) -> Option<Encoding> {
+    if bytes.len() >= 4 {
+        match (bytes[0] == 0, bytes[1] == 0, bytes[2] == 0, bytes[3] == 0) {
+            (true, true, true, false) => return Some(Encoding::Utf32Be),
+            (false, true, true, true) => return Some(Encoding::Utf32Le),
+            (true, false, true, false) => return Some(Encoding::Utf16Be),
+            (false, true, false, true) => return Some(Encoding::Utf16Le),
+            _ => {},
+        }
+    }
+    if bytes.len() >= 2 {
+        match (bytes[0] == 0, bytes[1] == 0) {
+            (true, false) => return Some(Encoding::Utf16Be),
+            (false, true) => return Some(Encoding::Utf16Le),
+            _ => {},
+        }
+    }
+    return None;
+}
+
+/// Tells the proportion of code-points that are likely to be found in text.
+fn plausibility(glyphs: &[u32]) -> f64 {
+    if glyphs.is_empty() {
+        return 1.0;
+    }
+    let plausible = glyphs.iter().filter(|x| is_plausible(**x)).count();
+    return plausible as f64 / glyphs.len() as f64;
+}
+
+/// Tells if a code-point is likely to be found in text. Control characters
+/// other than white-spaces, surrogates, private use characters,
+/// non-characters, and code-points in the unassigned planes are not.
+fn is_plausible(glyph: u32) -> bool {
+    match glyph {
+        0x09 | 0x0A | 0x0D => true,
+        0x00..=0x1F | 0x7F..=0x9F => false,
+        0xD800..=0xDFFF => false,
+        0xE000..=0xF8FF => false,
+        0xFDD0..=0xFDEF => false,
+        x if x & 0xFFFE == 0xFFFE => false,
+        0x40000..=0xDFFFF => false,
+        0xF0000..=0x10FFFF => false,
+        _ => true,
+    }
+}
+
+/* --------------------------------- Testing -------------------------------- */
+
+#[test]
+fn test_detect_bom() {
+    let bytes: [u8; 6] = [0xFF, 0xFE, 0x00, 0x00, 0x41, 0x00];
+    let candidates = detect(&bytes[..4]);
+    assert_eq!(candidates[0].encoding, Encoding::Utf32Le);
+    assert!(candidates[0].has_bom);
+    let candidates = detect(&[0xFF, 0xFE, 0x41, 0x00]);
+    assert_eq!(candidates[0].encoding, Encoding::Utf16Le);
+    assert_eq!(candidates[0].confidence, 1.0);
+    let candidates = detect(&[0xEF, 0xBB, 0xBF, 0x41]);
+    assert_eq!(candidates[0].encoding, Encoding::Utf8);
+    assert!(candidates[0].has_bom);
+}
+
+#[test]
+fn test_detect_without_bom() {
+    use crate::utf_32::Utf32;
+    use crate::unicode_encoding::UnicodeEncoding;
+    let utf32 = Utf32::from_string("Hello, wörld! 😎").unwrap();
+    for encoding in [Encoding::Utf8, Encoding::Cesu8, Encoding::Utf16Le, Encoding::Utf16Be, Encoding::Utf32Le, Encoding::Utf32Be] {
+        let bytes = encoding.encode(&utf32).unwrap();
+        let candidates = detect(&bytes);
+        assert_eq!(candidates[0].encoding, encoding);
+        assert!(!candidates[0].has_bom);
+        // The bytes might be cut in the middle of a glyph.
+        assert_eq!(detect(&bytes[..bytes.len()-1])[0].encoding, encoding);
+    }
+    let candidates = detect(&[0xC3, 0x28, 0x41]);
+    assert!(candidates.iter().all(|x| x.encoding != Encoding::Utf8));
+}
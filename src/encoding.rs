@@ -0,0 +1,524 @@
+/// The `encoding` module contains the `Encoding` enumeration used to choose
+/// an encoding at runtime, for example from its name in a configuration file.
+
+use crate::unicode_encoding::UnicodeEncodingError;
+use crate::unicode_encoding::UnicodeEncoding;
+use crate::incremental::{Decoder, Encoder};
+use crate::lossy::DecodingPolicy;
+use crate::endianness::Endianness;
+use crate::utf_32::Utf32;
+use crate::utf_16::Utf16;
+use crate::utf_8::Utf8;
+use crate::utf_1::Utf1;
+use crate::utf_ebcdic::UtfEbcdic;
+use crate::utf_7::{Utf7, Utf7DirectCharacters};
+use crate::imap_utf_7::ImapUtf7;
+use crate::scsu::Scsu;
+use crate::bocu_1::Bocu1;
+use crate::single_byte::SingleByteEncoding;
+use crate::codepages::{Codepage, with_codepage_type};
+use crate::shift_jis::ShiftJis;
+use crate::euc_jp::EucJp;
+use crate::euc_kr::EucKr;
+use crate::big5::Big5;
+use crate::gbk::Gbk;
+use crate::gb18030::Gb18030;
+use crate::iso_2022_jp::Iso2022Jp;
+use crate::iso_2022_kr::Iso2022Kr;
+use crate::hz_gb_2312::HzGb2312;
+use crate::iso_6937::Iso6937;
+use crate::gsm_7::{Gsm7, Gsm7Packing};
+use crate::mapping::CharsetMapping;
+use crate::cesu_8::Cesu8;
+use crate::modified_utf_8::ModifiedUtf8;
+use crate::wtf_8::Wtf8;
+
+/// All the encodings supported by this crate, with their endianness when it
+/// matters, and the legacy encodings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// UTF-8, handled by the `Utf8` type.
+    Utf8,
+
+    /// UTF-1, handled by the `Utf1` type.
+    Utf1,
+
+    /// UTF-EBCDIC, handled by the `UtfEbcdic` type.
+    UtfEbcdic,
+
+    /// CESU-8, handled by the `Cesu8` type.
+    Cesu8,
+
+    /// The Modified UTF-8 of Java, handled by the `ModifiedUtf8` type.
+    ModifiedUtf8,
+
+    /// WTF-8, handled by the `Wtf8` type.
+    Wtf8,
+
+    /// UTF-7, handled by the `Utf7` type. Only the characters of the Set D
+    /// are written directly.
+    Utf7,
+
+    /// The modified UTF-7 of the IMAP mailbox names, handled by the
+    /// `ImapUtf7` type.
+    ImapUtf7,
+
+    /// SCSU, handled by the `Scsu` type.
+    Scsu,
+
+    /// BOCU-1, handled by the `Bocu1` type.
+    Bocu1,
+
+    /// GB18030, handled by the `Gb18030` type.
+    Gb18030,
+
+    /// UTF-16 whose endianness is told by its BOM. Without a BOM, it is
+    /// big-endian as required by the RFC 2781.
+    Utf16,
+
+    /// Little-endian UTF-16, handled by the `Utf16` type.
+    Utf16Le,
+
+    /// Big-endian UTF-16, handled by the `Utf16` type.
+    Utf16Be,
+
+    /// UTF-32 whose endianness is told by its BOM. Without a BOM, it is
+    /// big-endian.
+    Utf32,
+
+    /// Little-endian UTF-32, handled by the `Utf32` type.
+    Utf32Le,
+
+    /// Big-endian UTF-32, handled by the `Utf32` type.
+    Utf32Be,
+
+    /// Shift_JIS, handled by the `ShiftJis` type.
+    ShiftJis,
+
+    /// EUC-JP, handled by the `EucJp` type.
+    EucJp,
+
+    /// EUC-KR, handled by the `EucKr` type.
+    EucKr,
+
+    /// Big5 with the HKSCS extensions, handled by the `Big5` type.
+    Big5,
+
+    /// GBK, handled by the `Gbk` type.
+    Gbk,
+
+    /// ISO-2022-JP, handled by the `Iso2022Jp` type.
+    Iso2022Jp,
+
+    /// ISO-2022-KR, handled by the `Iso2022Kr` type.
+    Iso2022Kr,
+
+    /// HZ-GB-2312, handled by the `HzGb2312` type.
+    HzGb2312,
+
+    /// ISO 6937, handled by the `Iso6937` type.
+    Iso6937,
+
+    /// The GSM 7-bit default alphabet of the SMS, one septet per byte,
+    /// handled by the `Gsm7` type.
+    Gsm7,
+
+    /// The GSM 7-bit default alphabet of the SMS, with the septets packed as
+    /// they are sent over the air, handled by the `Gsm7` type.
+    Gsm7Packed,
+
+    /// A legacy single-byte codepage, handled by the `SingleByteEncoding`
+    /// type.
+    Codepage(Codepage),
+
+    /// A charset loaded at runtime from a mapping file, handled by
+    /// `CharsetMapping`.
+    Mapped(&'static CharsetMapping),
+}
+
+/// Calls some code with `$type` being the type implementing the encoding and
+/// `$options` the options to use with it. The charsets loaded from mapping
+/// files have no type, so `$mapped_body` is called instead with `$mapping`
+/// being their `CharsetMapping`.
+macro_rules! with_encoding_type {
+    ($encoding: expr, $type: ident, $options: ident, $body: expr, $mapping: ident => $mapped_body: expr) => {
+        match $encoding {
+            Encoding::Utf8    => {type $type = Utf8;  let $options = ();                  $body},
+            Encoding::Utf1    => {type $type = Utf1;  let $options = ();                  $body},
+            Encoding::UtfEbcdic => {type $type = UtfEbcdic; let $options = ();            $body},
+            Encoding::Cesu8   => {type $type = Cesu8; let $options = ();                  $body},
+            Encoding::ModifiedUtf8 => {type $type = ModifiedUtf8; let $options = ();     $body},
+            Encoding::Wtf8    => {type $type = Wtf8;  let $options = ();                  $body},
+            Encoding::Utf7    => {type $type = Utf7;  let $options = Utf7DirectCharacters::SetD; $body},
+            Encoding::ImapUtf7 => {type $type = ImapUtf7; let $options = ();             $body},
+            Encoding::Scsu    => {type $type = Scsu;  let $options = ();                  $body},
+            Encoding::Bocu1   => {type $type = Bocu1; let $options = ();                  $body},
+            Encoding::Gb18030 => {type $type = Gb18030; let $options = ();                $body},
+            Encoding::Utf16   => {type $type = Utf16; let $options = Endianness::FromBom; $body},
+            Encoding::Utf16Le => {type $type = Utf16; let $options = Endianness::Little;  $body},
+            Encoding::Utf16Be => {type $type = Utf16; let $options = Endianness::Big;     $body},
+            Encoding::Utf32   => {type $type = Utf32; let $options = Endianness::FromBom; $body},
+            Encoding::Utf32Le => {type $type = Utf32; let $options = Endianness::Little;  $body},
+            Encoding::Utf32Be => {type $type = Utf32; let $options = Endianness::Big;     $body},
+            Encoding::ShiftJis => {type $type = ShiftJis; let $options = ();             $body},
+            Encoding::EucJp   => {type $type = EucJp; let $options = ();                  $body},
+            Encoding::EucKr   => {type $type = EucKr; let $options = ();                  $body},
+            Encoding::Big5    => {type $type = Big5;  let $options = ();                  $body},
+            Encoding::Gbk     => {type $type = Gbk;   let $options = ();                  $body},
+            Encoding::Iso2022Jp => {type $type = Iso2022Jp; let $options = ();            $body},
+            Encoding::Iso2022Kr => {type $type = Iso2022Kr; let $options = ();            $body},
+            Encoding::HzGb2312 => {type $type = HzGb2312; let $options = ();             $body},
+            Encoding::Iso6937 => {type $type = Iso6937; let $options = ();               $body},
+            Encoding::Gsm7    => {type $type = Gsm7;  let $options = Gsm7Packing::Unpacked; $body},
+            Encoding::Gsm7Packed => {type $type = Gsm7; let $options = Gsm7Packing::Packed; $body},
+            Encoding::Codepage(x) => with_codepage_type!(x, C, {type $type = SingleByteEncoding<C>; let $options = (); $body}),
+            Encoding::Mapped($mapping) => $mapped_body,
+        }
+    }
+}
+
+impl Encoding {
+    /// All the Unicode encodings, in the order they are listed to the user.
+    /// The legacy encodings are listed in `Encoding::MULTI_BYTE`,
+    /// `Encoding::SMS` and `Codepage::ALL`.
+    pub const ALL: [Encoding; 17] = [Encoding::Utf8, Encoding::Utf1, Encoding::UtfEbcdic, Encoding::Cesu8, Encoding::ModifiedUtf8, Encoding::Wtf8,
+                                     Encoding::Utf7, Encoding::ImapUtf7, Encoding::Scsu, Encoding::Bocu1, Encoding::Gb18030,
+                                     Encoding::Utf16, Encoding::Utf16Le, Encoding::Utf16Be,
+                                     Encoding::Utf32, Encoding::Utf32Le, Encoding::Utf32Be];
+
+    /// The legacy multi-byte encodings of East Asia, including the stateful
+    /// ones, and ISO 6937, whose diacritics are bytes of their own.
+    pub const MULTI_BYTE: [Encoding; 9] = [Encoding::ShiftJis, Encoding::EucJp, Encoding::EucKr, Encoding::Big5, Encoding::Gbk,
+                                           Encoding::Iso2022Jp, Encoding::Iso2022Kr, Encoding::HzGb2312, Encoding::Iso6937];
+
+    /// The alphabet of the SMS, unpacked and packed.
+    pub const SMS: [Encoding; 2] = [Encoding::Gsm7, Encoding::Gsm7Packed];
+
+    /// Finds an encoding from its name or one of its aliases. The case of the
+    /// name is ignored.
+    pub fn from_name(name: &str) -> Option<Encoding> {
+        let codepages = Codepage::ALL.into_iter().map(Encoding::Codepage);
+        return Encoding::ALL.into_iter().chain(Encoding::MULTI_BYTE).chain(Encoding::SMS).chain(codepages).find(|x| x.name().eq_ignore_ascii_case(name) || x.aliases().iter().any(|y| y.eq_ignore_ascii_case(name)));
+    }
+
+    /// The canonical name of the encoding.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Encoding::Utf8    => "UTF-8",
+            Encoding::Utf1    => "UTF-1",
+            Encoding::UtfEbcdic => "UTF-EBCDIC",
+            Encoding::Cesu8   => "CESU-8",
+            Encoding::ModifiedUtf8 => "MUTF-8",
+            Encoding::Wtf8    => "WTF-8",
+            Encoding::Utf7    => "UTF-7",
+            Encoding::ImapUtf7 => "UTF-7-IMAP",
+            Encoding::Scsu    => "SCSU",
+            Encoding::Bocu1   => "BOCU-1",
+            Encoding::Gb18030 => "GB18030",
+            Encoding::Utf16   => "UTF-16",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::Utf16Be => "UTF-16BE",
+            Encoding::Utf32   => "UTF-32",
+            Encoding::Utf32Le => "UTF-32LE",
+            Encoding::Utf32Be => "UTF-32BE",
+            Encoding::ShiftJis => "Shift_JIS",
+            Encoding::EucJp   => "EUC-JP",
+            Encoding::EucKr   => "EUC-KR",
+            Encoding::Big5    => "Big5",
+            Encoding::Gbk     => "GBK",
+            Encoding::Iso2022Jp => "ISO-2022-JP",
+            Encoding::Iso2022Kr => "ISO-2022-KR",
+            Encoding::HzGb2312 => "HZ-GB-2312",
+            Encoding::Iso6937 => "ISO_6937",
+            Encoding::Gsm7    => "GSM-7",
+            Encoding::Gsm7Packed => "GSM-7-packed",
+            Encoding::Codepage(x) => x.name(),
+            Encoding::Mapped(x) => x.name(),
+        }
+    }
+
+    /// The other names of the encoding, taken from the IANA registry and the
+    /// WHATWG Encoding Standard. The `_be` suffixed names were used by the
+    /// CLI tool to tell that the data is big-endian.
+    pub fn aliases(&self) -> &'static [&'static str] {
+        match self {
+            Encoding::Utf8    => &["utf8", "csUTF8", "unicode-1-1-utf-8", "unicode11utf8", "unicode20utf8", "x-unicode20utf8"],
+            Encoding::Utf1    => &["utf1", "ISO-10646-UTF-1", "csISO10646UTF1"],
+            Encoding::UtfEbcdic => &["utfebcdic", "utf-ebcdic-1047"],
+            Encoding::Cesu8   => &["cesu8", "csCESU8", "csCESU-8"],
+            Encoding::ModifiedUtf8 => &["mutf8", "x-java-mutf8", "modified-utf-8", "java-modified-utf-8"],
+            Encoding::Wtf8    => &["wtf8"],
+            Encoding::Utf7    => &["utf7", "csUTF7", "unicode-1-1-utf-7", "csUnicode11UTF7"],
+            Encoding::ImapUtf7 => &["utf7-imap", "imap-utf-7", "x-imap4-modified-utf7"],
+            Encoding::Scsu    => &["csSCSU"],
+            Encoding::Bocu1   => &["bocu1", "csBOCU1", "csBOCU-1"],
+            Encoding::Gb18030 => &["gb-18030", "csGB18030"],
+            Encoding::Utf16   => &["utf16", "csUTF16"],
+            Encoding::Utf16Le => &["utf16le", "csUTF16LE", "unicode", "csUnicode", "ucs-2", "iso-10646-ucs-2", "unicodefeff"],
+            Encoding::Utf16Be => &["utf16be", "csUTF16BE", "UTF-16_be", "unicodefffe"],
+            Encoding::Utf32   => &["utf32", "csUTF32"],
+            Encoding::Utf32Le => &["utf32le", "csUTF32LE", "UCS-4LE"],
+            Encoding::Utf32Be => &["utf32be", "csUTF32BE", "UTF-32_be", "UCS-4BE"],
+            Encoding::ShiftJis => &["sjis", "shift-jis", "x-sjis", "csShiftJIS", "MS_Kanji", "windows-31j", "ms932", "cp932"],
+            Encoding::EucJp   => &["eucjp", "x-euc-jp", "csEUCPkdFmtJapanese"],
+            Encoding::EucKr   => &["euckr", "csEUCKR", "korean", "ks_c_5601-1987", "ks_c_5601-1989", "ksc5601", "ksc_5601", "csKSC56011987", "iso-ir-149", "windows-949", "cp949"],
+            Encoding::Big5    => &["big5-hkscs", "cn-big5", "csBig5", "x-x-big5"],
+            Encoding::Gbk     => &["x-gbk", "gb2312", "gb_2312", "gb_2312-80", "csGB2312", "csISO58GB231280", "iso-ir-58", "chinese", "windows-936", "cp936", "ms936"],
+            Encoding::Iso2022Jp => &["csISO2022JP", "iso2022jp"],
+            Encoding::Iso2022Kr => &["csISO2022KR", "iso2022kr"],
+            Encoding::HzGb2312 => &["hz", "hzgb2312"],
+            Encoding::Iso6937 => &["iso6937", "iso-6937", "ISO_6937:1992", "ISO_6937-2", "T.61", "T.61-8bit", "csISO103T618bit", "iso-ir-103"],
+            Encoding::Gsm7    => &["gsm7", "gsm0338", "gsm-03.38", "x-gsm-7bit"],
+            Encoding::Gsm7Packed => &["gsm7-packed", "gsm0338-packed"],
+            Encoding::Codepage(x) => x.aliases(),
+            Encoding::Mapped(_) => &[],
+        }
+    }
+
+    /// The size in bytes of the code units of the encoding.
+    pub fn code_unit_size(&self) -> usize {
+        match self {
+            Encoding::Utf8 | Encoding::Utf1 | Encoding::UtfEbcdic | Encoding::Cesu8 | Encoding::ModifiedUtf8 | Encoding::Wtf8 | Encoding::Utf7 | Encoding::ImapUtf7 | Encoding::Scsu | Encoding::Bocu1 | Encoding::Gb18030 => 1,
+            Encoding::ShiftJis | Encoding::EucJp | Encoding::EucKr | Encoding::Big5 | Encoding::Gbk | Encoding::Codepage(_) => 1,
+            Encoding::Iso2022Jp | Encoding::Iso2022Kr | Encoding::HzGb2312 | Encoding::Mapped(_) => 1,
+            Encoding::Iso6937 | Encoding::Gsm7 | Encoding::Gsm7Packed => 1,
+            Encoding::Utf16 | Encoding::Utf16Le | Encoding::Utf16Be => 2,
+            Encoding::Utf32 | Encoding::Utf32Le | Encoding::Utf32Be => 4,
+        }
+    }
+
+    /// Tells if the order of the bytes in a code unit matters.
+    pub fn is_endian_sensitive(&self) -> bool {
+        return self.code_unit_size() > 1;
+    }
+
+    /// Tells if the code units are big-endian. This is always false for the
+    /// encodings that are not sensitive to endianness. For the encodings
+    /// whose endianness is told by a BOM, this is the endianness used without
+    /// BOM.
+    pub fn is_big_endian(&self) -> bool {
+        return matches!(self, Encoding::Utf16 | Encoding::Utf16Be | Encoding::Utf32 | Encoding::Utf32Be);
+    }
+
+    /// Tells if the endianness of the encoding is told by a BOM at the
+    /// beginning of the data.
+    pub fn has_endianness_from_bom(&self) -> bool {
+        return *self == Encoding::Utf16 || *self == Encoding::Utf32;
+    }
+
+    /// Gives the encoding of the same family with the given endianness.
+    fn with_endianness(&self, big_endian: bool) -> Encoding {
+        match (self, big_endian) {
+            (Encoding::Utf16 | Encoding::Utf16Le | Encoding::Utf16Be, false) => Encoding::Utf16Le,
+            (Encoding::Utf16 | Encoding::Utf16Le | Encoding::Utf16Be, true) => Encoding::Utf16Be,
+            (Encoding::Utf32 | Encoding::Utf32Le | Encoding::Utf32Be, false) => Encoding::Utf32Le,
+            (Encoding::Utf32 | Encoding::Utf32Le | Encoding::Utf32Be, true) => Encoding::Utf32Be,
+            _ => *self,
+        }
+    }
+
+    /// The Byte Order Mark of the encoding.
+    pub fn bom(&self) -> Vec<u8> {
+        return with_encoding_type!(self, T, options, T::bom(options), _mapping => Vec::new());
+    }
+
+    /// Looks for a BOM of the encoding at the beginning of some bytes. Returns
+    /// the encoding to use to read them, with the endianness told by the BOM
+    /// if needed, and the bytes without the BOM. A BOM telling an endianness
+    /// other than the one of the encoding is not considered as a BOM.
+    pub fn strip_bom<'a>(&self, bytes: &'a [u8]) -> (Encoding, &'a [u8]) {
+        let candidates = if self.has_endianness_from_bom() {
+            vec![self.with_endianness(true), self.with_endianness(false)]
+        } else {
+            vec![*self]
+        };
+        for candidate in candidates {
+            let data = with_encoding_type!(candidate, T, options, T::strip_bom(bytes, options), _mapping => bytes);
+            if data.len() < bytes.len() {
+                return (candidate, data);
+            }
+        }
+        return (self.with_endianness(self.is_big_endian()), bytes);
+    }
+
+    /// The maximum number of bytes needed to encode a single code-point.
+    pub fn max_bytes_per_codepoint(&self) -> usize {
+        match self {
+            Encoding::Utf8 | Encoding::Wtf8 | Encoding::Bocu1 | Encoding::Gb18030 => 4,
+            Encoding::Utf1 | Encoding::UtfEbcdic => 5,
+            Encoding::Cesu8 | Encoding::ModifiedUtf8 => 6,
+            // A shift, the base64 of a surrogate pair and the end of the shift.
+            Encoding::Utf7 | Encoding::ImapUtf7 => 8,
+            // A window definition followed by the byte of the character, or
+            // a quoted pair of surrogates.
+            Encoding::Scsu => 6,
+            Encoding::Utf16 | Encoding::Utf16Le | Encoding::Utf16Be => 4,
+            Encoding::Utf32 | Encoding::Utf32Le | Encoding::Utf32Be => 4,
+            // JIS X 0212 is only decoded, so EUC-JP writes two bytes at most.
+            Encoding::ShiftJis | Encoding::EucJp | Encoding::EucKr | Encoding::Big5 | Encoding::Gbk => 2,
+            // The escape sequence of the character set, the character and
+            // the escape sequence back to ASCII.
+            Encoding::Iso2022Jp | Encoding::Iso2022Kr | Encoding::HzGb2312 => 8,
+            // A diacritic followed by the base letter.
+            Encoding::Iso6937 => 2,
+            // An escape septet followed by the septet of the extension table.
+            Encoding::Gsm7 | Encoding::Gsm7Packed => 2,
+            Encoding::Codepage(_) => 1,
+            Encoding::Mapped(x) => x.max_bytes_per_codepoint(),
+        }
+    }
+
+    /// Decodes a stream of bytes in the encoding. If the endianness of the
+    /// encoding is told by a BOM, the BOM is removed from the data.
+    pub fn decode(&self, bytes: &[u8]) -> Result<Utf32, UnicodeEncodingError> {
+        return with_encoding_type!(self, T, options, T::from_bytes(bytes, options)?.try_to_utf_32(), mapping => mapping.decode(bytes).map_err(|x| x.in_encoding(mapping.name())));
+    }
+
+    /// Decodes a stream of bytes in the encoding, handling the invalid
+    /// sequences with the policy. The number of invalid sequences is returned
+    /// along the data. If the endianness of the encoding is told by a BOM,
+    /// the BOM is removed from the data.
+    pub fn decode_lossy(&self, bytes: &[u8], policy: DecodingPolicy) -> Result<(Utf32, usize), UnicodeEncodingError> {
+        return with_encoding_type!(self, T, options, T::decode_lossy(bytes, options, policy), mapping => mapping.decode_lossy(bytes, policy).map_err(|x| x.in_encoding(mapping.name())));
+    }
+
+    /// Encodes UTF-32 data in the encoding.
+    pub fn encode(&self, data: &Utf32) -> Result<Vec<u8>, UnicodeEncodingError> {
+        return with_encoding_type!(self, T, options, Ok(T::try_from_utf_32(data)?.to_bytes(options)), mapping => mapping.encode(data).map_err(|x| x.in_encoding(mapping.name())));
+    }
+
+    /// Works like `encode` but the bytes start with the BOM of the encoding.
+    pub fn encode_with_bom(&self, data: &Utf32) -> Result<Vec<u8>, UnicodeEncodingError> {
+        let mut ret = self.bom();
+        ret.extend(self.encode(data)?);
+        return Ok(ret);
+    }
+
+    /// Creates a `Decoder` to decode the encoding chunk by chunk. If the
+    /// endianness of the encoding is told by a BOM, the BOM is removed from
+    /// the data.
+    pub fn decoder(&self) -> Box<dyn Decoder> {
+        return with_encoding_type!(self, T, options, T::decoder(options), mapping => mapping.decoder());
+    }
+
+    /// Creates an `Encoder` to encode the encoding chunk by chunk.
+    pub fn encoder(&self) -> Box<dyn Encoder> {
+        return with_encoding_type!(self, T, options, T::encoder(options), mapping => mapping.encoder());
+    }
+}
+
+impl std::fmt::Display for Encoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        return write!(f, "{}", self.name());
+    }
+}
+
+/* --------------------------------- Testing -------------------------------- */
+
+#[test]
+fn test_encoding_names() {
+    assert_eq!(Encoding::from_name("utf8"), Some(Encoding::Utf8));
+    assert_eq!(Encoding::from_name("UTF-16LE"), Some(Encoding::Utf16Le));
+    assert_eq!(Encoding::from_name("csUnicode"), Some(Encoding::Utf16Le));
+    assert_eq!(Encoding::from_name("UTF-32_BE"), Some(Encoding::Utf32Be));
+    assert_eq!(Encoding::from_name("latin1"), Some(Encoding::Codepage(Codepage::Iso8859_1)));
+    assert_eq!(Encoding::from_name("CP1252"), Some(Encoding::Codepage(Codepage::Windows1252)));
+    assert_eq!(Encoding::from_name("x-unknown"), None);
+    assert_eq!(Encoding::from_name("SJIS"), Some(Encoding::ShiftJis));
+    assert_eq!(Encoding::from_name("gb2312"), Some(Encoding::Gbk));
+    for encoding in Encoding::ALL.into_iter().chain(Encoding::MULTI_BYTE).chain(Encoding::SMS).chain(Codepage::ALL.into_iter().map(Encoding::Codepage)) {
+        assert_eq!(Encoding::from_name(&encoding.to_string()), Some(encoding));
+        for alias in encoding.aliases() {
+            assert_eq!(Encoding::from_name(alias), Some(encoding));
+        }
+    }
+}
+
+#[test]
+fn test_encoding_dispatch() {
+    let s = "aeé¤ㅢㅟ😎🐤";
+    let utf32 = Utf32::from_string(s).unwrap();
+    for encoding in Encoding::ALL {
+        let bytes = encoding.encode(&utf32).unwrap();
+        assert!(encoding.decode(&bytes).unwrap() == utf32);
+        let decoded = crate::incremental::decode_byte_per_byte(encoding.decoder().as_mut(), &bytes).unwrap();
+        assert!(decoded == utf32);
+    }
+    assert_eq!(Encoding::Utf16Be.encode(&Utf32{data: vec![0x41]}).unwrap(), vec![0x00, 0x41]);
+    assert!(Encoding::Utf8.encode(&Utf32{data: vec![0x110000]}).is_err());
+}
+
+#[test]
+fn test_codepage_dispatch() {
+    let utf32 = Utf32::from_string("Öl fließt").unwrap();
+    let encoding = Encoding::from_name("windows-1252").unwrap();
+    let bytes = encoding.encode(&utf32).unwrap();
+    assert_eq!(bytes, vec![0xD6, 0x6C, 0x20, 0x66, 0x6C, 0x69, 0x65, 0xDF, 0x74]);
+    assert!(encoding.decode(&bytes).unwrap() == utf32);
+    assert!(encoding.bom().is_empty());
+    assert_eq!(encoding.encode(&Utf32::from_string("😎").unwrap()).err().unwrap().kind, crate::unicode_encoding::UnicodeEncodingErrorKind::UnmappableCodepoint);
+    let decoded = crate::incremental::decode_byte_per_byte(Encoding::Codepage(Codepage::Koi8R).decoder().as_mut(), &[0xED, 0xCF, 0xD3, 0xCB, 0xD7, 0xC1]).unwrap();
+    assert_eq!(decoded.to_string().unwrap(), "Москва");
+}
+
+#[test]
+fn test_codepage_decode_lossy() {
+    let encoding = Encoding::Codepage(Codepage::Windows1252);
+    let (decoded, count) = encoding.decode_lossy(&[0x41, 0x81, 0x42], DecodingPolicy::Replace).unwrap();
+    assert_eq!(count, 1);
+    assert_eq!(decoded.data, vec![0x41, 0xFFFD, 0x42]);
+}
+
+#[test]
+fn test_multi_byte_dispatch() {
+    let samples = [(Encoding::ShiftJis, "日本語ｱ"), (Encoding::EucJp, "日本語ｱ"), (Encoding::EucKr, "한국어"),
+                   (Encoding::Big5, "中文"), (Encoding::Gbk, "中文€"), (Encoding::Iso2022Jp, "日本語 abc"),
+                   (Encoding::Iso2022Kr, "한국어"), (Encoding::HzGb2312, "中文~"), (Encoding::Iso6937, "Škoda ½")];
+    for (encoding, text) in samples {
+        let utf32 = Utf32::from_string(text).unwrap();
+        let bytes = encoding.encode(&utf32).unwrap();
+        assert!(bytes.len() <= utf32.data.len() * encoding.max_bytes_per_codepoint());
+        assert!(encoding.decode(&bytes).unwrap() == utf32);
+        let decoded = crate::incremental::decode_byte_per_byte(encoding.decoder().as_mut(), &bytes).unwrap();
+        assert!(decoded == utf32);
+        assert!(encoding.bom().is_empty());
+        assert!(encoding.encode(&Utf32::from_string("😎").unwrap()).is_err());
+    }
+}
+
+#[test]
+fn test_sms_dispatch() {
+    let utf32 = Utf32::from_string("hellohello €").unwrap();
+    for encoding in Encoding::SMS {
+        let bytes = encoding.encode(&utf32).unwrap();
+        assert!(encoding.decode(&bytes).unwrap() == utf32);
+        let decoded = crate::incremental::decode_byte_per_byte(encoding.decoder().as_mut(), &bytes).unwrap();
+        assert!(decoded == utf32);
+        assert!(encoding.bom().is_empty());
+        assert!(encoding.encode(&Utf32::from_string("😎").unwrap()).is_err());
+    }
+    assert_eq!(Encoding::from_name("gsm0338"), Some(Encoding::Gsm7));
+    assert_eq!(Encoding::Gsm7Packed.encode(&Utf32::from_string("hellohello").unwrap()).unwrap(), vec![0xE8, 0x32, 0x9B, 0xFD, 0x46, 0x97, 0xD9, 0xEC, 0x37]);
+}
+
+#[test]
+fn test_encoding_bom() {
+    let utf32 = Utf32{data: vec![0x41, 0x1F60E]};
+    let le_bytes = Encoding::Utf16Le.encode_with_bom(&utf32).unwrap();
+    assert_eq!(le_bytes[..2], [0xFF, 0xFE]);
+    assert_eq!(Encoding::Utf16.strip_bom(&le_bytes), (Encoding::Utf16Le, &le_bytes[2..]));
+    assert!(Encoding::Utf16.decode(&le_bytes).unwrap() == utf32);
+    let decoded = crate::incremental::decode_byte_per_byte(Encoding::Utf16.decoder().as_mut(), &le_bytes).unwrap();
+    assert!(decoded == utf32);
+    // Without BOM, UTF-16 is big-endian.
+    assert!(Encoding::Utf16.decode(&Encoding::Utf16Be.encode(&utf32).unwrap()).unwrap() == utf32);
+    // With an explicit endianness, the BOM is kept as a code-point.
+    assert_eq!(Encoding::Utf16Le.decode(&le_bytes).unwrap().data, vec![0xFEFF, 0x41, 0x1F60E]);
+    assert_eq!(Encoding::Utf16Be.strip_bom(&le_bytes), (Encoding::Utf16Be, &le_bytes[..]));
+
+    let be_bytes = Encoding::Utf32.encode_with_bom(&utf32).unwrap();
+    assert_eq!(be_bytes[..4], [0x00, 0x00, 0xFE, 0xFF]);
+    assert!(Encoding::Utf32.decode(&be_bytes).unwrap() == utf32);
+    let utf8_bytes = Encoding::Utf8.encode_with_bom(&utf32).unwrap();
+    assert_eq!(Encoding::Utf8.strip_bom(&utf8_bytes).1, &utf8_bytes[3..]);
+}
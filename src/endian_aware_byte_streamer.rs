@@ -2,7 +2,7 @@
 /// knowing and taking care about the endianness. It works with any number type
 /// that can be bit-cased to u64.
 
-use crate::unicode_encoding::UnicodeEncodingError::*;
+use crate::unicode_encoding::UnicodeEncodingErrorKind::*;
 use crate::unicode_encoding::UnicodeEncodingError;
 use std::mem::size_of;
 
@@ -61,7 +61,8 @@ pub fn to_bytes<T: Copy>(data: &Vec<T>, big_endian: bool) -> Vec<u8>  where u64:
 pub fn from_bytes<T: Copy + TryFrom<u64> + std::fmt::Debug>(bytes: &[u8], big_endian: bool) -> Result<Vec<T>, UnicodeEncodingError> where <T as TryFrom<u64>>::Error: std::fmt::Debug {
     let len_t = size_of::<T>();
     if bytes.len() % len_t != 0 {
-        return Err(InvalidStreamSize);
+        let end = bytes.len() - bytes.len() % len_t;
+        return Err(UnicodeEncodingError::new(InvalidStreamSize).at_byte(end).with_code_units(&bytes[end..]));
     }
     let mut ret: Vec<T> = Vec::new();
     let endian_index = gen_endian_indexes::<T>(big_endian);
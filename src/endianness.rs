@@ -0,0 +1,63 @@
+/// The `endianness` module contains the `Endianness` type used to tell the
+/// order of the bytes of the encodings with multi-byte code units.
+
+use crate::endian_aware_byte_streamer;
+use crate::unicode_encoding::BOM_CODEPOINT;
+
+/// The order of the bytes in the code units of encodings such as UTF-16 or
+/// UTF-32.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endianness {
+    /// The least significant byte comes first.
+    Little,
+
+    /// The most significant byte comes first.
+    Big,
+
+    /// The endianness of the machine running the program.
+    Native,
+
+    /// When reading data, the endianness is told by the BOM at its
+    /// beginning, which is removed. Without a BOM, the data is big-endian as
+    /// required by the RFC 2781. When writing data, it is big-endian.
+    FromBom,
+}
+
+impl Endianness {
+    /// Tells if the data is big-endian when the endianness is not told by a
+    /// BOM.
+    pub fn is_big_endian(&self) -> bool {
+        match self {
+            Endianness::Little => false,
+            Endianness::Big => true,
+            Endianness::Native => cfg!(target_endian = "big"),
+            Endianness::FromBom => true,
+        }
+    }
+
+    /// Tells if the data is big-endian and the size of the BOM that must be
+    /// skipped at its beginning. The BOM is only looked for when the
+    /// endianness is `FromBom`, `T` is the type of the code units.
+    pub(crate) fn resolve<T: Copy + From<u16>>(&self, bytes: &[u8]) -> (bool, usize) where u64: From<T> {
+        if *self != Endianness::FromBom {
+            return (self.is_big_endian(), 0);
+        }
+        for big_endian in [true, false] {
+            let bom = endian_aware_byte_streamer::to_bytes::<T>(&vec![T::from(BOM_CODEPOINT as u16)], big_endian);
+            if bytes.starts_with(&bom) {
+                return (big_endian, bom.len());
+            }
+        }
+        return (true, 0);
+    }
+}
+
+/* --------------------------------- Testing -------------------------------- */
+
+#[test]
+fn test_resolve() {
+    assert_eq!(Endianness::FromBom.resolve::<u16>(&[0xFF, 0xFE, 0x41, 0x00]), (false, 2));
+    assert_eq!(Endianness::FromBom.resolve::<u16>(&[0x00, 0x41]), (true, 0));
+    assert_eq!(Endianness::FromBom.resolve::<u32>(&[0xFF, 0xFE, 0x00, 0x00]), (false, 4));
+    assert_eq!(Endianness::Little.resolve::<u16>(&[0xFE, 0xFF]), (false, 0));
+}
@@ -0,0 +1,105 @@
+/// The EUC-JP module handles the __Extended Unix Code__ for Japanese, where
+/// the characters of JIS X 0208 are two bytes, the half-width katakana are
+/// prefixed with 0x8E and the characters of JIS X 0212 with 0x8F.
+
+use crate::unicode_encoding::UnicodeEncodingErrorKind::*;
+use crate::multi_byte::{self, MultiByteCodec, MultiByteEncoding};
+use crate::unicode_encoding::UnicodeEncodingError;
+use crate::cjk_indexes::{JIS0208, JIS0212};
+
+/// The EUC-JP codec. As in the WHATWG Encoding Standard, JIS X 0212 is only
+/// used to decode.
+pub struct EucJpCodec;
+
+/// A wrapper for EUC-JP encoded bytes.
+pub type EucJp = MultiByteEncoding<EucJpCodec>;
+
+impl MultiByteCodec for EucJpCodec {
+    const NAME: &'static str = "EUC-JP";
+
+    fn decode_character(data: &[u8], start: usize) -> Result<(u32, usize), UnicodeEncodingError> {
+        let lead = data[start];
+        match lead {
+            0x00..=0x7F => return Ok((lead as u32, 1)),
+            0x8E | 0x8F | 0xA1..=0xFE => {},
+            _ => return Err(multi_byte::invalid_byte(data, start)),
+        }
+        if start + 1 == data.len() {
+            return Err(multi_byte::missing_bytes(data, start));
+        }
+        let trail = data[start+1];
+        if lead == 0x8E && matches!(trail, 0xA1..=0xDF) {
+            return Ok((0xFF61 + (trail - 0xA1) as u32, 2));
+        }
+        if lead == 0x8F && is_euc_byte(trail) {
+            // A character of JIS X 0212, whose bytes follow 0x8F.
+            if start + 2 == data.len() {
+                return Err(multi_byte::missing_bytes(data, start));
+            }
+            let last = data[start+2];
+            if is_euc_byte(last) {
+                if let Some(glyph) = JIS0212.code_point(pointer(trail, last)) {
+                    return Ok((glyph, 3));
+                }
+            }
+            let len = if last < 0x80 {2} else {3};
+            return Err(UnicodeEncodingError::new(InvalidMultiByteSequence).at_byte(start).with_code_units(&data[start..start+len]));
+        }
+        if is_euc_byte(lead) && is_euc_byte(trail) {
+            if let Some(glyph) = JIS0208.code_point(pointer(lead, trail)) {
+                return Ok((glyph, 2));
+            }
+        }
+        return Err(multi_byte::invalid_pair(data, start));
+    }
+
+    fn encode_character(glyph: u32, bytes: &mut Vec<u8>) -> bool {
+        match glyph {
+            0x00..=0x7F => bytes.push(glyph as u8),
+            0xA5 => bytes.push(0x5C),
+            0x203E => bytes.push(0x7E),
+            0xFF61..=0xFF9F => bytes.extend_from_slice(&[0x8E, (glyph - 0xFF61) as u8 + 0xA1]),
+            _ => {
+                let glyph = if glyph == 0x2212 {0xFF0D} else {glyph};
+                let pointer = match JIS0208.pointer(glyph) {
+                    // The IBM extensions are beyond the 94 rows of JIS X 0208.
+                    Some(pointer) if pointer < 94 * 94 => pointer,
+                    _ => return false,
+                };
+                bytes.push((pointer / 94) as u8 + 0xA1);
+                bytes.push((pointer % 94) as u8 + 0xA1);
+            },
+        }
+        return true;
+    }
+}
+
+/* ---------------------------- Helper functions ---------------------------- */
+
+/// Tells if a byte is one of the 94 bytes used by the characters of the JIS
+/// standards.
+fn is_euc_byte(byte: u8) -> bool {
+    return matches!(byte, 0xA1..=0xFE);
+}
+
+/// The pointer of a character of the JIS standards.
+fn pointer(row: u8, cell: u8) -> usize {
+    return (row - 0xA1) as usize * 94 + (cell - 0xA1) as usize;
+}
+
+/* --------------------------------- Testing -------------------------------- */
+
+#[test]
+fn test_euc_jp() {
+    use crate::unicode_encoding::UnicodeEncoding;
+    let bytes = [0xC6, 0xFC, 0xCB, 0xDC, 0xB8, 0xEC, 0x8E, 0xB1];
+    assert_eq!(EucJp::from_bytes(&bytes, ()).unwrap().to_string().unwrap(), "日本語ｱ");
+    assert_eq!(EucJp::from_string("日本語ｱ").unwrap().data, bytes);
+    assert_eq!(EucJp::from_bytes(&[0x8F, 0xB0, 0xA1], ()).unwrap().to_string().unwrap(), "丂");
+    assert_eq!(EucJp::from_string("丂").err().unwrap().kind, UnmappableCodepoint);
+    let err = EucJp::from_bytes(&[0x8F, 0xA1, 0x41], ()).err().unwrap();
+    assert_eq!(err.code_units, vec![0x8F, 0xA1]);
+    let err = EucJp::from_bytes(&[0x8F, 0x41], ()).err().unwrap();
+    assert_eq!(err.code_units, vec![0x8F]);
+    assert_eq!(EucJp::from_bytes(&[0x8F, 0xB0], ()).err().unwrap().kind, MissingEncodedBytes);
+}
@@ -0,0 +1,65 @@
+/// The EUC-KR module handles the legacy Korean encoding, as extended by
+/// Windows with the Unified Hangul Code to cover all the modern syllables.
+
+use crate::multi_byte::{self, MultiByteCodec, MultiByteEncoding};
+use crate::unicode_encoding::UnicodeEncodingError;
+use crate::cjk_indexes::EUC_KR;
+
+/// The EUC-KR codec, which is the codepage 949 of Windows.
+pub struct EucKrCodec;
+
+/// A wrapper for EUC-KR encoded bytes.
+pub type EucKr = MultiByteEncoding<EucKrCodec>;
+
+impl MultiByteCodec for EucKrCodec {
+    const NAME: &'static str = "EUC-KR";
+
+    fn decode_character(data: &[u8], start: usize) -> Result<(u32, usize), UnicodeEncodingError> {
+        let lead = data[start];
+        match lead {
+            0x00..=0x7F => return Ok((lead as u32, 1)),
+            0x81..=0xFE => {},
+            _ => return Err(multi_byte::invalid_byte(data, start)),
+        }
+        if start + 1 == data.len() {
+            return Err(multi_byte::missing_bytes(data, start));
+        }
+        let trail = data[start+1];
+        if matches!(trail, 0x41..=0xFE) {
+            if let Some(glyph) = EUC_KR.code_point((lead - 0x81) as usize * 190 + (trail - 0x41) as usize) {
+                return Ok((glyph, 2));
+            }
+        }
+        return Err(multi_byte::invalid_pair(data, start));
+    }
+
+    fn encode_character(glyph: u32, bytes: &mut Vec<u8>) -> bool {
+        if glyph < 0x80 {
+            bytes.push(glyph as u8);
+            return true;
+        }
+        return match EUC_KR.pointer(glyph) {
+            Some(pointer) => {
+                bytes.push((pointer / 190) as u8 + 0x81);
+                bytes.push((pointer % 190) as u8 + 0x41);
+                true
+            },
+            None => false,
+        };
+    }
+}
+
+/* --------------------------------- Testing -------------------------------- */
+
+#[test]
+fn test_euc_kr() {
+    use crate::unicode_encoding::UnicodeEncoding;
+    use crate::unicode_encoding::UnicodeEncodingErrorKind::*;
+    let bytes = [0xC7, 0xD1, 0xB1, 0xB9, 0xBE, 0xEE, 0x8C, 0x63];
+    assert_eq!(EucKr::from_bytes(&bytes, ()).unwrap().to_string().unwrap(), "한국어똠");
+    assert_eq!(EucKr::from_string("한국어똠").unwrap().data, bytes);
+    assert_eq!(EucKr::from_string("ㄱ漢").unwrap().data, vec![0xA4, 0xA1, 0xF9, 0xD3]);
+    assert_eq!(EucKr::from_bytes(&[0x80], ()).err().unwrap().kind, InvalidMultiByteSequence);
+    assert_eq!(EucKr::from_string("€").unwrap().data, vec![0xA2, 0xE6]);
+    assert_eq!(EucKr::from_string("😎").err().unwrap().kind, UnmappableCodepoint);
+}
@@ -0,0 +1,131 @@
+/// The GB18030 module handles the Chinese national standard extending GBK
+/// with four-byte characters, which makes it able to encode every Unicode
+/// code-point.
+
+use crate::multi_byte::{self, MultiByteCodec, MultiByteEncoding};
+use crate::unicode_encoding::UnicodeEncodingErrorKind::*;
+use crate::unicode_encoding::UnicodeEncodingError;
+use crate::cjk_indexes::GB18030_RANGES;
+use crate::gbk::{self, GbkCodec};
+
+/// The GB18030 codec. The characters of GBK are one or two bytes, the other
+/// code-points of the BMP are four bytes given by ranges of consecutive
+/// code-points, and the supplementary code-points are four bytes computed
+/// from their value.
+pub struct Gb18030Codec;
+
+/// A wrapper for GB18030 encoded bytes. Unlike the other legacy encodings,
+/// every code-point can be encoded.
+pub type Gb18030 = MultiByteEncoding<Gb18030Codec>;
+
+impl MultiByteCodec for Gb18030Codec {
+    const NAME: &'static str = "GB18030";
+    const HAS_BOM: bool = true;
+
+    fn decode_character(data: &[u8], start: usize) -> Result<(u32, usize), UnicodeEncodingError> {
+        let four_bytes = matches!(data[start], 0x81..=0xFE) && matches!(data.get(start+1), Some(0x30..=0x39));
+        if !four_bytes {
+            return GbkCodec::decode_character(data, start);
+        }
+        // Each byte is checked as soon as it is there, so a broken sequence
+        // is reported even at the end of the data.
+        for (i, range) in [(2, 0x81..=0xFE), (3, 0x30..=0x39)] {
+            match data.get(start+i) {
+                None => return Err(multi_byte::missing_bytes(data, start)),
+                Some(byte) if !range.contains(byte) => return Err(multi_byte::invalid_byte(data, start)),
+                _ => {},
+            }
+        }
+        let digits = [data[start] - 0x81, data[start+1] - 0x30, data[start+2] - 0x81, data[start+3] - 0x30];
+        let pointer = ((digits[0] as usize * 10 + digits[1] as usize) * 126 + digits[2] as usize) * 10 + digits[3] as usize;
+        let glyph = match pointer {
+            E7C7_POINTER => 0xE7C7,
+            0..=BMP_LAST_POINTER => {
+                let (first_pointer, first_glyph) = GB18030_RANGES[GB18030_RANGES.partition_point(|x| x.0 <= pointer) - 1];
+                first_glyph + (pointer - first_pointer) as u32
+            },
+            SUPPLEMENTARY_FIRST_POINTER..=SUPPLEMENTARY_LAST_POINTER => 0x10000 + (pointer - SUPPLEMENTARY_FIRST_POINTER) as u32,
+            _ => return Err(UnicodeEncodingError::new(InvalidMultiByteSequence).at_byte(start).with_code_units(&data[start..start+4])),
+        };
+        return Ok((glyph, 4));
+    }
+
+    fn encode_character(glyph: u32, bytes: &mut Vec<u8>) -> bool {
+        let pointer = match glyph {
+            0x00..=0x7F => {
+                bytes.push(glyph as u8);
+                return true;
+            },
+            0xD800..=0xDFFF | 0x110000.. => return false,
+            0x10000.. => SUPPLEMENTARY_FIRST_POINTER + (glyph - 0x10000) as usize,
+            0xE7C7 => E7C7_POINTER,
+            // Unlike GBK, U+E5E5 is kept to round-trip the data.
+            _ if gbk::encode_two_bytes(glyph, bytes) => return true,
+            _ => {
+                let (first_pointer, first_glyph) = GB18030_RANGES[GB18030_RANGES.partition_point(|x| x.1 <= glyph) - 1];
+                first_pointer + (glyph - first_glyph) as usize
+            },
+        };
+        bytes.push((pointer / 12600) as u8 + 0x81);
+        bytes.push((pointer / 1260 % 10) as u8 + 0x30);
+        bytes.push((pointer / 10 % 126) as u8 + 0x81);
+        bytes.push((pointer % 10) as u8 + 0x30);
+        return true;
+    }
+}
+
+/* ---------------------------- Helper functions ---------------------------- */
+
+/// The pointer of U+E7C7. Until GB18030-2005, it was the pointer of U+1E3F,
+/// which has moved to two bytes, so it lies in the range of U+1E3F.
+const E7C7_POINTER: usize = 7457;
+
+/// The pointer of the four bytes of U+FFFF, the last one of the BMP.
+const BMP_LAST_POINTER: usize = 39419;
+
+/// The pointer of the four bytes of U+10000.
+const SUPPLEMENTARY_FIRST_POINTER: usize = 189000;
+
+/// The pointer of the four bytes of U+10FFFF.
+const SUPPLEMENTARY_LAST_POINTER: usize = SUPPLEMENTARY_FIRST_POINTER + 0xFFFFF;
+
+/* --------------------------------- Testing -------------------------------- */
+
+#[test]
+fn test_gb18030() {
+    use crate::unicode_encoding::UnicodeEncoding;
+    let bytes = [0xD6, 0xD0, 0xCE, 0xC4, 0xA2, 0xE3, 0x81, 0x30, 0x81, 0x30, 0x84, 0x31, 0xA4, 0x39, 0x90, 0x30, 0x81, 0x30, 0xE3, 0x32, 0x9A, 0x35];
+    let text = "中文€\u{80}\u{FFFF}\u{10000}\u{10FFFF}";
+    assert_eq!(Gb18030::from_string(text).unwrap().data, bytes);
+    assert_eq!(Gb18030::from_bytes(&bytes, ()).unwrap().to_string().unwrap(), text);
+    assert_eq!(Gb18030::from_string("\u{E5E5}ḿ\u{E7C7}😎").unwrap().data, vec![0xA3, 0xA0, 0xA8, 0xBC, 0x81, 0x35, 0xF4, 0x37, 0x94, 0x39, 0xFE, 0x30]);
+    assert_eq!(Gb18030::bom(()), vec![0x84, 0x31, 0x95, 0x33]);
+    // As in GBK, 0x80 is read as the euro sign.
+    assert_eq!(Gb18030::from_bytes(&[0x80], ()).unwrap().to_string().unwrap(), "€");
+}
+
+#[test]
+fn test_gb18030_round_trip() {
+    use crate::unicode_encoding::UnicodeEncoding;
+    use crate::utf_32::Utf32;
+    let utf32 = Utf32{data: (0..0x110000).filter(|x| !(0xD800..=0xDFFF).contains(x)).collect()};
+    let gb18030 = Gb18030::try_from_utf_32(&utf32).unwrap();
+    assert!(gb18030.to_utf_32() == utf32);
+}
+
+#[test]
+fn test_invalid_gb18030() {
+    use crate::unicode_encoding::UnicodeEncoding;
+    // A broken four-byte sequence only takes its first byte.
+    let err = Gb18030::from_bytes(&[0x81, 0x30, 0x41, 0x30], ()).err().unwrap();
+    assert_eq!(err.kind, InvalidMultiByteSequence);
+    assert_eq!(err.code_units, vec![0x81]);
+    let err = Gb18030::from_bytes(&[0x84, 0x32, 0x81, 0x30], ()).err().unwrap();
+    assert_eq!(err.code_units, vec![0x84, 0x32, 0x81, 0x30]);
+    assert_eq!(Gb18030::from_bytes(&[0xE3, 0x32, 0x9A, 0x36], ()).err().unwrap().kind, InvalidMultiByteSequence);
+    assert_eq!(Gb18030::from_bytes(&[0x81, 0x30, 0x81], ()).err().unwrap().kind, MissingEncodedBytes);
+    assert_eq!(Gb18030::try_from_utf_32(&crate::utf_32::Utf32{data: vec![0xD800]}).err().unwrap().kind, UnmappableCodepoint);
+    let mut decoder = Gb18030::decoder(());
+    let decoded = crate::incremental::decode_byte_per_byte(decoder.as_mut(), &[0x41, 0x94, 0x39, 0xFE, 0x30, 0xD6, 0xD0]).unwrap();
+    assert_eq!(decoded.to_string().unwrap(), "A😎中");
+}
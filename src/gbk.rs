@@ -0,0 +1,84 @@
+/// The GBK module handles the legacy encoding of simplified Chinese extending
+/// GB 2312, used by Windows as its codepage 936. GBK is the part of GB18030
+/// made of one and two bytes.
+
+use crate::multi_byte::{self, MultiByteCodec, MultiByteEncoding};
+use crate::unicode_encoding::UnicodeEncodingError;
+use crate::cjk_indexes::GB18030;
+
+/// The GBK codec.
+pub struct GbkCodec;
+
+/// A wrapper for GBK encoded bytes.
+pub type Gbk = MultiByteEncoding<GbkCodec>;
+
+impl MultiByteCodec for GbkCodec {
+    const NAME: &'static str = "GBK";
+
+    fn decode_character(data: &[u8], start: usize) -> Result<(u32, usize), UnicodeEncodingError> {
+        let lead = data[start];
+        match lead {
+            0x00..=0x7F => return Ok((lead as u32, 1)),
+            0x80 => return Ok((EURO_SIGN, 1)),
+            0x81..=0xFE => {},
+            _ => return Err(multi_byte::invalid_byte(data, start)),
+        }
+        if start + 1 == data.len() {
+            return Err(multi_byte::missing_bytes(data, start));
+        }
+        let trail = data[start+1];
+        if !matches!(trail, 0x40..=0x7E | 0x80..=0xFE) {
+            return Err(multi_byte::invalid_pair(data, start));
+        }
+        let trail_offset = if trail < 0x7F {0x40} else {0x41};
+        return match GB18030.code_point((lead - 0x81) as usize * 190 + (trail - trail_offset) as usize) {
+            Some(glyph) => Ok((glyph, 2)),
+            None => Err(multi_byte::invalid_pair(data, start)),
+        };
+    }
+
+    fn encode_character(glyph: u32, bytes: &mut Vec<u8>) -> bool {
+        match glyph {
+            0x00..=0x7F => bytes.push(glyph as u8),
+            EURO_SIGN => bytes.push(0x80),
+            // The private use code-point of the ideographic space of GB 2312,
+            // which is not round-tripped by the browsers.
+            0xE5E5 => return false,
+            _ => return encode_two_bytes(glyph, bytes),
+        }
+        return true;
+    }
+}
+
+/* ---------------------------- Helper functions ---------------------------- */
+
+/// Writes the two bytes of a code-point, shared by GBK and GB18030. Returns
+/// false if the code-point has no two-byte character.
+pub(crate) fn encode_two_bytes(glyph: u32, bytes: &mut Vec<u8>) -> bool {
+    let pointer = match GB18030.pointer(glyph) {
+        Some(pointer) => pointer,
+        None => return false,
+    };
+    let trail = (pointer % 190) as u8;
+    bytes.push((pointer / 190) as u8 + 0x81);
+    bytes.push(trail + if trail < 0x3F {0x40} else {0x41});
+    return true;
+}
+
+/// The euro sign, encoded as 0x80 in GBK.
+const EURO_SIGN: u32 = 0x20AC;
+
+/* --------------------------------- Testing -------------------------------- */
+
+#[test]
+fn test_gbk() {
+    use crate::unicode_encoding::UnicodeEncoding;
+    use crate::unicode_encoding::UnicodeEncodingErrorKind::*;
+    assert_eq!(Gbk::from_bytes(&[0xD6, 0xD0, 0xCE, 0xC4, 0x80], ()).unwrap().to_string().unwrap(), "中文€");
+    assert_eq!(Gbk::from_string("中文€").unwrap().data, vec![0xD6, 0xD0, 0xCE, 0xC4, 0x80]);
+    assert_eq!(Gbk::from_string("丂").unwrap().data, vec![0x81, 0x40]);
+    // The four-byte sequences of GB18030 are not part of GBK.
+    assert_eq!(Gbk::from_bytes(&[0x81, 0x30, 0x81, 0x30], ()).err().unwrap().code_units, vec![0x81]);
+    assert_eq!(Gbk::from_string("😎").err().unwrap().kind, UnmappableCodepoint);
+    assert_eq!(Gbk::from_string("\u{E5E5}").err().unwrap().kind, UnmappableCodepoint);
+}
@@ -0,0 +1,538 @@
+/// The GSM 7-bit module handles the default alphabet of the SMS described in
+/// the 3GPP TS 23.038, where each character is a septet, and the characters
+/// of the extension table are preceded by an escape septet. The national
+/// language shift tables replace the default alphabet or its extension.
+
+use crate::unicode_encoding::UnicodeEncodingErrorKind::*;
+use crate::unicode_encoding::UnicodeEncodingError;
+use crate::unicode_encoding::UnicodeEncoding;
+use crate::utf_32::Utf32;
+use crate::utf_16::{self, Utf16};
+use crate::lossy::{self, DecodingPolicy};
+
+use std::marker::PhantomData;
+
+/// The code-point of each of the 128 septets of a table. The septets that are
+/// not used by the table are `UNMAPPED`.
+pub type Gsm7Table = [u16; 128];
+
+/// The value of the septets not used by a table in a `Gsm7Table`.
+pub const UNMAPPED: u16 = 0xFFFF;
+
+/// The tables used to read and write the septets, described by the national
+/// language identifiers of the 3GPP TS 23.038. The language 0 is the default
+/// alphabet or its extension table.
+pub trait Gsm7Tables {
+    /// The name of the encoding, used in error messages.
+    const NAME: &'static str;
+
+    /// The table of the septets, which is the default alphabet or a national
+    /// locking shift table.
+    const LOCKING_SHIFT: Gsm7Table;
+
+    /// The national language identifier of the locking shift table.
+    const LOCKING_SHIFT_LANGUAGE: u8;
+
+    /// The table of the septets following an escape septet, which is the
+    /// extension table or a national single shift table.
+    const SINGLE_SHIFT: Gsm7Table;
+
+    /// The national language identifier of the single shift table.
+    const SINGLE_SHIFT_LANGUAGE: u8;
+}
+
+/// Tells how the septets are stored in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gsm7Packing {
+    /// Each septet is a byte, as used by the AT commands and most SMS
+    /// gateways.
+    Unpacked,
+
+    /// The septets are packed, eight of them in seven bytes, as they are
+    /// sent over the air. When the last byte has seven spare bits, they are
+    /// filled with a CR, and a CR ending the data on an octet boundary is
+    /// doubled, both being removed when decoding.
+    Packed,
+}
+
+/// A wrapper for GSM 7-bit septets, one per byte. Like a codepage, only
+/// some characters can be encoded: `try_from_utf_32` fails on the other
+/// ones, telling that the message must be sent in UCS-2, while `from_utf_32`
+/// replaces them with `?`.
+pub struct Gsm7Encoding<T: Gsm7Tables> {
+    pub data: Vec<u8>,
+    tables: PhantomData<T>,
+}
+
+/// The GSM 7-bit default alphabet with its extension table.
+pub type Gsm7 = Gsm7Encoding<DefaultAlphabet>;
+
+impl<T: Gsm7Tables> Gsm7Encoding<T> {
+    /// Encodes UTF-32 data, replacing the code-points that have no septet
+    /// with the given septet. The number of replaced code-points is returned
+    /// along the data.
+    pub fn from_utf_32_lossy(data_utf_32: &Utf32, replacement: u8) -> (Self, usize) {
+        let mut data: Vec<u8> = Vec::new();
+        let mut count: usize = 0;
+        for glyph in &data_utf_32.data {
+            if !encode_glyph::<T>(*glyph, &mut data) {
+                data.push(replacement);
+                count += 1;
+            }
+        }
+        return (Gsm7Encoding{data: data, tables: PhantomData}, count);
+    }
+
+    /// The number of SMS needed to send the septets. A single SMS holds 160
+    /// septets, and the parts of a concatenated message 153 septets, as their
+    /// header takes room. The headers telling the national language tables
+    /// take room as well, and a character of the single shift table is
+    /// never split between two parts.
+    pub fn segment_count(&self) -> usize {
+        let languages = [T::LOCKING_SHIFT_LANGUAGE, T::SINGLE_SHIFT_LANGUAGE].iter().filter(|x| **x != 0).count();
+        let language_header = languages * LANGUAGE_HEADER_SIZE;
+        let single_capacity = if languages == 0 {SMS_SIZE * 8 / 7} else {(SMS_SIZE - 1 - language_header) * 8 / 7};
+        if self.data.len() <= single_capacity {
+            return 1;
+        }
+        let capacity = (SMS_SIZE - 1 - CONCATENATION_HEADER_SIZE - language_header) * 8 / 7;
+        let mut segments: usize = 1;
+        let mut used: usize = 0;
+        let mut index: usize = 0;
+        while index < self.data.len() {
+            let len = if self.data[index] == ESC {2} else {1};
+            if used + len > capacity {
+                segments += 1;
+                used = 0;
+            }
+            used += len;
+            index += len;
+        }
+        return segments;
+    }
+}
+
+impl<T: Gsm7Tables> UnicodeEncoding for Gsm7Encoding<T> {
+    const NAME: &'static str = T::NAME;
+    type Options = Gsm7Packing;
+
+    /// Convert UTF-32 data to septets. The code-points that have no septet
+    /// are replaced with `?`.
+    fn from_utf_32(data_utf_32: &Utf32) -> Self {
+        return Self::from_utf_32_lossy(data_utf_32, REPLACEMENT_SEPTET).0;
+    }
+
+    /// Convert UTF-32 data to septets. An error locating the first
+    /// code-point that has no septet is returned if there is one.
+    fn try_from_utf_32(data_utf_32: &Utf32) -> Result<Self, UnicodeEncodingError> {
+        let mut data: Vec<u8> = Vec::new();
+        for (i, glyph) in data_utf_32.data.iter().enumerate() {
+            if !encode_glyph::<T>(*glyph, &mut data) {
+                return Err(UnicodeEncodingError::new(UnmappableCodepoint).at_codepoint(i).with_code_units(&[*glyph]).in_encoding(Self::NAME));
+            }
+        }
+        return Ok(Gsm7Encoding{data: data, tables: PhantomData});
+    }
+
+    /// Convert septets to UTF-32.
+    fn try_to_utf_32(&self) -> Result<Utf32, UnicodeEncodingError> {
+        let mut data: Vec<u32> = Vec::new();
+        let mut index: usize = 0;
+        while index < self.data.len() {
+            let (glyph, len) = decode_septet::<T>(&self.data, index).map_err(|x| x.in_encoding(Self::NAME))?;
+            data.push(glyph);
+            index += len;
+        }
+        return Ok(Utf32{data: data});
+    }
+
+    /// Convert the instance of `Gsm7Encoding` to a vector of bytes, packing
+    /// the septets if asked to.
+    fn to_bytes(&self, packing: Gsm7Packing) -> Vec<u8> {
+        match packing {
+            Gsm7Packing::Unpacked => self.data.clone(),
+            Gsm7Packing::Packed => pack_septets(&self.data),
+        }
+    }
+
+    /// Consider a stream of bytes holding septets and turn it into a
+    /// `Gsm7Encoding` type. The septets are decoded to ensure they are valid.
+    /// The errors of packed data are located in the unpacked septets.
+    fn from_bytes_no_check(bytes: &[u8], packing: Gsm7Packing) -> Result<Self, UnicodeEncodingError> {
+        let ret = Gsm7Encoding{data: unpack_septets(bytes, packing), tables: PhantomData};
+        ret.try_to_utf_32()?;
+        return Ok(ret);
+    }
+
+    /// Decodes the data septet by septet, replacing the invalid ones. The
+    /// replacements that have no septet, such as U+FFFD, are stored as `?`,
+    /// use `decode_lossy` to keep them.
+    fn from_bytes_lossy(bytes: &[u8], packing: Gsm7Packing, policy: DecodingPolicy) -> Result<(Self, usize), UnicodeEncodingError> {
+        let (data, count) = Self::decode_lossy(bytes, packing, policy)?;
+        return Ok((Self::from_utf_32(&data), count));
+    }
+
+    /// Decodes the data septet by septet to UTF-32, replacing the invalid
+    /// ones. The escape septets followed by a septet that is not in the
+    /// single shift table are skipped, so the septet is read from the locking
+    /// shift table, as the standard advises.
+    fn decode_lossy(bytes: &[u8], packing: Gsm7Packing, policy: DecodingPolicy) -> Result<(Utf32, usize), UnicodeEncodingError> {
+        let septets = unpack_septets(bytes, packing);
+        let (data, count) = lossy::decode_glyphs_lossy(&septets, decode_septet::<T>, policy).map_err(|x| x.in_encoding(Self::NAME))?;
+        return Ok((Utf32{data: data}, count));
+    }
+
+    /// GSM 7-bit has no BOM.
+    fn bom(_packing: Gsm7Packing) -> Vec<u8> {
+        return Vec::new();
+    }
+}
+
+/// The number of SMS needed to send UTF-16 data in UCS-2, which is how the
+/// messages that can't be written in GSM 7-bit are sent. A single SMS holds
+/// 70 code units, and the parts of a concatenated message 67 code units. A
+/// surrogate pair is never split between two parts.
+pub fn ucs2_segment_count(data: &Utf16) -> usize {
+    if data.data.len() <= SMS_SIZE / 2 {
+        return 1;
+    }
+    let capacity = (SMS_SIZE - 1 - CONCATENATION_HEADER_SIZE) / 2;
+    let mut segments: usize = 1;
+    let mut used: usize = 0;
+    let mut index: usize = 0;
+    while index < data.data.len() {
+        let len = if utf_16::is_high_surrogate(data.data[index] as u32) && index + 1 < data.data.len() {2} else {1};
+        if used + len > capacity {
+            segments += 1;
+            used = 0;
+        }
+        used += len;
+        index += len;
+    }
+    return segments;
+}
+
+/* ------------------------ National language tables ------------------------ */
+
+/// The default alphabet with its extension table.
+pub struct DefaultAlphabet;
+
+impl Gsm7Tables for DefaultAlphabet {
+    const NAME: &'static str = "GSM-7";
+    const LOCKING_SHIFT: Gsm7Table = DEFAULT_ALPHABET;
+    const LOCKING_SHIFT_LANGUAGE: u8 = 0;
+    const SINGLE_SHIFT: Gsm7Table = DEFAULT_EXTENSION;
+    const SINGLE_SHIFT_LANGUAGE: u8 = 0;
+}
+
+/// The Turkish locking and single shift tables.
+pub struct Turkish;
+
+impl Gsm7Tables for Turkish {
+    const NAME: &'static str = "GSM-7 Turkish";
+    const LOCKING_SHIFT: Gsm7Table = TURKISH_LOCKING_SHIFT;
+    const LOCKING_SHIFT_LANGUAGE: u8 = 1;
+    const SINGLE_SHIFT: Gsm7Table = TURKISH_SINGLE_SHIFT;
+    const SINGLE_SHIFT_LANGUAGE: u8 = 1;
+}
+
+/// The default alphabet with the Spanish single shift table, as Spanish has
+/// no locking shift table.
+pub struct Spanish;
+
+impl Gsm7Tables for Spanish {
+    const NAME: &'static str = "GSM-7 Spanish";
+    const LOCKING_SHIFT: Gsm7Table = DEFAULT_ALPHABET;
+    const LOCKING_SHIFT_LANGUAGE: u8 = 0;
+    const SINGLE_SHIFT: Gsm7Table = SPANISH_SINGLE_SHIFT;
+    const SINGLE_SHIFT_LANGUAGE: u8 = 2;
+}
+
+/// The Portuguese locking and single shift tables.
+pub struct Portuguese;
+
+impl Gsm7Tables for Portuguese {
+    const NAME: &'static str = "GSM-7 Portuguese";
+    const LOCKING_SHIFT: Gsm7Table = PORTUGUESE_LOCKING_SHIFT;
+    const LOCKING_SHIFT_LANGUAGE: u8 = 3;
+    const SINGLE_SHIFT: Gsm7Table = PORTUGUESE_SINGLE_SHIFT;
+    const SINGLE_SHIFT_LANGUAGE: u8 = 3;
+}
+
+/* ---------------------------- Helper functions ---------------------------- */
+
+/// The septet written in place of the code-points that have no septet.
+const REPLACEMENT_SEPTET: u8 = b'?';
+
+/// The escape septet preceding the characters of the single shift table.
+const ESC: u8 = 0x1B;
+
+/// The carriage return used to fill the seven spare bits of packed data.
+const CR: u8 = 0x0D;
+
+/// The number of bytes of the user data of an SMS.
+const SMS_SIZE: usize = 140;
+
+/// The size of the information element of the user data header giving the
+/// part of a concatenated message.
+const CONCATENATION_HEADER_SIZE: usize = 5;
+
+/// The size of the information element of the user data header giving a
+/// national language table.
+const LANGUAGE_HEADER_SIZE: usize = 3;
+
+/// Decodes the septet at the given index, with the one following it if it
+/// is an escape septet.
+fn decode_septet<T: Gsm7Tables>(data: &[u8], start: usize) -> Result<(u32, usize), UnicodeEncodingError> {
+    let septet = data[start];
+    if septet >= 0x80 {
+        return Err(UnicodeEncodingError::new(UndefinedCodepageByte).at_byte(start).with_code_units(&data[start..start+1]));
+    }
+    if septet != ESC {
+        return Ok((T::LOCKING_SHIFT[septet as usize] as u32, 1));
+    }
+    match data.get(start+1) {
+        None => return Err(UnicodeEncodingError::new(MissingEncodedBytes).at_byte(start).with_code_units(&data[start..])),
+        Some(x) if *x < 0x80 && T::SINGLE_SHIFT[*x as usize] != UNMAPPED => return Ok((T::SINGLE_SHIFT[*x as usize] as u32, 2)),
+        Some(_) => return Err(UnicodeEncodingError::new(InvalidEscapeSequence).at_byte(start).with_code_units(&data[start..start+1])),
+    }
+}
+
+/// Writes the septets of a code-point. Returns false if it has none.
+fn encode_glyph<T: Gsm7Tables>(glyph: u32, septets: &mut Vec<u8>) -> bool {
+    if glyph >= UNMAPPED as u32 {
+        return false;
+    }
+    if let Some(septet) = T::LOCKING_SHIFT.iter().position(|x| *x as u32 == glyph) {
+        septets.push(septet as u8);
+        return true;
+    }
+    if let Some(septet) = T::SINGLE_SHIFT.iter().position(|x| *x as u32 == glyph) {
+        septets.extend_from_slice(&[ESC, septet as u8]);
+        return true;
+    }
+    return false;
+}
+
+/// Packs septets, eight of them in seven bytes, starting from the low bits.
+/// The spare bits of the last byte are filled with a CR, and a CR ending the
+/// data on an octet boundary is doubled so it is not taken for the padding,
+/// as in the section 6.1.2.3.1 of the TS 23.038.
+fn pack_septets(septets: &[u8]) -> Vec<u8> {
+    let mut septets = septets.to_vec();
+    if septets.len() % 8 == 7 || (septets.len() % 8 == 0 && septets.last() == Some(&CR)) {
+        septets.push(CR);
+    }
+    let mut ret: Vec<u8> = Vec::new();
+    let mut buffer: u32 = 0;
+    let mut bits: usize = 0;
+    for septet in septets {
+        buffer |= ((septet & 0x7F) as u32) << bits;
+        bits += 7;
+        while bits >= 8 {
+            ret.push(buffer as u8);
+            buffer >>= 8;
+            bits -= 8;
+        }
+    }
+    if bits > 0 {
+        ret.push(buffer as u8);
+    }
+    return ret;
+}
+
+/// Reads the septets of some bytes. When the last septet of packed data is
+/// in the seven spare bits of the last byte and is a CR, it is the padding
+/// and is removed. When the data ends with two CR just after an octet
+/// boundary, the second one is the doubling of `pack_septets` and is removed
+/// as well; the standard gives both forms the same meaning.
+fn unpack_septets(bytes: &[u8], packing: Gsm7Packing) -> Vec<u8> {
+    if packing == Gsm7Packing::Unpacked {
+        return bytes.to_vec();
+    }
+    let mut ret: Vec<u8> = Vec::new();
+    let mut buffer: u32 = 0;
+    let mut bits: usize = 0;
+    for byte in bytes {
+        buffer |= (*byte as u32) << bits;
+        bits += 8;
+        while bits >= 7 {
+            ret.push((buffer & 0x7F) as u8);
+            buffer >>= 7;
+            bits -= 7;
+        }
+    }
+    let padded = ret.len() % 8 == 0 && ret.last() == Some(&CR);
+    let doubled = ret.len() % 8 == 1 && ret.ends_with(&[CR, CR]);
+    if padded || doubled {
+        ret.pop();
+    }
+    return ret;
+}
+
+/// The GSM 7-bit default alphabet.
+const DEFAULT_ALPHABET: Gsm7Table = [
+    0x0040, 0x00A3, 0x0024, 0x00A5, 0x00E8, 0x00E9, 0x00F9, 0x00EC, 0x00F2, 0x00C7, 0x000A, 0x00D8, 0x00F8, 0x000D, 0x00C5, 0x00E5,
+    0x0394, 0x005F, 0x03A6, 0x0393, 0x039B, 0x03A9, 0x03A0, 0x03A8, 0x03A3, 0x0398, 0x039E, 0xFFFF, 0x00C6, 0x00E6, 0x00DF, 0x00C9,
+    0x0020, 0x0021, 0x0022, 0x0023, 0x00A4, 0x0025, 0x0026, 0x0027, 0x0028, 0x0029, 0x002A, 0x002B, 0x002C, 0x002D, 0x002E, 0x002F,
+    0x0030, 0x0031, 0x0032, 0x0033, 0x0034, 0x0035, 0x0036, 0x0037, 0x0038, 0x0039, 0x003A, 0x003B, 0x003C, 0x003D, 0x003E, 0x003F,
+    0x00A1, 0x0041, 0x0042, 0x0043, 0x0044, 0x0045, 0x0046, 0x0047, 0x0048, 0x0049, 0x004A, 0x004B, 0x004C, 0x004D, 0x004E, 0x004F,
+    0x0050, 0x0051, 0x0052, 0x0053, 0x0054, 0x0055, 0x0056, 0x0057, 0x0058, 0x0059, 0x005A, 0x00C4, 0x00D6, 0x00D1, 0x00DC, 0x00A7,
+    0x00BF, 0x0061, 0x0062, 0x0063, 0x0064, 0x0065, 0x0066, 0x0067, 0x0068, 0x0069, 0x006A, 0x006B, 0x006C, 0x006D, 0x006E, 0x006F,
+    0x0070, 0x0071, 0x0072, 0x0073, 0x0074, 0x0075, 0x0076, 0x0077, 0x0078, 0x0079, 0x007A, 0x00E4, 0x00F6, 0x00F1, 0x00FC, 0x00E0,
+];
+
+/// The extension table of the default alphabet.
+const DEFAULT_EXTENSION: Gsm7Table = [
+    0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x000C, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF,
+    0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x005E, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF,
+    0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x007B, 0x007D, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x005C,
+    0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x005B, 0x007E, 0x005D, 0xFFFF,
+    0x007C, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF,
+    0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF,
+    0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x20AC, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF,
+    0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF,
+];
+
+/// The Turkish locking shift table.
+const TURKISH_LOCKING_SHIFT: Gsm7Table = [
+    0x0040, 0x00A3, 0x0024, 0x00A5, 0x20AC, 0x00E9, 0x00F9, 0x0131, 0x00F2, 0x00C7, 0x000A, 0x011E, 0x011F, 0x000D, 0x00C5, 0x00E5,
+    0x0394, 0x005F, 0x03A6, 0x0393, 0x039B, 0x03A9, 0x03A0, 0x03A8, 0x03A3, 0x0398, 0x039E, 0xFFFF, 0x015E, 0x015F, 0x00DF, 0x00C9,
+    0x0020, 0x0021, 0x0022, 0x0023, 0x00A4, 0x0025, 0x0026, 0x0027, 0x0028, 0x0029, 0x002A, 0x002B, 0x002C, 0x002D, 0x002E, 0x002F,
+    0x0030, 0x0031, 0x0032, 0x0033, 0x0034, 0x0035, 0x0036, 0x0037, 0x0038, 0x0039, 0x003A, 0x003B, 0x003C, 0x003D, 0x003E, 0x003F,
+    0x0130, 0x0041, 0x0042, 0x0043, 0x0044, 0x0045, 0x0046, 0x0047, 0x0048, 0x0049, 0x004A, 0x004B, 0x004C, 0x004D, 0x004E, 0x004F,
+    0x0050, 0x0051, 0x0052, 0x0053, 0x0054, 0x0055, 0x0056, 0x0057, 0x0058, 0x0059, 0x005A, 0x00C4, 0x00D6, 0x00D1, 0x00DC, 0x00A7,
+    0x00E7, 0x0061, 0x0062, 0x0063, 0x0064, 0x0065, 0x0066, 0x0067, 0x0068, 0x0069, 0x006A, 0x006B, 0x006C, 0x006D, 0x006E, 0x006F,
+    0x0070, 0x0071, 0x0072, 0x0073, 0x0074, 0x0075, 0x0076, 0x0077, 0x0078, 0x0079, 0x007A, 0x00E4, 0x00F6, 0x00F1, 0x00FC, 0x00E0,
+];
+
+/// The Turkish single shift table.
+const TURKISH_SINGLE_SHIFT: Gsm7Table = [
+    0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x000C, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF,
+    0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x005E, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF,
+    0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x007B, 0x007D, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x005C,
+    0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x005B, 0x007E, 0x005D, 0xFFFF,
+    0x007C, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x011E, 0xFFFF, 0x0130, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF,
+    0xFFFF, 0xFFFF, 0xFFFF, 0x015E, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF,
+    0xFFFF, 0xFFFF, 0xFFFF, 0x00E7, 0xFFFF, 0x20AC, 0xFFFF, 0x011F, 0xFFFF, 0x0131, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF,
+    0xFFFF, 0xFFFF, 0xFFFF, 0x015F, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF,
+];
+
+/// The Spanish single shift table. Spanish has no locking shift table.
+const SPANISH_SINGLE_SHIFT: Gsm7Table = [
+    0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x00E7, 0x000C, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF,
+    0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x005E, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF,
+    0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x007B, 0x007D, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x005C,
+    0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x005B, 0x007E, 0x005D, 0xFFFF,
+    0x007C, 0x00C1, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x00CD, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x00D3,
+    0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x00DA, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF,
+    0xFFFF, 0x00E1, 0xFFFF, 0xFFFF, 0xFFFF, 0x20AC, 0xFFFF, 0xFFFF, 0xFFFF, 0x00ED, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x00F3,
+    0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x00FA, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF,
+];
+
+/// The Portuguese locking shift table.
+const PORTUGUESE_LOCKING_SHIFT: Gsm7Table = [
+    0x0040, 0x00A3, 0x0024, 0x00A5, 0x00EA, 0x00E9, 0x00FA, 0x00ED, 0x00F3, 0x00E7, 0x000A, 0x00D4, 0x00F4, 0x000D, 0x00C1, 0x00E1,
+    0x0394, 0x005F, 0x00AA, 0x00C7, 0x00C0, 0x221E, 0x005E, 0x005C, 0x20AC, 0x00D3, 0x007C, 0xFFFF, 0x00C2, 0x00E2, 0x00CA, 0x00C9,
+    0x0020, 0x0021, 0x0022, 0x0023, 0x00BA, 0x0025, 0x0026, 0x0027, 0x0028, 0x0029, 0x002A, 0x002B, 0x002C, 0x002D, 0x002E, 0x002F,
+    0x0030, 0x0031, 0x0032, 0x0033, 0x0034, 0x0035, 0x0036, 0x0037, 0x0038, 0x0039, 0x003A, 0x003B, 0x003C, 0x003D, 0x003E, 0x003F,
+    0x00CD, 0x0041, 0x0042, 0x0043, 0x0044, 0x0045, 0x0046, 0x0047, 0x0048, 0x0049, 0x004A, 0x004B, 0x004C, 0x004D, 0x004E, 0x004F,
+    0x0050, 0x0051, 0x0052, 0x0053, 0x0054, 0x0055, 0x0056, 0x0057, 0x0058, 0x0059, 0x005A, 0x00C3, 0x00D5, 0x00DA, 0x00DC, 0x00A7,
+    0x007E, 0x0061, 0x0062, 0x0063, 0x0064, 0x0065, 0x0066, 0x0067, 0x0068, 0x0069, 0x006A, 0x006B, 0x006C, 0x006D, 0x006E, 0x006F,
+    0x0070, 0x0071, 0x0072, 0x0073, 0x0074, 0x0075, 0x0076, 0x0077, 0x0078, 0x0079, 0x007A, 0x00E3, 0x00F5, 0x0060, 0x00FC, 0x00E0,
+];
+
+/// The Portuguese single shift table.
+const PORTUGUESE_SINGLE_SHIFT: Gsm7Table = [
+    0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x00EA, 0xFFFF, 0xFFFF, 0xFFFF, 0x00E7, 0x000C, 0x00D4, 0x00F4, 0xFFFF, 0x00C1, 0x00E1,
+    0xFFFF, 0xFFFF, 0x03A6, 0x0393, 0x005E, 0x03A9, 0x03A0, 0x03A8, 0x03A3, 0x0398, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x00CA,
+    0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x007B, 0x007D, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x005C,
+    0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x005B, 0x007E, 0x005D, 0xFFFF,
+    0x007C, 0x00C0, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x00CD, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x00D3,
+    0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x00DA, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x00C3, 0x00D5, 0xFFFF, 0xFFFF, 0xFFFF,
+    0xFFFF, 0x00C2, 0xFFFF, 0xFFFF, 0xFFFF, 0x20AC, 0xFFFF, 0xFFFF, 0xFFFF, 0x00ED, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x00F3,
+    0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x00FA, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x00E3, 0x00F5, 0xFFFF, 0xFFFF, 0x00E2,
+];
+
+/* --------------------------------- Testing -------------------------------- */
+
+#[test]
+fn test_gsm_7() {
+    let text = "Hello @£$ ΔΣ {é}";
+    let gsm = Gsm7::from_string(text).unwrap();
+    assert_eq!(gsm.data, vec![0x48, 0x65, 0x6C, 0x6C, 0x6F, 0x20, 0x00, 0x01, 0x02, 0x20, 0x10, 0x18, 0x20, 0x1B, 0x28, 0x05, 0x1B, 0x29]);
+    assert_eq!(Gsm7::from_bytes(&gsm.data, Gsm7Packing::Unpacked).unwrap().to_string().unwrap(), text);
+    assert_eq!(Gsm7::from_string("a€\u{C}").unwrap().data, vec![0x61, 0x1B, 0x65, 0x1B, 0x0A]);
+    let err = Gsm7::from_string("été ☃").err().unwrap();
+    assert_eq!(err.kind, UnmappableCodepoint);
+    assert_eq!(err.position, Some(crate::unicode_encoding::ErrorPosition::Codepoint(4)));
+    assert_eq!(Gsm7::from_utf_32(&Utf32::from_string("a☃").unwrap()).data, b"a?");
+    assert!(Gsm7::bom(Gsm7Packing::Unpacked).is_empty());
+}
+
+#[test]
+fn test_gsm_7_packing() {
+    let gsm = Gsm7::from_string("hellohello").unwrap();
+    let packed = gsm.to_bytes(Gsm7Packing::Packed);
+    assert_eq!(packed, vec![0xE8, 0x32, 0x9B, 0xFD, 0x46, 0x97, 0xD9, 0xEC, 0x37]);
+    assert_eq!(Gsm7::from_bytes(&packed, Gsm7Packing::Packed).unwrap().to_string().unwrap(), "hellohello");
+    // The seven spare bits are filled with a CR, which is not read back.
+    let packed = Gsm7::from_string("1234567").unwrap().to_bytes(Gsm7Packing::Packed);
+    assert_eq!(packed, vec![0x31, 0xD9, 0x8C, 0x56, 0xB3, 0xDD, 0x1A]);
+    assert_eq!(Gsm7::from_bytes(&packed, Gsm7Packing::Packed).unwrap().to_string().unwrap(), "1234567");
+    let packed = Gsm7::from_string("12345678").unwrap().to_bytes(Gsm7Packing::Packed);
+    assert_eq!(Gsm7::from_bytes(&packed, Gsm7Packing::Packed).unwrap().to_string().unwrap(), "12345678");
+    // A CR ending the data on an octet boundary is doubled to be kept.
+    let packed = Gsm7::from_string("1234567\r").unwrap().to_bytes(Gsm7Packing::Packed);
+    assert_eq!(packed.len(), 8);
+    assert_eq!(Gsm7::from_bytes(&packed, Gsm7Packing::Packed).unwrap().to_string().unwrap(), "1234567\r");
+    let packed = Gsm7::from_string("123456\r").unwrap().to_bytes(Gsm7Packing::Packed);
+    assert_eq!(Gsm7::from_bytes(&packed, Gsm7Packing::Packed).unwrap().to_string().unwrap(), "123456\r");
+}
+
+#[test]
+fn test_invalid_gsm_7() {
+    assert_eq!(Gsm7::from_bytes(&[0x41, 0x80], Gsm7Packing::Unpacked).err().unwrap().kind, UndefinedCodepageByte);
+    assert_eq!(Gsm7::from_bytes(&[0x41, 0x1B], Gsm7Packing::Unpacked).err().unwrap().kind, MissingEncodedBytes);
+    assert_eq!(Gsm7::from_bytes(&[0x1B, 0x41], Gsm7Packing::Unpacked).err().unwrap().kind, InvalidEscapeSequence);
+    // The septet following an unknown escape is read from the default
+    // alphabet.
+    let (decoded, count) = Gsm7::from_bytes_lossy(&[0x1B, 0x41, 0x1B, 0x65], Gsm7Packing::Unpacked, DecodingPolicy::Skip).unwrap();
+    assert_eq!(count, 1);
+    assert_eq!(decoded.to_string().unwrap(), "A€");
+    let (decoded, count) = Gsm7::decode_lossy(&[0x41, 0x80], Gsm7Packing::Unpacked, DecodingPolicy::Replace).unwrap();
+    assert_eq!(count, 1);
+    assert_eq!(decoded.data, vec![0x41, 0xFFFD]);
+}
+
+#[test]
+fn test_gsm_7_national_tables() {
+    assert_eq!(Gsm7Encoding::<Turkish>::from_string("İş €{").unwrap().data, vec![0x40, 0x1D, 0x20, 0x04, 0x1B, 0x28]);
+    assert_eq!(Gsm7Encoding::<Turkish>::from_bytes(&[0x07, 0x0B, 0x60], Gsm7Packing::Unpacked).unwrap().to_string().unwrap(), "ıĞç");
+    assert_eq!(Gsm7Encoding::<Spanish>::from_string("Él está").unwrap().data, vec![0x1F, 0x6C, 0x20, 0x65, 0x73, 0x74, 0x1B, 0x61]);
+    assert_eq!(Gsm7Encoding::<Portuguese>::from_string("Ação").unwrap().data, vec![0x41, 0x09, 0x7B, 0x6F]);
+    assert!(Gsm7::from_string("ş").is_err());
+}
+
+#[test]
+fn test_sms_segments() {
+    assert_eq!(Gsm7::from_string("").unwrap().segment_count(), 1);
+    assert_eq!(Gsm7::from_string(&"a".repeat(160)).unwrap().segment_count(), 1);
+    assert_eq!(Gsm7::from_string(&"a".repeat(161)).unwrap().segment_count(), 2);
+    assert_eq!(Gsm7::from_string(&"a".repeat(306)).unwrap().segment_count(), 2);
+    assert_eq!(Gsm7::from_string(&"a".repeat(307)).unwrap().segment_count(), 3);
+    // The euro sign takes two septets, which are kept in the same part.
+    assert_eq!(Gsm7::from_string(&"€".repeat(80)).unwrap().segment_count(), 1);
+    assert_eq!(Gsm7::from_string(&format!("{}€{}", "a".repeat(152), "a".repeat(10))).unwrap().segment_count(), 2);
+    assert_eq!(Gsm7::from_string(&format!("{}€{}", "a".repeat(152), "a".repeat(152))).unwrap().segment_count(), 3);
+    // The headers of the national language tables take room.
+    assert_eq!(Gsm7Encoding::<Turkish>::from_string(&"a".repeat(152)).unwrap().segment_count(), 1);
+    assert_eq!(Gsm7Encoding::<Turkish>::from_string(&"a".repeat(153)).unwrap().segment_count(), 2);
+    assert_eq!(Gsm7Encoding::<Spanish>::from_string(&"a".repeat(155)).unwrap().segment_count(), 1);
+    assert_eq!(Gsm7Encoding::<Spanish>::from_string(&"a".repeat(299)).unwrap().segment_count(), 3);
+    assert_eq!(ucs2_segment_count(&Utf16::from_string(&"☃".repeat(70)).unwrap()), 1);
+    assert_eq!(ucs2_segment_count(&Utf16::from_string(&"☃".repeat(71)).unwrap()), 2);
+    // A surrogate pair is never split.
+    assert_eq!(ucs2_segment_count(&Utf16::from_string(&format!("{}😎{}", "☃".repeat(66), "☃".repeat(66))).unwrap()), 3);
+    assert_eq!(ucs2_segment_count(&Utf16::from_string(&format!("{}😎{}", "☃".repeat(65), "☃".repeat(66))).unwrap()), 2);
+}
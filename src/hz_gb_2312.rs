@@ -0,0 +1,114 @@
+/// The HZ-GB-2312 module handles the encoding of simplified Chinese described
+/// in the RFC 1843, used in the mails and the news, where `~{` and `~}`
+/// switch between ASCII and GB 2312.
+
+use crate::iso_2022::{self, Iso2022Codec, Iso2022Encoding};
+use crate::unicode_encoding::UnicodeEncodingErrorKind::*;
+use crate::unicode_encoding::UnicodeEncodingError;
+use crate::cjk_indexes::GB18030;
+
+/// The HZ-GB-2312 codec. The characters of GB 2312 are read and written from
+/// the index of GB18030, which contains them.
+pub struct HzGb2312Codec;
+
+/// A wrapper for HZ-GB-2312 encoded bytes.
+pub type HzGb2312 = Iso2022Encoding<HzGb2312Codec>;
+
+impl Iso2022Codec for HzGb2312Codec {
+    const NAME: &'static str = "HZ-GB-2312";
+    /// Tells if GB 2312 is in use.
+    type DecoderState = bool;
+    /// Tells if GB 2312 is in use.
+    type EncoderState = bool;
+
+    fn decode_character(data: &[u8], start: usize, gb: &mut bool) -> Result<(Option<u32>, usize), UnicodeEncodingError> {
+        let byte = data[start];
+        match byte {
+            TILDE => {
+                let sequences: &[&[u8]] = if *gb {&[b"}", b"\n"]} else {&[b"~", b"{", b"\n"]};
+                let glyph = match sequences[iso_2022::read_escape_sequence(data, start, sequences)?] {
+                    b"~" => Some(TILDE as u32),
+                    // A tilde at the end of a line continues it.
+                    b"\n" => None,
+                    _ => {
+                        *gb = !*gb;
+                        None
+                    },
+                };
+                return Ok((glyph, 2));
+            },
+            // Each line starts in ASCII.
+            b'\n' => {
+                *gb = false;
+                return Ok((Some(byte as u32), 1));
+            },
+            0x00..=0x20 | 0x7F => return Ok((Some(byte as u32), 1)),
+            0x21..=0x7E if !*gb => return Ok((Some(byte as u32), 1)),
+            0x21..=0x7E => return iso_2022::read_94x94_character(data, start, |row, cell| GB18030.code_point(gb_pointer(row, cell))),
+            _ => return Err(UnicodeEncodingError::new(InvalidMultiByteSequence).at_byte(start).with_code_units(&data[start..start+1])),
+        }
+    }
+
+    fn encode_character(glyph: u32, gb: &mut bool, bytes: &mut Vec<u8>) -> bool {
+        if glyph < 0x80 {
+            Self::finish(gb, bytes);
+            if glyph == TILDE as u32 {
+                bytes.push(TILDE);
+            }
+            bytes.push(glyph as u8);
+            return true;
+        }
+        // Only the rows of GB 2312 can be written, without the private use
+        // characters GBK put in its free cells.
+        let pointer = match GB18030.pointer(glyph) {
+            Some(pointer) if !(0xE000..=0xF8FF).contains(&glyph) => pointer,
+            _ => return false,
+        };
+        let (lead, trail) = ((pointer / 190) as u8 + 0x81, (pointer % 190) as u8 + 0x41);
+        if !(0xA1..=0xF7).contains(&lead) || trail < 0xA1 {
+            return false;
+        }
+        if !*gb {
+            bytes.extend_from_slice(b"~{");
+            *gb = true;
+        }
+        bytes.extend_from_slice(&[lead - 0x80, trail - 0x80]);
+        return true;
+    }
+
+    fn finish(gb: &mut bool, bytes: &mut Vec<u8>) {
+        if *gb {
+            bytes.extend_from_slice(b"~}");
+            *gb = false;
+        }
+    }
+}
+
+/* ---------------------------- Helper functions ---------------------------- */
+
+/// The tilde starting the escape sequences.
+const TILDE: u8 = b'~';
+
+/// The pointer in the GB18030 index of a character of GB 2312.
+fn gb_pointer(row: usize, cell: usize) -> usize {
+    return (row + 0xA1 - 0x81) * 190 + cell + 0xA1 - 0x41;
+}
+
+/* --------------------------------- Testing -------------------------------- */
+
+#[test]
+fn test_hz_gb_2312() {
+    use crate::unicode_encoding::UnicodeEncoding;
+    let bytes = b"~{VPND~}~~a";
+    assert_eq!(HzGb2312::from_bytes(bytes, ()).unwrap().to_string().unwrap(), "中文~a");
+    assert_eq!(HzGb2312::from_string("中文~a").unwrap().data, bytes);
+    assert_eq!(HzGb2312::from_string("中\n文").unwrap().data, b"~{VP~}\n~{ND~}");
+    // The lines can be continued, and they start in ASCII.
+    assert_eq!(HzGb2312::from_bytes(b"~{VP~\nND\nVP", ()).unwrap().to_string().unwrap(), "中文\nVP");
+    // The characters added by GBK can't be written.
+    assert_eq!(HzGb2312::from_string("丂").err().unwrap().kind, UnmappableCodepoint);
+    assert_eq!(HzGb2312::from_bytes(b"~}", ()).err().unwrap().kind, InvalidEscapeSequence);
+    assert_eq!(HzGb2312::from_bytes(b"~{~~", ()).err().unwrap().kind, InvalidEscapeSequence);
+    assert_eq!(HzGb2312::from_bytes(b"a~", ()).err().unwrap().kind, MissingEncodedBytes);
+    assert_eq!(HzGb2312::from_bytes(b"~{V", ()).err().unwrap().kind, MissingEncodedBytes);
+}
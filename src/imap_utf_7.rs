@@ -0,0 +1,111 @@
+/// The IMAP UTF-7 module handles the modified UTF-7 used for the mailbox
+/// names of IMAP, described in the section 5.1.3 of the RFC 3501.
+
+use crate::unicode_encoding::UnicodeEncodingError;
+use crate::unicode_encoding::UnicodeEncoding;
+use crate::utf_32::Utf32;
+use crate::utf_7::{self, Utf7Variant, Utf7DirectCharacters, Utf7Decoder, Utf7Encoder};
+use crate::incremental::{Decoder, Encoder};
+use crate::lossy::DecodingPolicy;
+
+/// A wrapper for IMAP mailbox names encoded in modified UTF-7. It differs
+/// from UTF-7 as `&` is used to start the base64 sequences instead of `+`,
+/// `,` is used instead of `/` in base64, the base64 sequences always end with
+/// `-`, and all the printable ASCII characters are written directly.
+pub struct ImapUtf7 {
+    pub data: Vec<u8>
+}
+
+impl UnicodeEncoding for ImapUtf7 {
+    const NAME: &'static str = "UTF-7-IMAP";
+    type Options = ();
+
+    /// Convert UTF-32 data to modified UTF-7.
+    fn from_utf_32(data_utf_32: &Utf32) -> Self {
+        return ImapUtf7{data: utf_7::encode_utf_7(&data_utf_32.data, Utf7Variant::Imap, Utf7DirectCharacters::SetO)};
+    }
+
+    /// Convert modified UTF-7 data to UTF-32.
+    fn try_to_utf_32(&self) -> Result<Utf32, UnicodeEncodingError> {
+        return utf_7::decode_utf_7(&self.data, Utf7Variant::Imap);
+    }
+
+    /// Convert the instance of `ImapUtf7` to a vector of bytes.
+    /// No transformation is needed.
+    fn to_bytes(&self, _options: ()) -> Vec<u8> {
+        return self.data.clone();
+    }
+
+    /// Consider a stream of modified UTF-7 encoded bytes and turn it into an
+    /// `ImapUtf7` type. The bytes are decoded to ensure they are valid.
+    fn from_bytes_no_check(bytes: &[u8], _options: ()) -> Result<Self, UnicodeEncodingError> {
+        let ret = ImapUtf7{data: bytes.to_vec()};
+        ret.try_to_utf_32()?;
+        return Ok(ret);
+    }
+
+    /// Decodes modified UTF-7 byte by byte, handling the invalid bytes and
+    /// base64 sequences with the policy, as in UTF-7.
+    fn from_bytes_lossy(bytes: &[u8], _options: (), policy: DecodingPolicy) -> Result<(Self, usize), UnicodeEncodingError> {
+        let (data, count) = utf_7::decode_utf_7_lossy(bytes, Utf7Variant::Imap, policy)?;
+        return Ok((Self::from_utf_32(&data), count));
+    }
+
+    fn decoder(_options: ()) -> Box<dyn Decoder> {
+        return Box::new(Utf7Decoder::new(Utf7Variant::Imap));
+    }
+
+    fn encoder(_options: ()) -> Box<dyn Encoder> {
+        return Box::new(Utf7Encoder::new(Utf7Variant::Imap, Utf7DirectCharacters::SetO));
+    }
+}
+
+/* --------------------------------- Testing -------------------------------- */
+
+#[test]
+fn test_imap_utf_7_rfc_example() {
+    // Example from the RFC 3501.
+    let name = "~peter/mail/台北/日本語";
+    let imap = ImapUtf7::from_string(name).unwrap();
+    assert_eq!(imap.data, b"~peter/mail/&U,BTFw-/&ZeVnLIqe-");
+    let imap = ImapUtf7::from_bytes(b"~peter/mail/&U,BTFw-/&ZeVnLIqe-", ()).unwrap();
+    assert_eq!(imap.to_string().unwrap(), name);
+}
+
+#[test]
+fn test_imap_utf_7_ampersand() {
+    assert_eq!(ImapUtf7::from_string("Tom & Jerry").unwrap().data, b"Tom &- Jerry");
+    assert_eq!(ImapUtf7::from_string("é&").unwrap().data, b"&AOk-&-");
+    assert_eq!(ImapUtf7::from_bytes(b"&AOk-&-", ()).unwrap().to_string().unwrap(), "é&");
+}
+
+#[test]
+fn test_invalid_imap_utf_7() {
+    use crate::unicode_encoding::UnicodeEncodingErrorKind::*;
+    // The base64 sequences must end with `-`.
+    assert_eq!(ImapUtf7::from_bytes(b"&AOk.", ()).err().unwrap().kind, InvalidUtf7Byte);
+    assert_eq!(ImapUtf7::from_bytes(b"&AOk", ()).err().unwrap().kind, MissingEncodedBytes);
+    // Control characters are not allowed.
+    assert_eq!(ImapUtf7::from_bytes(b"a\tb", ()).err().unwrap().kind, InvalidUtf7Byte);
+    // The base64 alphabet uses `,` instead of `/`.
+    assert_eq!(ImapUtf7::from_bytes(b"&U/BTFw-", ()).err().unwrap().kind, InvalidUtf7Byte);
+}
+
+#[test]
+fn test_imap_utf_7_lossy() {
+    let (decoded, count) = ImapUtf7::from_bytes_lossy(b"a\tb&AOk\tA6Q-c", (), DecodingPolicy::Replace).unwrap();
+    assert_eq!(count, 2);
+    assert_eq!(decoded.to_string().unwrap(), "a\u{FFFD}b\u{E9}\u{FFFD}\u{E9}c");
+    // The base64 sequence left open at the end is reported once.
+    let (decoded, count) = ImapUtf7::from_bytes_lossy(b"&AOk.", (), DecodingPolicy::Replace).unwrap();
+    assert_eq!(count, 2);
+    assert_eq!(decoded.to_string().unwrap(), "\u{E9}\u{FFFD}\u{FFFD}");
+}
+
+#[test]
+fn test_imap_utf_7_incremental() {
+    let bytes = b"~peter/mail/&U,BTFw-/&ZeVnLIqe-";
+    let mut decoder = ImapUtf7::decoder(());
+    let decoded = crate::incremental::decode_byte_per_byte(decoder.as_mut(), bytes).unwrap();
+    assert_eq!(decoded.to_string().unwrap(), "~peter/mail/台北/日本語");
+}
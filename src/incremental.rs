@@ -0,0 +1,160 @@
+/// The `incremental` module contains the `Decoder` and `Encoder` traits used
+/// to convert data chunk by chunk, as it is read from or written to a stream.
+
+use crate::unicode_encoding::UnicodeEncodingError;
+use crate::unicode_encoding::UnicodeEncoding;
+use crate::utf_16::compatible_codepoints;
+use crate::utf_32::Utf32;
+
+use std::marker::PhantomData;
+
+/// A `Decoder` turns chunks of encoded bytes into UTF-32. A glyph can be split
+/// across two chunks, the bytes of an incomplete glyph are kept by the decoder
+/// until the next call.
+pub trait Decoder {
+    /// Decodes a chunk of bytes and returns all the glyphs that could be
+    /// completed with it. An error is only returned if the data is invalid,
+    /// not if more bytes are needed to finish a glyph.
+    fn decode(&mut self, bytes: &[u8]) -> Result<Utf32, UnicodeEncodingError>;
+
+    /// Signals the end of the stream. The remaining glyphs are returned. If
+    /// some bytes of an unfinished glyph are still pending, an error is
+    /// returned.
+    fn finish(&mut self) -> Result<Utf32, UnicodeEncodingError>;
+}
+
+/// An `Encoder` turns chunks of UTF-32 into encoded bytes. It keeps track of
+/// the previous glyphs so that the data can be checked as a whole.
+pub trait Encoder {
+    /// Encodes a chunk of glyphs and returns the resulting bytes.
+    fn encode(&mut self, data: &Utf32) -> Result<Vec<u8>, UnicodeEncodingError>;
+
+    /// Signals the end of the stream and returns the last bytes, if any.
+    fn finish(&mut self) -> Result<Vec<u8>, UnicodeEncodingError>;
+}
+
+/* ---------------------------- Generic fallbacks --------------------------- */
+
+/// A decoder for encodings that can't be decoded incrementally. All the bytes
+/// are kept until the end of the stream and are decoded with `from_bytes`.
+pub struct BufferedDecoder<T: UnicodeEncoding> {
+    options: T::Options,
+    pending: Vec<u8>,
+    encoding: PhantomData<T>,
+}
+
+impl<T: UnicodeEncoding> BufferedDecoder<T> {
+    pub fn new(options: T::Options) -> Self {
+        return BufferedDecoder{options: options, pending: Vec::new(), encoding: PhantomData};
+    }
+}
+
+impl<T: UnicodeEncoding> Decoder for BufferedDecoder<T> {
+    fn decode(&mut self, bytes: &[u8]) -> Result<Utf32, UnicodeEncodingError> {
+        self.pending.extend_from_slice(bytes);
+        return Ok(Utf32{data: Vec::new()});
+    }
+
+    fn finish(&mut self) -> Result<Utf32, UnicodeEncodingError> {
+        let bytes = std::mem::take(&mut self.pending);
+        let ret = T::from_bytes(&bytes, self.options)?;
+        return ret.try_to_utf_32();
+    }
+}
+
+/// An encoder for encodings that can't be encoded incrementally. All the
+/// glyphs are kept until the end of the stream and are then encoded at once.
+pub struct BufferedEncoder<T: UnicodeEncoding> {
+    options: T::Options,
+    pending: Vec<u32>,
+    checker: SanityChecker,
+    encoding: PhantomData<T>,
+}
+
+impl<T: UnicodeEncoding> BufferedEncoder<T> {
+    pub fn new(options: T::Options) -> Self {
+        return BufferedEncoder{options: options, pending: Vec::new(), checker: SanityChecker::new(), encoding: PhantomData};
+    }
+}
+
+impl<T: UnicodeEncoding> Encoder for BufferedEncoder<T> {
+    fn encode(&mut self, data: &Utf32) -> Result<Vec<u8>, UnicodeEncodingError> {
+        for glyph in &data.data {
+            self.checker.push(*glyph)?;
+            self.pending.push(*glyph);
+        }
+        return Ok(Vec::new());
+    }
+
+    fn finish(&mut self) -> Result<Vec<u8>, UnicodeEncodingError> {
+        self.checker.finish()?;
+        let utf32 = Utf32{data: std::mem::take(&mut self.pending)};
+        return Ok(T::from_utf_32(&utf32).to_bytes(self.options));
+    }
+}
+
+/* ---------------------------- Helper functions ---------------------------- */
+
+/// Performs incrementally the same checks as `Utf32::check_sanity_utf32`,
+/// the last glyph seen is kept to check it against the next one.
+pub(crate) struct SanityChecker {
+    previous: Option<u32>,
+    /// Number of glyphs checked, used to locate errors.
+    count: usize,
+}
+
+impl SanityChecker {
+    pub(crate) fn new() -> Self {
+        return SanityChecker{previous: None, count: 0};
+    }
+
+    /// Checks a new glyph against the previous one.
+    pub(crate) fn push(&mut self, glyph: u32) -> Result<(), UnicodeEncodingError> {
+        if let Some(previous) = self.previous {
+            compatible_codepoints(previous, glyph).map_err(|x| x.shifted(self.count - 1))?;
+        }
+        // Using 0 as next glyph only checks the number of bits of the glyph.
+        compatible_codepoints(glyph, 0).map_err(|x| x.shifted(self.count))?;
+        self.previous = Some(glyph);
+        self.count += 1;
+        return Ok(());
+    }
+
+    /// Checks a whole chunk of glyphs.
+    pub(crate) fn push_all(&mut self, data: &Utf32) -> Result<(), UnicodeEncodingError> {
+        for glyph in &data.data {
+            self.push(*glyph)?;
+        }
+        return Ok(());
+    }
+
+    /// Signals the end of the stream.
+    pub(crate) fn finish(&mut self) -> Result<(), UnicodeEncodingError> {
+        self.previous = None;
+        return Ok(());
+    }
+}
+
+/* --------------------------------- Testing -------------------------------- */
+
+#[cfg(test)]
+/// Decodes bytes fed one at a time to ensure that glyphs split across chunks
+/// are handled.
+pub(crate) fn decode_byte_per_byte(decoder: &mut dyn Decoder, bytes: &[u8]) -> Result<Utf32, UnicodeEncodingError> {
+    let mut ret: Vec<u32> = Vec::new();
+    for byte in bytes {
+        ret.extend(decoder.decode(&[*byte])?.data);
+    }
+    ret.extend(decoder.finish()?.data);
+    return Ok(Utf32{data: ret});
+}
+
+#[test]
+fn test_sanity_checker() {
+    let mut checker = SanityChecker::new();
+    checker.push(0x41).unwrap();
+    checker.push(0xD800).unwrap();
+    assert!(checker.push(0xDC00).is_err());
+    let mut checker = SanityChecker::new();
+    assert!(checker.push(0x200000).is_err());
+}
@@ -0,0 +1,336 @@
+/// The `iso_2022` module handles the stateful encodings of East Asia, such as
+/// ISO-2022-JP, where escape sequences switch between character sets. The
+/// meaning of a byte depends on the last escape sequence, so the data is
+/// read and written with a state kept from one character to the next.
+
+use crate::unicode_encoding::UnicodeEncodingErrorKind::*;
+use crate::unicode_encoding::UnicodeEncodingError;
+use crate::unicode_encoding::UnicodeEncoding;
+use crate::utf_32::Utf32;
+use crate::incremental::{Decoder, Encoder, SanityChecker};
+use crate::lossy::DecodingPolicy;
+
+use std::marker::PhantomData;
+
+/// A stateful encoding, described by the functions reading and writing a
+/// character with the state of the decoder or of the encoder. The encodings
+/// shipped with this crate are `Iso2022Jp`, `Iso2022Kr` and `HzGb2312`.
+pub trait Iso2022Codec {
+    /// The name of the encoding, used in error messages.
+    const NAME: &'static str;
+
+    /// The state of a decoder, such as the character set in use. The default
+    /// value is the state at the beginning of the data.
+    type DecoderState: Copy + Default;
+
+    /// The state of an encoder. The default value is the state at the
+    /// beginning of the data.
+    type EncoderState: Copy + Default;
+
+    /// Decodes the character or the escape sequence starting at the given
+    /// index of some bytes, returning the code-point, if any, and the length.
+    /// The state is only changed when no error is returned. The errors must
+    /// be located in the bytes and their code units must be the invalid
+    /// sequence to skip. A sequence cut by the end of the bytes is reported
+    /// with a `MissingEncodedBytes` error.
+    fn decode_character(data: &[u8], start: usize, state: &mut Self::DecoderState) -> Result<(Option<u32>, usize), UnicodeEncodingError>;
+
+    /// Writes the bytes of a code-point, preceded by the escape sequence
+    /// needed to reach its character set. Returns false, without writing
+    /// anything or changing the state, if the code-point can't be encoded.
+    fn encode_character(glyph: u32, state: &mut Self::EncoderState, bytes: &mut Vec<u8>) -> bool;
+
+    /// Writes the escape sequence going back to the initial state, as the
+    /// data must end in ASCII.
+    fn finish(state: &mut Self::EncoderState, bytes: &mut Vec<u8>);
+}
+
+/// A wrapper for data encoded in a stateful encoding. As with the other
+/// legacy encodings, encoding the code-points that have no character fails
+/// with `try_from_utf_32`, while `from_utf_32` replaces them with `?`.
+pub struct Iso2022Encoding<C: Iso2022Codec> {
+    pub data: Vec<u8>,
+    codec: PhantomData<C>,
+}
+
+impl<C: Iso2022Codec> Iso2022Encoding<C> {
+    /// Encodes UTF-32 data, replacing the code-points that have no character
+    /// with the given ASCII character. The number of replaced code-points is
+    /// returned along the data.
+    pub fn from_utf_32_lossy(data_utf_32: &Utf32, replacement: u8) -> (Self, usize) {
+        let mut data: Vec<u8> = Vec::new();
+        let mut state = C::EncoderState::default();
+        let mut count: usize = 0;
+        for glyph in &data_utf_32.data {
+            if !C::encode_character(*glyph, &mut state, &mut data) {
+                if !C::encode_character(replacement as u32, &mut state, &mut data) {
+                    data.push(replacement);
+                }
+                count += 1;
+            }
+        }
+        C::finish(&mut state, &mut data);
+        return (Iso2022Encoding{data: data, codec: PhantomData}, count);
+    }
+}
+
+impl<C: Iso2022Codec> UnicodeEncoding for Iso2022Encoding<C> {
+    const NAME: &'static str = C::NAME;
+    type Options = ();
+
+    /// Convert UTF-32 data to the encoding. The code-points that have no
+    /// character are replaced with `?`.
+    fn from_utf_32(data_utf_32: &Utf32) -> Self {
+        return Self::from_utf_32_lossy(data_utf_32, REPLACEMENT_BYTE).0;
+    }
+
+    /// Convert UTF-32 data to the encoding. An error locating the first
+    /// code-point that has no character is returned if there is one.
+    fn try_from_utf_32(data_utf_32: &Utf32) -> Result<Self, UnicodeEncodingError> {
+        let mut data: Vec<u8> = Vec::new();
+        let mut state = C::EncoderState::default();
+        encode_glyphs::<C>(&data_utf_32.data, &mut state, &mut data).map_err(|x| x.in_encoding(Self::NAME))?;
+        C::finish(&mut state, &mut data);
+        return Ok(Iso2022Encoding{data: data, codec: PhantomData});
+    }
+
+    /// Convert data in the encoding to UTF-32.
+    fn try_to_utf_32(&self) -> Result<Utf32, UnicodeEncodingError> {
+        let mut data: Vec<u32> = Vec::new();
+        let mut state = C::DecoderState::default();
+        let mut index: usize = 0;
+        while index < self.data.len() {
+            let (glyph, len) = C::decode_character(&self.data, index, &mut state).map_err(|x| x.in_encoding(Self::NAME))?;
+            data.extend(glyph);
+            index += len;
+        }
+        return Ok(Utf32{data: data});
+    }
+
+    /// Convert the instance of `Iso2022Encoding` to a vector of bytes.
+    /// No transformation is needed.
+    fn to_bytes(&self, _options: ()) -> Vec<u8> {
+        return self.data.clone();
+    }
+
+    /// Consider a stream of bytes in the encoding and turn it into an
+    /// `Iso2022Encoding` type. The bytes are decoded to ensure they are
+    /// valid.
+    fn from_bytes_no_check(bytes: &[u8], _options: ()) -> Result<Self, UnicodeEncodingError> {
+        let ret = Iso2022Encoding{data: bytes.to_vec(), codec: PhantomData};
+        ret.try_to_utf_32()?;
+        return Ok(ret);
+    }
+
+    /// Decodes the data character by character, replacing the invalid
+    /// sequences. The replacements that can't be encoded, such as U+FFFD, are
+    /// stored as `?`, use `decode_lossy` to keep them.
+    fn from_bytes_lossy(bytes: &[u8], options: (), policy: DecodingPolicy) -> Result<(Self, usize), UnicodeEncodingError> {
+        let (data, count) = Self::decode_lossy(bytes, options, policy)?;
+        return Ok((Self::from_utf_32(&data), count));
+    }
+
+    /// Decodes the data character by character to UTF-32, replacing the
+    /// invalid sequences. The state is kept across the invalid sequences.
+    fn decode_lossy(bytes: &[u8], _options: (), policy: DecodingPolicy) -> Result<(Utf32, usize), UnicodeEncodingError> {
+        let mut data: Vec<u32> = Vec::new();
+        let mut state = C::DecoderState::default();
+        let mut count: usize = 0;
+        let mut index: usize = 0;
+        while index < bytes.len() {
+            match C::decode_character(bytes, index, &mut state) {
+                Ok((glyph, len)) => {
+                    data.extend(glyph);
+                    index += len;
+                },
+                Err(err) => {
+                    let err = err.in_encoding(Self::NAME);
+                    data.extend(policy.handle(&err)?);
+                    count += 1;
+                    index += std::cmp::max(1, err.code_units.len());
+                },
+            }
+        }
+        return Ok((Utf32{data: data}, count));
+    }
+
+    /// The stateful encodings have no BOM.
+    fn bom(_options: ()) -> Vec<u8> {
+        return Vec::new();
+    }
+
+    fn decoder(_options: ()) -> Box<dyn Decoder> where Self: 'static {
+        return Box::new(Iso2022Decoder::<C>{pending: Vec::new(), consumed: 0, state: C::DecoderState::default()});
+    }
+
+    fn encoder(_options: ()) -> Box<dyn Encoder> where Self: 'static {
+        return Box::new(Iso2022Encoder::<C>{consumed: 0, state: C::EncoderState::default(), checker: SanityChecker::new()});
+    }
+}
+
+/* --------------------------- Incremental coding --------------------------- */
+
+/// Decodes data in a stateful encoding chunk by chunk. The state and the
+/// bytes of a sequence split between two chunks are kept until the next one.
+pub struct Iso2022Decoder<C: Iso2022Codec> {
+    pending: Vec<u8>,
+    consumed: usize,
+    state: C::DecoderState,
+}
+
+impl<C: Iso2022Codec> Decoder for Iso2022Decoder<C> {
+    fn decode(&mut self, bytes: &[u8]) -> Result<Utf32, UnicodeEncodingError> {
+        self.pending.extend_from_slice(bytes);
+        let mut ret: Vec<u32> = Vec::new();
+        let mut index: usize = 0;
+        while index < self.pending.len() {
+            match C::decode_character(&self.pending, index, &mut self.state) {
+                Ok((glyph, len)) => {
+                    ret.extend(glyph);
+                    index += len;
+                },
+                Err(x) if x.kind == MissingEncodedBytes => break,
+                Err(x) => return Err(x.shifted(self.consumed).in_encoding(C::NAME)),
+            }
+        }
+        self.pending.drain(..index);
+        self.consumed += index;
+        return Ok(Utf32{data: ret});
+    }
+
+    fn finish(&mut self) -> Result<Utf32, UnicodeEncodingError> {
+        if !self.pending.is_empty() {
+            return Err(UnicodeEncodingError::new(MissingEncodedBytes).at_byte(self.consumed).with_code_units(&self.pending).in_encoding(C::NAME));
+        }
+        return Ok(Utf32{data: Vec::new()});
+    }
+}
+
+/// Encodes data in a stateful encoding chunk by chunk. The escape sequence
+/// going back to ASCII is written when the encoding is finished.
+pub struct Iso2022Encoder<C: Iso2022Codec> {
+    consumed: usize,
+    state: C::EncoderState,
+    checker: SanityChecker,
+}
+
+impl<C: Iso2022Codec> Encoder for Iso2022Encoder<C> {
+    fn encode(&mut self, data: &Utf32) -> Result<Vec<u8>, UnicodeEncodingError> {
+        self.checker.push_all(data)?;
+        let mut ret: Vec<u8> = Vec::new();
+        encode_glyphs::<C>(&data.data, &mut self.state, &mut ret).map_err(|x| x.shifted(self.consumed).in_encoding(C::NAME))?;
+        self.consumed += data.data.len();
+        return Ok(ret);
+    }
+
+    fn finish(&mut self) -> Result<Vec<u8>, UnicodeEncodingError> {
+        self.checker.finish()?;
+        let mut ret: Vec<u8> = Vec::new();
+        C::finish(&mut self.state, &mut ret);
+        return Ok(ret);
+    }
+}
+
+/* ---------------------------- Helper functions ---------------------------- */
+
+/// The byte written in place of the code-points that have no character.
+const REPLACEMENT_BYTE: u8 = b'?';
+
+/// The escape character starting the escape sequences.
+pub(crate) const ESC: u8 = 0x1B;
+
+/// The Shift Out control, switching to the second character set.
+pub(crate) const SO: u8 = 0x0E;
+
+/// The Shift In control, switching back to ASCII.
+pub(crate) const SI: u8 = 0x0F;
+
+/// Encodes code-points with the given state, returning an error locating the
+/// first one that has no character.
+fn encode_glyphs<C: Iso2022Codec>(glyphs: &[u32], state: &mut C::EncoderState, bytes: &mut Vec<u8>) -> Result<(), UnicodeEncodingError> {
+    for (i, glyph) in glyphs.iter().enumerate() {
+        if !C::encode_character(*glyph, state, bytes) {
+            return Err(UnicodeEncodingError::new(UnmappableCodepoint).at_codepoint(i).with_code_units(&[*glyph]));
+        }
+    }
+    return Ok(());
+}
+
+/// Reads the escape sequence starting at the given index, whose bytes after
+/// the escape character must be one of the given sequences. Returns the index
+/// of the sequence found.
+pub(crate) fn read_escape_sequence(data: &[u8], start: usize, sequences: &[&[u8]]) -> Result<usize, UnicodeEncodingError> {
+    let tail = &data[start+1..];
+    if let Some(index) = sequences.iter().position(|x| tail.starts_with(x)) {
+        return Ok(index);
+    }
+    if sequences.iter().any(|x| x.starts_with(tail)) {
+        return Err(UnicodeEncodingError::new(MissingEncodedBytes).at_byte(start).with_code_units(&data[start..]));
+    }
+    return Err(UnicodeEncodingError::new(InvalidEscapeSequence).at_byte(start).with_code_units(&data[start..start+1]));
+}
+
+/// Reads the two bytes of a character of a 94×94 character set, such as
+/// JIS X 0208, whose row and cell are given to `lookup`. As in the WHATWG
+/// Encoding Standard, a second byte that can't be part of the character is
+/// read again.
+pub(crate) fn read_94x94_character(data: &[u8], start: usize, lookup: impl Fn(usize, usize) -> Option<u32>) -> Result<(Option<u32>, usize), UnicodeEncodingError> {
+    if start + 1 == data.len() {
+        return Err(UnicodeEncodingError::new(MissingEncodedBytes).at_byte(start).with_code_units(&data[start..]));
+    }
+    let (lead, trail) = (data[start], data[start+1]);
+    let len = if is_94_byte(trail) {2} else {1};
+    if is_94_byte(lead) && is_94_byte(trail) {
+        if let Some(glyph) = lookup((lead - 0x21) as usize, (trail - 0x21) as usize) {
+            return Ok((Some(glyph), 2));
+        }
+    }
+    return Err(UnicodeEncodingError::new(InvalidMultiByteSequence).at_byte(start).with_code_units(&data[start..start+len]));
+}
+
+/// Tells if a byte is one of the 94 bytes used by the characters of a
+/// 94×94 character set.
+pub(crate) fn is_94_byte(byte: u8) -> bool {
+    return matches!(byte, 0x21..=0x7E);
+}
+
+/* --------------------------------- Testing -------------------------------- */
+
+#[test]
+fn test_iso_2022_incremental() {
+    use crate::iso_2022_jp::Iso2022Jp;
+    let bytes = b"\x1b$BF|K\\8l\x1b(B abc";
+    let mut decoder = Iso2022Jp::decoder(());
+    let decoded = crate::incremental::decode_byte_per_byte(decoder.as_mut(), bytes).unwrap();
+    assert_eq!(decoded.to_string().unwrap(), "日本語 abc");
+    let mut decoder = Iso2022Jp::decoder(());
+    decoder.decode(b"a\x1b$").unwrap();
+    assert_eq!(decoder.finish().err().unwrap().position, Some(crate::unicode_encoding::ErrorPosition::Byte(1)));
+    // The state is kept between the chunks, the escape sequence back to ASCII
+    // is only written at the end.
+    let mut encoder = Iso2022Jp::encoder(());
+    let mut encoded = encoder.encode(&Utf32::from_string("日本").unwrap()).unwrap();
+    encoded.extend(encoder.encode(&Utf32::from_string("語").unwrap()).unwrap());
+    encoded.extend(encoder.finish().unwrap());
+    assert_eq!(encoded, b"\x1b$BF|K\\8l\x1b(B");
+    let err = encoder.encode(&Utf32::from_string("a😎").unwrap()).err().unwrap();
+    assert_eq!(err.position, Some(crate::unicode_encoding::ErrorPosition::Codepoint(4)));
+}
+
+#[test]
+fn test_iso_2022_lossy() {
+    use crate::iso_2022_jp::Iso2022Jp;
+    // The unknown escape sequence is skipped, so the next bytes are still
+    // read as JIS X 0208.
+    let (decoded, count) = Iso2022Jp::from_bytes_lossy(b"\x1b$BF|\x1b(ZK\\\x1b(B", (), DecodingPolicy::Escape).unwrap();
+    assert_eq!(count, 2);
+    assert_eq!(decoded.to_string().unwrap(), "日\\x1B\\x28\\x5A本");
+    let (decoded, count) = Iso2022Jp::decode_lossy(b"a\x80b", (), DecodingPolicy::Replace).unwrap();
+    assert_eq!(count, 1);
+    assert_eq!(decoded.data, vec![0x61, 0xFFFD, 0x62]);
+    let (decoded, _) = crate::encoding::Encoding::Iso2022Jp.decode_lossy(b"a\x80b", DecodingPolicy::Replace).unwrap();
+    assert_eq!(decoded.data, vec![0x61, 0xFFFD, 0x62]);
+    let (encoded, count) = Iso2022Jp::from_utf_32_lossy(&Utf32::from_string("日😎本").unwrap(), b'?');
+    assert_eq!(count, 1);
+    assert_eq!(encoded.data, b"\x1b$BF|\x1b(B?\x1b$BK\\\x1b(B");
+}
@@ -0,0 +1,156 @@
+/// The ISO-2022-JP module handles the Japanese encoding of the mails and of
+/// the news, described in the RFC 1468, where escape sequences switch between
+/// ASCII, JIS X 0201 and JIS X 0208, as in the WHATWG Encoding Standard.
+
+use crate::iso_2022::{self, Iso2022Codec, Iso2022Encoding, ESC, SO, SI};
+use crate::unicode_encoding::UnicodeEncodingErrorKind::*;
+use crate::unicode_encoding::UnicodeEncodingError;
+use crate::cjk_indexes::JIS0208;
+
+/// The character sets of ISO-2022-JP.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Iso2022JpCharset {
+    /// ASCII, designated with `ESC ( B`.
+    #[default]
+    Ascii,
+    /// JIS X 0201 Roman, which is ASCII with a yen sign and an overline,
+    /// designated with `ESC ( J`.
+    Roman,
+    /// The half-width katakana of JIS X 0201, designated with `ESC ( I`. It
+    /// is only decoded.
+    Katakana,
+    /// JIS X 0208, designated with `ESC $ B` or `ESC $ @`.
+    Jis0208,
+}
+
+/// The ISO-2022-JP codec. The half-width katakana are written with their
+/// full-width equivalent, as the RFC 1468 does not allow JIS X 0201
+/// katakana.
+pub struct Iso2022JpCodec;
+
+/// A wrapper for ISO-2022-JP encoded bytes.
+pub type Iso2022Jp = Iso2022Encoding<Iso2022JpCodec>;
+
+impl Iso2022Codec for Iso2022JpCodec {
+    const NAME: &'static str = "ISO-2022-JP";
+    type DecoderState = Iso2022JpCharset;
+    type EncoderState = Iso2022JpCharset;
+
+    fn decode_character(data: &[u8], start: usize, state: &mut Iso2022JpCharset) -> Result<(Option<u32>, usize), UnicodeEncodingError> {
+        let byte = data[start];
+        if byte == ESC {
+            *state = ESCAPE_SEQUENCES[iso_2022::read_escape_sequence(data, start, &ESCAPE_SEQUENCES.map(|x| x.1))?].0;
+            return Ok((None, 3));
+        }
+        let glyph = match (*state, byte) {
+            (_, SO | SI) | (_, 0x80..) => None,
+            // The line ends are always in ASCII.
+            (Iso2022JpCharset::Katakana | Iso2022JpCharset::Jis0208, b'\n') => {
+                *state = Iso2022JpCharset::Ascii;
+                Some(byte as u32)
+            },
+            (Iso2022JpCharset::Ascii, _) => Some(byte as u32),
+            (Iso2022JpCharset::Roman, 0x5C) => Some(0xA5),
+            (Iso2022JpCharset::Roman, 0x7E) => Some(0x203E),
+            (Iso2022JpCharset::Roman, _) => Some(byte as u32),
+            (Iso2022JpCharset::Katakana, 0x21..=0x5F) => Some(0xFF61 + (byte - 0x21) as u32),
+            (Iso2022JpCharset::Katakana, _) => None,
+            (Iso2022JpCharset::Jis0208, _) => return iso_2022::read_94x94_character(data, start, |row, cell| JIS0208.code_point(row * 94 + cell)),
+        };
+        return match glyph {
+            Some(glyph) => Ok((Some(glyph), 1)),
+            None => Err(UnicodeEncodingError::new(InvalidMultiByteSequence).at_byte(start).with_code_units(&data[start..start+1])),
+        };
+    }
+
+    fn encode_character(glyph: u32, state: &mut Iso2022JpCharset, bytes: &mut Vec<u8>) -> bool {
+        let glyph = match glyph {
+            0xFF61..=0xFF9F => HALF_WIDTH_KATAKANA[(glyph - 0xFF61) as usize] as u32,
+            0x2212 => 0xFF0D,
+            _ => glyph,
+        };
+        let (charset, character) = match glyph {
+            0x0E | 0x0F | 0x1B => return false,
+            // In JIS X 0201 Roman, only the line ends need ASCII.
+            0x00..=0x7F if *state == Iso2022JpCharset::Roman && !matches!(glyph, 0x5C | 0x7E | 0x0A | 0x0D) => (Iso2022JpCharset::Roman, vec![glyph as u8]),
+            0x00..=0x7F => (Iso2022JpCharset::Ascii, vec![glyph as u8]),
+            0xA5 => (Iso2022JpCharset::Roman, vec![0x5C]),
+            0x203E => (Iso2022JpCharset::Roman, vec![0x7E]),
+            _ => match JIS0208.pointer(glyph) {
+                Some(pointer) if pointer < 94 * 94 => (Iso2022JpCharset::Jis0208, vec![(pointer / 94) as u8 + 0x21, (pointer % 94) as u8 + 0x21]),
+                _ => return false,
+            },
+        };
+        if charset != *state {
+            write_escape_sequence(charset, bytes);
+            *state = charset;
+        }
+        bytes.extend_from_slice(&character);
+        return true;
+    }
+
+    fn finish(state: &mut Iso2022JpCharset, bytes: &mut Vec<u8>) {
+        if *state != Iso2022JpCharset::Ascii {
+            write_escape_sequence(Iso2022JpCharset::Ascii, bytes);
+            *state = Iso2022JpCharset::Ascii;
+        }
+    }
+}
+
+/* ---------------------------- Helper functions ---------------------------- */
+
+/// The escape sequences designating each character set, without the escape
+/// character. The first one of a character set is the one written.
+const ESCAPE_SEQUENCES: [(Iso2022JpCharset, &[u8]); 5] = [(Iso2022JpCharset::Ascii, b"(B"), (Iso2022JpCharset::Roman, b"(J"),
+                                                          (Iso2022JpCharset::Katakana, b"(I"), (Iso2022JpCharset::Jis0208, b"$B"),
+                                                          (Iso2022JpCharset::Jis0208, b"$@")];
+
+fn write_escape_sequence(charset: Iso2022JpCharset, bytes: &mut Vec<u8>) {
+    let sequence = ESCAPE_SEQUENCES.iter().find(|x| x.0 == charset).unwrap().1;
+    bytes.push(ESC);
+    bytes.extend_from_slice(sequence);
+}
+
+/// The full-width katakana written in place of the half-width ones, from
+/// U+FF61 to U+FF9F.
+const HALF_WIDTH_KATAKANA: [u16; 63] = [
+    0x3002, 0x300C, 0x300D, 0x3001, 0x30FB, 0x30F2, 0x30A1, 0x30A3, 0x30A5, 0x30A7, 0x30A9, 0x30E3, 0x30E5, 0x30E7, 0x30C3, 0x30FC,
+    0x30A2, 0x30A4, 0x30A6, 0x30A8, 0x30AA, 0x30AB, 0x30AD, 0x30AF, 0x30B1, 0x30B3, 0x30B5, 0x30B7, 0x30B9, 0x30BB, 0x30BD, 0x30BF,
+    0x30C1, 0x30C4, 0x30C6, 0x30C8, 0x30CA, 0x30CB, 0x30CC, 0x30CD, 0x30CE, 0x30CF, 0x30D2, 0x30D5, 0x30D8, 0x30DB, 0x30DE, 0x30DF,
+    0x30E0, 0x30E1, 0x30E2, 0x30E4, 0x30E6, 0x30E8, 0x30E9, 0x30EA, 0x30EB, 0x30EC, 0x30ED, 0x30EF, 0x30F3, 0x309B, 0x309C];
+
+/* --------------------------------- Testing -------------------------------- */
+
+#[test]
+fn test_iso_2022_jp() {
+    use crate::unicode_encoding::UnicodeEncoding;
+    let bytes = b"\x1b$BF|K\\8l\x1b(B abc";
+    assert_eq!(Iso2022Jp::from_bytes(bytes, ()).unwrap().to_string().unwrap(), "日本語 abc");
+    assert_eq!(Iso2022Jp::from_string("日本語 abc").unwrap().data, bytes);
+    assert_eq!(Iso2022Jp::from_string("a¥b\n").unwrap().data, b"a\x1b(J\\b\x1b(B\n");
+    assert_eq!(Iso2022Jp::from_string("ｱｰ").unwrap().data, b"\x1b$B%\"!<\x1b(B");
+    // The old escape sequence of JIS X 0208 and JIS X 0201 katakana are read.
+    assert_eq!(Iso2022Jp::from_bytes(b"\x1b$@F|\x1b(I1\x1b(J\\~", ()).unwrap().to_string().unwrap(), "日ｱ¥‾");
+}
+
+#[test]
+fn test_iso_2022_jp_line_ends() {
+    use crate::unicode_encoding::UnicodeEncoding;
+    // The data and the lines end in ASCII.
+    assert_eq!(Iso2022Jp::from_string("日\n本").unwrap().data, b"\x1b$BF|\x1b(B\n\x1b$BK\\\x1b(B");
+    assert_eq!(Iso2022Jp::from_bytes(b"\x1b$BF|\nK\\", ()).unwrap().to_string().unwrap(), "日\nK\\");
+}
+
+#[test]
+fn test_invalid_iso_2022_jp() {
+    use crate::unicode_encoding::UnicodeEncoding;
+    let err = Iso2022Jp::from_bytes(b"a\x1b(Zb", ()).err().unwrap();
+    assert_eq!(err.kind, InvalidEscapeSequence);
+    assert_eq!(err.position, Some(crate::unicode_encoding::ErrorPosition::Byte(1)));
+    assert_eq!(Iso2022Jp::from_bytes(b"a\x1b(", ()).err().unwrap().kind, MissingEncodedBytes);
+    assert_eq!(Iso2022Jp::from_bytes(b"\x1b$BF", ()).err().unwrap().kind, MissingEncodedBytes);
+    assert_eq!(Iso2022Jp::from_bytes(b"\x0e", ()).err().unwrap().kind, InvalidMultiByteSequence);
+    assert_eq!(Iso2022Jp::from_bytes(&[0xA4], ()).err().unwrap().kind, InvalidMultiByteSequence);
+    assert_eq!(Iso2022Jp::from_string("한").err().unwrap().kind, UnmappableCodepoint);
+    assert_eq!(Iso2022Jp::from_string("\u{1B}").err().unwrap().kind, UnmappableCodepoint);
+}
@@ -0,0 +1,122 @@
+/// The ISO-2022-KR module handles the Korean encoding of the mails described
+/// in the RFC 1557, where the Shift Out and Shift In controls switch between
+/// ASCII and KS X 1001.
+
+use crate::iso_2022::{self, Iso2022Codec, Iso2022Encoding, ESC, SO, SI};
+use crate::unicode_encoding::UnicodeEncodingErrorKind::*;
+use crate::unicode_encoding::UnicodeEncodingError;
+use crate::cjk_indexes::EUC_KR;
+
+/// The state of an ISO-2022-KR encoder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Iso2022KrEncoderState {
+    /// Tells if the designation of KS X 1001 has been written.
+    designated: bool,
+    /// Tells if KS X 1001 is in use.
+    shifted: bool,
+}
+
+/// The ISO-2022-KR codec. The designation of KS X 1001, `ESC $ ) C`, is
+/// written once, before the first Korean character.
+pub struct Iso2022KrCodec;
+
+/// A wrapper for ISO-2022-KR encoded bytes.
+pub type Iso2022Kr = Iso2022Encoding<Iso2022KrCodec>;
+
+impl Iso2022Codec for Iso2022KrCodec {
+    const NAME: &'static str = "ISO-2022-KR";
+    /// Tells if KS X 1001 is in use.
+    type DecoderState = bool;
+    type EncoderState = Iso2022KrEncoderState;
+
+    fn decode_character(data: &[u8], start: usize, shifted: &mut bool) -> Result<(Option<u32>, usize), UnicodeEncodingError> {
+        let byte = data[start];
+        match byte {
+            ESC => {
+                iso_2022::read_escape_sequence(data, start, &[DESIGNATION])?;
+                return Ok((None, DESIGNATION.len() + 1));
+            },
+            SO | SI => {
+                *shifted = byte == SO;
+                return Ok((None, 1));
+            },
+            // Each line starts in ASCII.
+            b'\n' => {
+                *shifted = false;
+                return Ok((Some(byte as u32), 1));
+            },
+            // The controls and the space are the ones of ASCII in both sets.
+            0x00..=0x20 | 0x7F => return Ok((Some(byte as u32), 1)),
+            0x21..=0x7E if !*shifted => return Ok((Some(byte as u32), 1)),
+            0x21..=0x7E => return iso_2022::read_94x94_character(data, start, |row, cell| EUC_KR.code_point(euc_kr_pointer(row, cell))),
+            _ => return Err(UnicodeEncodingError::new(InvalidMultiByteSequence).at_byte(start).with_code_units(&data[start..start+1])),
+        }
+    }
+
+    fn encode_character(glyph: u32, state: &mut Iso2022KrEncoderState, bytes: &mut Vec<u8>) -> bool {
+        if glyph < 0x80 {
+            if matches!(glyph as u8, ESC | SO | SI) {
+                return false;
+            }
+            Self::finish(state, bytes);
+            bytes.push(glyph as u8);
+            return true;
+        }
+        // Only the part of the Unified Hangul Code made of KS X 1001, whose
+        // bytes are above 0xA0, can be written.
+        let pointer = match EUC_KR.pointer(glyph) {
+            Some(pointer) => pointer,
+            None => return false,
+        };
+        let (lead, trail) = ((pointer / 190) as u8 + 0x81, (pointer % 190) as u8 + 0x41);
+        if lead < 0xA1 || trail < 0xA1 {
+            return false;
+        }
+        if !state.designated {
+            bytes.push(ESC);
+            bytes.extend_from_slice(DESIGNATION);
+            state.designated = true;
+        }
+        if !state.shifted {
+            bytes.push(SO);
+            state.shifted = true;
+        }
+        bytes.extend_from_slice(&[lead - 0x80, trail - 0x80]);
+        return true;
+    }
+
+    fn finish(state: &mut Iso2022KrEncoderState, bytes: &mut Vec<u8>) {
+        if state.shifted {
+            bytes.push(SI);
+            state.shifted = false;
+        }
+    }
+}
+
+/* ---------------------------- Helper functions ---------------------------- */
+
+/// The escape sequence designating KS X 1001, without the escape character.
+const DESIGNATION: &[u8] = b"$)C";
+
+/// The pointer in the EUC-KR index of a character of KS X 1001.
+fn euc_kr_pointer(row: usize, cell: usize) -> usize {
+    return (row + 0xA1 - 0x81) * 190 + cell + 0xA1 - 0x41;
+}
+
+/* --------------------------------- Testing -------------------------------- */
+
+#[test]
+fn test_iso_2022_kr() {
+    use crate::unicode_encoding::UnicodeEncoding;
+    let bytes = b"\x1b$)C\x0eGQ19>n\x0f a";
+    assert_eq!(Iso2022Kr::from_bytes(bytes, ()).unwrap().to_string().unwrap(), "한국어 a");
+    assert_eq!(Iso2022Kr::from_string("한국어 a").unwrap().data, bytes);
+    assert_eq!(Iso2022Kr::from_string("a").unwrap().data, b"a");
+    assert_eq!(Iso2022Kr::from_string("한\n국").unwrap().data, b"\x1b$)C\x0eGQ\x0f\n\x0e19\x0f");
+    // The space is read in both sets, and the lines start in ASCII.
+    assert_eq!(Iso2022Kr::from_bytes(b"\x0eGQ 19\nGQ", ()).unwrap().to_string().unwrap(), "한 국\nGQ");
+    // The characters added by the Unified Hangul Code can't be written.
+    assert_eq!(Iso2022Kr::from_string("똠").err().unwrap().kind, UnmappableCodepoint);
+    assert_eq!(Iso2022Kr::from_bytes(b"\x1b$)A", ()).err().unwrap().kind, InvalidEscapeSequence);
+    assert_eq!(Iso2022Kr::from_bytes(b"\x0eG", ()).err().unwrap().kind, MissingEncodedBytes);
+}
@@ -0,0 +1,434 @@
+/// The ISO 6937 module handles the Latin encoding of ISO/IEC 6937, used by
+/// the DVB for the texts of the broadcasts and by X.500 and LDAP for the
+/// `T61String`, where the non-spacing diacritics precede the base letter
+/// instead of following it as the Unicode combining characters do.
+
+use crate::unicode_encoding::UnicodeEncodingErrorKind::*;
+use crate::unicode_encoding::UnicodeEncodingError;
+use crate::unicode_encoding::UnicodeEncoding;
+use crate::utf_32::Utf32;
+use crate::utf_8;
+use crate::incremental::{Decoder, Encoder, SanityChecker};
+use crate::lossy::{self, DecodingPolicy};
+
+/// A wrapper for ISO 6937 encoded bytes. A diacritic followed by a letter is
+/// decoded to the precomposed character when Unicode has one, such as `é`,
+/// and to the letter followed by the combining diacritic otherwise. Both
+/// forms are encoded back to the diacritic followed by the letter. The data
+/// of T.61, whose characters are mostly a subset of ISO 6937, can be read as
+/// well.
+pub struct Iso6937 {
+    pub data: Vec<u8>,
+}
+
+impl Iso6937 {
+    /// Encodes UTF-32 data, replacing the code-points that have no character
+    /// with the given byte. The number of replaced code-points is returned
+    /// along the data.
+    pub fn from_utf_32_lossy(data_utf_32: &Utf32, replacement: u8) -> (Self, usize) {
+        let mut data: Vec<u8> = Vec::new();
+        let mut count: usize = 0;
+        let mut index: usize = 0;
+        while index < data_utf_32.data.len() {
+            match encode_character(&data_utf_32.data, index, &mut data) {
+                Some(len) => index += len,
+                None => {
+                    data.push(replacement);
+                    count += 1;
+                    index += 1;
+                },
+            }
+        }
+        return (Iso6937{data: data}, count);
+    }
+}
+
+impl UnicodeEncoding for Iso6937 {
+    const NAME: &'static str = "ISO_6937";
+    type Options = ();
+
+    /// Convert UTF-32 data to ISO 6937. The code-points that have no
+    /// character are replaced with `?`.
+    fn from_utf_32(data_utf_32: &Utf32) -> Self {
+        return Self::from_utf_32_lossy(data_utf_32, REPLACEMENT_BYTE).0;
+    }
+
+    /// Convert UTF-32 data to ISO 6937. An error locating the first
+    /// code-point that has no character is returned if there is one.
+    fn try_from_utf_32(data_utf_32: &Utf32) -> Result<Self, UnicodeEncodingError> {
+        let mut data: Vec<u8> = Vec::new();
+        encode_glyphs(&data_utf_32.data, data_utf_32.data.len(), &mut data).map_err(|x| x.in_encoding(Self::NAME))?;
+        return Ok(Iso6937{data: data});
+    }
+
+    /// Convert ISO 6937 data to UTF-32.
+    fn try_to_utf_32(&self) -> Result<Utf32, UnicodeEncodingError> {
+        let mut data: Vec<u32> = Vec::new();
+        let mut index: usize = 0;
+        while index < self.data.len() {
+            let (glyph, len) = decode_character(&self.data, index).map_err(|x| x.in_encoding(Self::NAME))?;
+            data.push(glyph);
+            index += len;
+        }
+        return Ok(Utf32{data: expand_sequences(data)});
+    }
+
+    /// Convert the instance of `Iso6937` to a vector of bytes. No
+    /// transformation is needed.
+    fn to_bytes(&self, _options: ()) -> Vec<u8> {
+        return self.data.clone();
+    }
+
+    /// Consider a stream of bytes in ISO 6937 and turn it into an `Iso6937`
+    /// type. The bytes are decoded to ensure they are valid.
+    fn from_bytes_no_check(bytes: &[u8], _options: ()) -> Result<Self, UnicodeEncodingError> {
+        let ret = Iso6937{data: bytes.to_vec()};
+        ret.try_to_utf_32()?;
+        return Ok(ret);
+    }
+
+    /// Decodes the data character by character, replacing the invalid
+    /// sequences. The replacements that can't be encoded, such as U+FFFD, are
+    /// stored as `?`, use `decode_lossy` to keep them.
+    fn from_bytes_lossy(bytes: &[u8], options: (), policy: DecodingPolicy) -> Result<(Self, usize), UnicodeEncodingError> {
+        let (data, count) = Self::decode_lossy(bytes, options, policy)?;
+        return Ok((Self::from_utf_32(&data), count));
+    }
+
+    /// Decodes the data character by character to UTF-32, replacing the
+    /// invalid sequences. A diacritic that is not followed by a base
+    /// character is invalid on its own, and the byte following it is read
+    /// again.
+    fn decode_lossy(bytes: &[u8], _options: (), policy: DecodingPolicy) -> Result<(Utf32, usize), UnicodeEncodingError> {
+        let (data, count) = lossy::decode_glyphs_lossy(bytes, decode_character, policy).map_err(|x| x.in_encoding(Self::NAME))?;
+        return Ok((Utf32{data: expand_sequences(data)}, count));
+    }
+
+    /// ISO 6937 has no BOM.
+    fn bom(_options: ()) -> Vec<u8> {
+        return Vec::new();
+    }
+
+    fn decoder(_options: ()) -> Box<dyn Decoder> where Self: 'static {
+        return Box::new(Iso6937Decoder{pending: Vec::new(), consumed: 0});
+    }
+
+    fn encoder(_options: ()) -> Box<dyn Encoder> where Self: 'static {
+        return Box::new(Iso6937Encoder{pending: Vec::new(), consumed: 0, checker: SanityChecker::new()});
+    }
+}
+
+/* --------------------------- Incremental coding --------------------------- */
+
+/// Decodes ISO 6937 data chunk by chunk. A diacritic ending a chunk is kept
+/// until the base character of the next one.
+pub struct Iso6937Decoder {
+    pending: Vec<u8>,
+    consumed: usize,
+}
+
+impl Decoder for Iso6937Decoder {
+    fn decode(&mut self, bytes: &[u8]) -> Result<Utf32, UnicodeEncodingError> {
+        self.pending.extend_from_slice(bytes);
+        let data = utf_8::decode_available_glyphs(&mut self.pending, &mut self.consumed, decode_character).map_err(|x| x.in_encoding(Iso6937::NAME))?;
+        return Ok(Utf32{data: expand_sequences(data)});
+    }
+
+    fn finish(&mut self) -> Result<Utf32, UnicodeEncodingError> {
+        if !self.pending.is_empty() {
+            return Err(UnicodeEncodingError::new(MissingEncodedBytes).at_byte(self.consumed).with_code_units(&self.pending).in_encoding(Iso6937::NAME));
+        }
+        return Ok(Utf32{data: Vec::new()});
+    }
+}
+
+/// Encodes data in ISO 6937 chunk by chunk. The last code-point of a chunk
+/// is kept until the next one, as it may be followed by a combining
+/// diacritic.
+pub struct Iso6937Encoder {
+    pending: Vec<u32>,
+    consumed: usize,
+    checker: SanityChecker,
+}
+
+impl Encoder for Iso6937Encoder {
+    fn encode(&mut self, data: &Utf32) -> Result<Vec<u8>, UnicodeEncodingError> {
+        self.checker.push_all(data)?;
+        self.pending.extend_from_slice(&data.data);
+        let mut ret: Vec<u8> = Vec::new();
+        let end = self.pending.len().saturating_sub(1);
+        let len = encode_glyphs(&self.pending, end, &mut ret).map_err(|x| x.shifted(self.consumed).in_encoding(Iso6937::NAME))?;
+        self.pending.drain(..len);
+        self.consumed += len;
+        return Ok(ret);
+    }
+
+    fn finish(&mut self) -> Result<Vec<u8>, UnicodeEncodingError> {
+        self.checker.finish()?;
+        let mut ret: Vec<u8> = Vec::new();
+        encode_glyphs(&self.pending, self.pending.len(), &mut ret).map_err(|x| x.shifted(self.consumed).in_encoding(Iso6937::NAME))?;
+        self.consumed += self.pending.len();
+        self.pending.clear();
+        return Ok(ret);
+    }
+}
+
+/* ---------------------------- Helper functions ---------------------------- */
+
+/// The byte written in place of the code-points that have no character.
+const REPLACEMENT_BYTE: u8 = b'?';
+
+/// The value of the bytes that are not a character on their own in `TABLE`.
+const UNMAPPED: u16 = 0xFFFF;
+
+/// The space following a diacritic to write its spacing form.
+const SPACE: u8 = 0x20;
+
+/// The code-points returned by `decode_character` from this value on stand
+/// for a base character followed by a combining diacritic, the diacritic
+/// being told by the bits above the 16 bits of the base character.
+const SEQUENCE_BASE: u32 = 0x110000;
+
+/// The non-spacing diacritics, with their byte, the combining character they
+/// stand for, and their spacing form, written as the diacritic followed by a
+/// space.
+const DIACRITICS: [(u8, u32, u32); 13] = [(0xC1, 0x0300, 0x0060), (0xC2, 0x0301, 0x00B4), (0xC3, 0x0302, 0x005E), (0xC4, 0x0303, 0x007E),
+                                          (0xC5, 0x0304, 0x00AF), (0xC6, 0x0306, 0x02D8), (0xC7, 0x0307, 0x02D9), (0xC8, 0x0308, 0x00A8),
+                                          (0xCA, 0x030A, 0x02DA), (0xCB, 0x0327, 0x00B8), (0xCD, 0x030B, 0x02DD), (0xCE, 0x0328, 0x02DB),
+                                          (0xCF, 0x030C, 0x02C7)];
+
+/// The diacritics of T.61 that ISO 6937 does not have, the umlaut and the
+/// underline. They are only decoded.
+const T61_DIACRITICS: [(u8, u32, u32); 2] = [(0xC9, 0x0308, 0x00A8), (0xCC, 0x0332, 0x005F)];
+
+/// Finds the diacritic of a byte, if it is one.
+fn diacritic(byte: u8) -> Option<(u8, u32, u32)> {
+    return DIACRITICS.iter().chain(T61_DIACRITICS.iter()).find(|x| x.0 == byte).copied();
+}
+
+/// Tells if a byte is a character that can follow a diacritic, which is any
+/// character but the controls.
+fn is_base(byte: u8) -> bool {
+    return TABLE[byte as usize] != UNMAPPED && matches!(byte, 0x20..=0x7E | 0xA0..=0xFF);
+}
+
+/// Decodes the character starting at the given index of some ISO 6937 data,
+/// returning its code-point and its length. A base character that has no
+/// precomposed form with its diacritic is returned as a value from
+/// `SEQUENCE_BASE` on.
+fn decode_character(data: &[u8], start: usize) -> Result<(u32, usize), UnicodeEncodingError> {
+    let byte = data[start];
+    let (_, mark, spacing) = match diacritic(byte) {
+        Some(x) => x,
+        None if TABLE[byte as usize] == UNMAPPED => return Err(UnicodeEncodingError::new(UndefinedCodepageByte).at_byte(start).with_code_units(&data[start..start+1])),
+        None => return Ok((TABLE[byte as usize] as u32, 1)),
+    };
+    let base = match data.get(start+1) {
+        None => return Err(UnicodeEncodingError::new(MissingEncodedBytes).at_byte(start).with_code_units(&data[start..])),
+        Some(x) if is_base(*x) => *x,
+        // The byte following the lone diacritic is read again.
+        Some(_) => return Err(UnicodeEncodingError::new(InvalidMultiByteSequence).at_byte(start).with_code_units(&data[start..start+1])),
+    };
+    if base == SPACE {
+        return Ok((spacing, 2));
+    }
+    // The umlaut of T.61 composes like the diaeresis.
+    let composing_byte = DIACRITICS.iter().find(|x| x.1 == mark).map(|x| x.0).unwrap_or(byte);
+    return match COMPOSITIONS.binary_search_by_key(&(composing_byte, base), |x| (x.0, x.1)) {
+        Ok(index) => Ok((COMPOSITIONS[index].2 as u32, 2)),
+        Err(_) => Ok((SEQUENCE_BASE + (((byte - 0xC0) as u32) << 16) + TABLE[base as usize] as u32, 2)),
+    };
+}
+
+/// Replaces the values standing for a base character followed by a
+/// combining diacritic with the two code-points.
+fn expand_sequences(glyphs: Vec<u32>) -> Vec<u32> {
+    let mut ret: Vec<u32> = Vec::new();
+    for glyph in glyphs {
+        if glyph < SEQUENCE_BASE {
+            ret.push(glyph);
+            continue;
+        }
+        let value = glyph - SEQUENCE_BASE;
+        let (_, mark, _) = diacritic((value >> 16) as u8 + 0xC0).unwrap();
+        ret.extend_from_slice(&[value & 0xFFFF, mark]);
+    }
+    return ret;
+}
+
+/// Writes the bytes of the character starting at the given index of some
+/// code-points. Returns the number of code-points written, which is two for
+/// a base character followed by a combining diacritic, or None if the
+/// code-point can't be encoded.
+fn encode_character(glyphs: &[u32], index: usize, bytes: &mut Vec<u8>) -> Option<usize> {
+    let glyph = glyphs[index];
+    if glyph < UNMAPPED as u32 {
+        if let Some(byte) = TABLE.iter().position(|x| *x as u32 == glyph).map(|x| x as u8) {
+            match glyphs.get(index+1).and_then(|x| DIACRITICS.iter().find(|y| y.1 == *x)) {
+                Some(mark) if is_base(byte) => {
+                    bytes.extend_from_slice(&[mark.0, byte]);
+                    return Some(2);
+                },
+                _ => {
+                    bytes.push(byte);
+                    return Some(1);
+                },
+            }
+        }
+    }
+    if let Some(composition) = COMPOSITIONS.iter().find(|x| x.2 as u32 == glyph) {
+        bytes.extend_from_slice(&[composition.0, composition.1]);
+        return Some(1);
+    }
+    if let Some(mark) = DIACRITICS.iter().find(|x| x.2 == glyph) {
+        bytes.extend_from_slice(&[mark.0, SPACE]);
+        return Some(1);
+    }
+    return None;
+}
+
+/// Encodes the characters of some code-points starting before `end`,
+/// returning the number of code-points encoded or an error locating the
+/// first one that has no character.
+fn encode_glyphs(glyphs: &[u32], end: usize, bytes: &mut Vec<u8>) -> Result<usize, UnicodeEncodingError> {
+    let mut index: usize = 0;
+    while index < end {
+        match encode_character(glyphs, index, bytes) {
+            Some(len) => index += len,
+            None => return Err(UnicodeEncodingError::new(UnmappableCodepoint).at_codepoint(index).with_code_units(&[glyphs[index]])),
+        }
+    }
+    return Ok(index);
+}
+
+/// The code-point of each byte standing for a character on its own. The
+/// T.61 dollar and number signs at 0xA4 and 0xA6 are only decoded.
+const TABLE: [u16; 256] = [
+    0x0000, 0x0001, 0x0002, 0x0003, 0x0004, 0x0005, 0x0006, 0x0007, 0x0008, 0x0009, 0x000A, 0x000B, 0x000C, 0x000D, 0x000E, 0x000F,
+    0x0010, 0x0011, 0x0012, 0x0013, 0x0014, 0x0015, 0x0016, 0x0017, 0x0018, 0x0019, 0x001A, 0x001B, 0x001C, 0x001D, 0x001E, 0x001F,
+    0x0020, 0x0021, 0x0022, 0x0023, 0x0024, 0x0025, 0x0026, 0x0027, 0x0028, 0x0029, 0x002A, 0x002B, 0x002C, 0x002D, 0x002E, 0x002F,
+    0x0030, 0x0031, 0x0032, 0x0033, 0x0034, 0x0035, 0x0036, 0x0037, 0x0038, 0x0039, 0x003A, 0x003B, 0x003C, 0x003D, 0x003E, 0x003F,
+    0x0040, 0x0041, 0x0042, 0x0043, 0x0044, 0x0045, 0x0046, 0x0047, 0x0048, 0x0049, 0x004A, 0x004B, 0x004C, 0x004D, 0x004E, 0x004F,
+    0x0050, 0x0051, 0x0052, 0x0053, 0x0054, 0x0055, 0x0056, 0x0057, 0x0058, 0x0059, 0x005A, 0x005B, 0x005C, 0x005D, 0x005E, 0x005F,
+    0x0060, 0x0061, 0x0062, 0x0063, 0x0064, 0x0065, 0x0066, 0x0067, 0x0068, 0x0069, 0x006A, 0x006B, 0x006C, 0x006D, 0x006E, 0x006F,
+    0x0070, 0x0071, 0x0072, 0x0073, 0x0074, 0x0075, 0x0076, 0x0077, 0x0078, 0x0079, 0x007A, 0x007B, 0x007C, 0x007D, 0x007E, 0x007F,
+    0x0080, 0x0081, 0x0082, 0x0083, 0x0084, 0x0085, 0x0086, 0x0087, 0x0088, 0x0089, 0x008A, 0x008B, 0x008C, 0x008D, 0x008E, 0x008F,
+    0x0090, 0x0091, 0x0092, 0x0093, 0x0094, 0x0095, 0x0096, 0x0097, 0x0098, 0x0099, 0x009A, 0x009B, 0x009C, 0x009D, 0x009E, 0x009F,
+    0x00A0, 0x00A1, 0x00A2, 0x00A3, 0x0024, 0x00A5, 0x0023, 0x00A7, 0x00A4, 0x2018, 0x201C, 0x00AB, 0x2190, 0x2191, 0x2192, 0x2193,
+    0x00B0, 0x00B1, 0x00B2, 0x00B3, 0x00D7, 0x00B5, 0x00B6, 0x00B7, 0x00F7, 0x2019, 0x201D, 0x00BB, 0x00BC, 0x00BD, 0x00BE, 0x00BF,
+    0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF,
+    0x2014, 0x00B9, 0x00AE, 0x00A9, 0x2122, 0x266A, 0x00AC, 0x00A6, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x215B, 0x215C, 0x215D, 0x215E,
+    0x2126, 0x00C6, 0x00D0, 0x00AA, 0x0126, 0xFFFF, 0x0132, 0x013F, 0x0141, 0x00D8, 0x0152, 0x00BA, 0x00DE, 0x0166, 0x014A, 0x0149,
+    0x0138, 0x00E6, 0x0111, 0x00F0, 0x0127, 0x0131, 0x0133, 0x0140, 0x0142, 0x00F8, 0x0153, 0x00DF, 0x00FE, 0x0167, 0x014B, 0x00AD
+];
+
+/// The characters written as a diacritic followed by a base character,
+/// sorted by diacritic and base.
+const COMPOSITIONS: [(u8, u8, u16); 257] = [
+    (0xC1, 0x41, 0x00C0), (0xC1, 0x45, 0x00C8), (0xC1, 0x49, 0x00CC), (0xC1, 0x4E, 0x01F8), (0xC1, 0x4F, 0x00D2), (0xC1, 0x55, 0x00D9),
+    (0xC1, 0x57, 0x1E80), (0xC1, 0x59, 0x1EF2), (0xC1, 0x61, 0x00E0), (0xC1, 0x65, 0x00E8), (0xC1, 0x69, 0x00EC), (0xC1, 0x6E, 0x01F9),
+    (0xC1, 0x6F, 0x00F2), (0xC1, 0x75, 0x00F9), (0xC1, 0x77, 0x1E81), (0xC1, 0x79, 0x1EF3), (0xC2, 0x41, 0x00C1), (0xC2, 0x43, 0x0106),
+    (0xC2, 0x45, 0x00C9), (0xC2, 0x47, 0x01F4), (0xC2, 0x49, 0x00CD), (0xC2, 0x4B, 0x1E30), (0xC2, 0x4C, 0x0139), (0xC2, 0x4D, 0x1E3E),
+    (0xC2, 0x4E, 0x0143), (0xC2, 0x4F, 0x00D3), (0xC2, 0x50, 0x1E54), (0xC2, 0x52, 0x0154), (0xC2, 0x53, 0x015A), (0xC2, 0x55, 0x00DA),
+    (0xC2, 0x57, 0x1E82), (0xC2, 0x59, 0x00DD), (0xC2, 0x5A, 0x0179), (0xC2, 0x61, 0x00E1), (0xC2, 0x63, 0x0107), (0xC2, 0x65, 0x00E9),
+    (0xC2, 0x67, 0x01F5), (0xC2, 0x69, 0x00ED), (0xC2, 0x6B, 0x1E31), (0xC2, 0x6C, 0x013A), (0xC2, 0x6D, 0x1E3F), (0xC2, 0x6E, 0x0144),
+    (0xC2, 0x6F, 0x00F3), (0xC2, 0x70, 0x1E55), (0xC2, 0x72, 0x0155), (0xC2, 0x73, 0x015B), (0xC2, 0x75, 0x00FA), (0xC2, 0x77, 0x1E83),
+    (0xC2, 0x79, 0x00FD), (0xC2, 0x7A, 0x017A), (0xC2, 0xE1, 0x01FC), (0xC2, 0xE9, 0x01FE), (0xC2, 0xF1, 0x01FD), (0xC2, 0xF9, 0x01FF),
+    (0xC3, 0x41, 0x00C2), (0xC3, 0x43, 0x0108), (0xC3, 0x45, 0x00CA), (0xC3, 0x47, 0x011C), (0xC3, 0x48, 0x0124), (0xC3, 0x49, 0x00CE),
+    (0xC3, 0x4A, 0x0134), (0xC3, 0x4F, 0x00D4), (0xC3, 0x53, 0x015C), (0xC3, 0x55, 0x00DB), (0xC3, 0x57, 0x0174), (0xC3, 0x59, 0x0176),
+    (0xC3, 0x5A, 0x1E90), (0xC3, 0x61, 0x00E2), (0xC3, 0x63, 0x0109), (0xC3, 0x65, 0x00EA), (0xC3, 0x67, 0x011D), (0xC3, 0x68, 0x0125),
+    (0xC3, 0x69, 0x00EE), (0xC3, 0x6A, 0x0135), (0xC3, 0x6F, 0x00F4), (0xC3, 0x73, 0x015D), (0xC3, 0x75, 0x00FB), (0xC3, 0x77, 0x0175),
+    (0xC3, 0x79, 0x0177), (0xC3, 0x7A, 0x1E91), (0xC4, 0x41, 0x00C3), (0xC4, 0x45, 0x1EBC), (0xC4, 0x49, 0x0128), (0xC4, 0x4E, 0x00D1),
+    (0xC4, 0x4F, 0x00D5), (0xC4, 0x55, 0x0168), (0xC4, 0x56, 0x1E7C), (0xC4, 0x59, 0x1EF8), (0xC4, 0x61, 0x00E3), (0xC4, 0x65, 0x1EBD),
+    (0xC4, 0x69, 0x0129), (0xC4, 0x6E, 0x00F1), (0xC4, 0x6F, 0x00F5), (0xC4, 0x75, 0x0169), (0xC4, 0x76, 0x1E7D), (0xC4, 0x79, 0x1EF9),
+    (0xC5, 0x41, 0x0100), (0xC5, 0x45, 0x0112), (0xC5, 0x47, 0x1E20), (0xC5, 0x49, 0x012A), (0xC5, 0x4F, 0x014C), (0xC5, 0x55, 0x016A),
+    (0xC5, 0x59, 0x0232), (0xC5, 0x61, 0x0101), (0xC5, 0x65, 0x0113), (0xC5, 0x67, 0x1E21), (0xC5, 0x69, 0x012B), (0xC5, 0x6F, 0x014D),
+    (0xC5, 0x75, 0x016B), (0xC5, 0x79, 0x0233), (0xC5, 0xE1, 0x01E2), (0xC5, 0xF1, 0x01E3), (0xC6, 0x41, 0x0102), (0xC6, 0x45, 0x0114),
+    (0xC6, 0x47, 0x011E), (0xC6, 0x49, 0x012C), (0xC6, 0x4F, 0x014E), (0xC6, 0x55, 0x016C), (0xC6, 0x61, 0x0103), (0xC6, 0x65, 0x0115),
+    (0xC6, 0x67, 0x011F), (0xC6, 0x69, 0x012D), (0xC6, 0x6F, 0x014F), (0xC6, 0x75, 0x016D), (0xC7, 0x41, 0x0226), (0xC7, 0x42, 0x1E02),
+    (0xC7, 0x43, 0x010A), (0xC7, 0x44, 0x1E0A), (0xC7, 0x45, 0x0116), (0xC7, 0x46, 0x1E1E), (0xC7, 0x47, 0x0120), (0xC7, 0x48, 0x1E22),
+    (0xC7, 0x49, 0x0130), (0xC7, 0x4D, 0x1E40), (0xC7, 0x4E, 0x1E44), (0xC7, 0x4F, 0x022E), (0xC7, 0x50, 0x1E56), (0xC7, 0x52, 0x1E58),
+    (0xC7, 0x53, 0x1E60), (0xC7, 0x54, 0x1E6A), (0xC7, 0x57, 0x1E86), (0xC7, 0x58, 0x1E8A), (0xC7, 0x59, 0x1E8E), (0xC7, 0x5A, 0x017B),
+    (0xC7, 0x61, 0x0227), (0xC7, 0x62, 0x1E03), (0xC7, 0x63, 0x010B), (0xC7, 0x64, 0x1E0B), (0xC7, 0x65, 0x0117), (0xC7, 0x66, 0x1E1F),
+    (0xC7, 0x67, 0x0121), (0xC7, 0x68, 0x1E23), (0xC7, 0x6D, 0x1E41), (0xC7, 0x6E, 0x1E45), (0xC7, 0x6F, 0x022F), (0xC7, 0x70, 0x1E57),
+    (0xC7, 0x72, 0x1E59), (0xC7, 0x73, 0x1E61), (0xC7, 0x74, 0x1E6B), (0xC7, 0x77, 0x1E87), (0xC7, 0x78, 0x1E8B), (0xC7, 0x79, 0x1E8F),
+    (0xC7, 0x7A, 0x017C), (0xC8, 0x41, 0x00C4), (0xC8, 0x45, 0x00CB), (0xC8, 0x48, 0x1E26), (0xC8, 0x49, 0x00CF), (0xC8, 0x4F, 0x00D6),
+    (0xC8, 0x55, 0x00DC), (0xC8, 0x57, 0x1E84), (0xC8, 0x58, 0x1E8C), (0xC8, 0x59, 0x0178), (0xC8, 0x61, 0x00E4), (0xC8, 0x65, 0x00EB),
+    (0xC8, 0x68, 0x1E27), (0xC8, 0x69, 0x00EF), (0xC8, 0x6F, 0x00F6), (0xC8, 0x74, 0x1E97), (0xC8, 0x75, 0x00FC), (0xC8, 0x77, 0x1E85),
+    (0xC8, 0x78, 0x1E8D), (0xC8, 0x79, 0x00FF), (0xCA, 0x41, 0x00C5), (0xCA, 0x55, 0x016E), (0xCA, 0x61, 0x00E5), (0xCA, 0x75, 0x016F),
+    (0xCA, 0x77, 0x1E98), (0xCA, 0x79, 0x1E99), (0xCB, 0x43, 0x00C7), (0xCB, 0x44, 0x1E10), (0xCB, 0x45, 0x0228), (0xCB, 0x47, 0x0122),
+    (0xCB, 0x48, 0x1E28), (0xCB, 0x4B, 0x0136), (0xCB, 0x4C, 0x013B), (0xCB, 0x4E, 0x0145), (0xCB, 0x52, 0x0156), (0xCB, 0x53, 0x015E),
+    (0xCB, 0x54, 0x0162), (0xCB, 0x63, 0x00E7), (0xCB, 0x64, 0x1E11), (0xCB, 0x65, 0x0229), (0xCB, 0x67, 0x0123), (0xCB, 0x68, 0x1E29),
+    (0xCB, 0x6B, 0x0137), (0xCB, 0x6C, 0x013C), (0xCB, 0x6E, 0x0146), (0xCB, 0x72, 0x0157), (0xCB, 0x73, 0x015F), (0xCB, 0x74, 0x0163),
+    (0xCD, 0x4F, 0x0150), (0xCD, 0x55, 0x0170), (0xCD, 0x6F, 0x0151), (0xCD, 0x75, 0x0171), (0xCE, 0x41, 0x0104), (0xCE, 0x45, 0x0118),
+    (0xCE, 0x49, 0x012E), (0xCE, 0x4F, 0x01EA), (0xCE, 0x55, 0x0172), (0xCE, 0x61, 0x0105), (0xCE, 0x65, 0x0119), (0xCE, 0x69, 0x012F),
+    (0xCE, 0x6F, 0x01EB), (0xCE, 0x75, 0x0173), (0xCF, 0x41, 0x01CD), (0xCF, 0x43, 0x010C), (0xCF, 0x44, 0x010E), (0xCF, 0x45, 0x011A),
+    (0xCF, 0x47, 0x01E6), (0xCF, 0x48, 0x021E), (0xCF, 0x49, 0x01CF), (0xCF, 0x4B, 0x01E8), (0xCF, 0x4C, 0x013D), (0xCF, 0x4E, 0x0147),
+    (0xCF, 0x4F, 0x01D1), (0xCF, 0x52, 0x0158), (0xCF, 0x53, 0x0160), (0xCF, 0x54, 0x0164), (0xCF, 0x55, 0x01D3), (0xCF, 0x5A, 0x017D),
+    (0xCF, 0x61, 0x01CE), (0xCF, 0x63, 0x010D), (0xCF, 0x64, 0x010F), (0xCF, 0x65, 0x011B), (0xCF, 0x67, 0x01E7), (0xCF, 0x68, 0x021F),
+    (0xCF, 0x69, 0x01D0), (0xCF, 0x6A, 0x01F0), (0xCF, 0x6B, 0x01E9), (0xCF, 0x6C, 0x013E), (0xCF, 0x6E, 0x0148), (0xCF, 0x6F, 0x01D2),
+    (0xCF, 0x72, 0x0159), (0xCF, 0x73, 0x0161), (0xCF, 0x74, 0x0165), (0xCF, 0x75, 0x01D4), (0xCF, 0x7A, 0x017E)
+];
+
+/* --------------------------------- Testing -------------------------------- */
+
+#[test]
+fn test_iso_6937() {
+    let bytes = b"Caf\xC2e \xC8uber \xA8 \xCFSkoda";
+    assert_eq!(Iso6937::from_bytes(bytes, ()).unwrap().to_string().unwrap(), "Café über ¤ Škoda");
+    assert_eq!(Iso6937::from_string("Café über ¤ Škoda").unwrap().data, bytes);
+    // The combining sequences are written the same way.
+    assert_eq!(Iso6937::from_string("Cafe\u{301} u\u{308}ber").unwrap().data, b"Caf\xC2e \xC8uber");
+    assert_eq!(Iso6937::from_string("Łódź ß ½ ´").unwrap().data, b"\xE8\xC2od\xC2z \xFB \xBD \xC2 ");
+    assert_eq!(Iso6937::from_bytes(b"\xC2 \xCF ", ()).unwrap().to_string().unwrap(), "´ˇ");
+}
+
+#[test]
+fn test_iso_6937_combining_sequences() {
+    // Without a precomposed character, the combining diacritic follows the
+    // base character.
+    let decoded = Iso6937::from_bytes(b"\xC8q\xC7\xE9", ()).unwrap().try_to_utf_32().unwrap();
+    assert_eq!(decoded.data, vec![0x71, 0x0308, 0xD8, 0x0307]);
+    assert_eq!(Iso6937::from_utf_32(&decoded).data, b"\xC8q\xC7\xE9");
+    // The umlaut and the underline of T.61 are read.
+    assert_eq!(Iso6937::from_bytes(b"\xC9a\xCCb\xA4", ()).unwrap().try_to_utf_32().unwrap().data, vec![0xE4, 0x62, 0x0332, 0x24]);
+    // Only one diacritic can be written on a character.
+    let err = Iso6937::from_string("e\u{301}\u{301}").err().unwrap();
+    assert_eq!(err.kind, UnmappableCodepoint);
+    assert_eq!(err.position, Some(crate::unicode_encoding::ErrorPosition::Codepoint(2)));
+    assert_eq!(Iso6937::from_string("ǘ").err().unwrap().kind, UnmappableCodepoint);
+    assert_eq!(Iso6937::from_string("\u{301}e").err().unwrap().kind, UnmappableCodepoint);
+}
+
+#[test]
+fn test_invalid_iso_6937() {
+    assert_eq!(Iso6937::from_bytes(b"a\xC2", ()).err().unwrap().kind, MissingEncodedBytes);
+    assert_eq!(Iso6937::from_bytes(b"\xC0", ()).err().unwrap().kind, UndefinedCodepageByte);
+    let err = Iso6937::from_bytes(b"\xC2\xC2e", ()).err().unwrap();
+    assert_eq!(err.kind, InvalidMultiByteSequence);
+    assert_eq!(err.code_units, vec![0xC2]);
+    let (decoded, count) = Iso6937::from_bytes_lossy(b"\xC2\xC2e\xC2\n", (), DecodingPolicy::Skip).unwrap();
+    assert_eq!(count, 2);
+    assert_eq!(decoded.to_string().unwrap(), "é\n");
+    let (decoded, count) = Iso6937::decode_lossy(b"a\xC0b", (), DecodingPolicy::Replace).unwrap();
+    assert_eq!(count, 1);
+    assert_eq!(decoded.data, vec![0x61, 0xFFFD, 0x62]);
+}
+
+#[test]
+fn test_iso_6937_incremental() {
+    let bytes = b"\xC2e\xC8q\xCF";
+    let decoded = crate::incremental::decode_byte_per_byte(Iso6937::decoder(()).as_mut(), &bytes[..4]).unwrap();
+    assert_eq!(decoded.data, vec![0xE9, 0x71, 0x0308]);
+    assert_eq!(crate::incremental::decode_byte_per_byte(Iso6937::decoder(()).as_mut(), bytes).err().unwrap().kind, MissingEncodedBytes);
+    let mut encoder = Iso6937::encoder(());
+    let mut encoded = encoder.encode(&Utf32{data: vec![0x61, 0x65]}).unwrap();
+    encoded.extend(encoder.encode(&Utf32{data: vec![0x0301, 0x7A]}).unwrap());
+    encoded.extend(encoder.finish().unwrap());
+    assert_eq!(encoded, b"a\xC2ez");
+    let mut encoder = Iso6937::encoder(());
+    encoder.encode(&Utf32{data: vec![0x61, 0x65]}).unwrap();
+    encoder.encode(&Utf32{data: vec![0x0301, 0x263A]}).unwrap();
+    let err = encoder.finish().err().unwrap();
+    assert_eq!(err.position, Some(crate::unicode_encoding::ErrorPosition::Codepoint(3)));
+}
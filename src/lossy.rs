@@ -0,0 +1,133 @@
+/// The `lossy` module contains the policies used to decode data that is not
+/// fully valid, replacing the invalid sequences instead of giving up.
+
+use crate::unicode_encoding::UnicodeEncodingError;
+use crate::unicode_encoding::ErrorPosition;
+use crate::utf_16::compatible_codepoints;
+
+/// The code-point used to replace invalid data.
+pub const REPLACEMENT_CHARACTER: u32 = 0xFFFD;
+
+/// Tells what to do when invalid data is met while decoding.
+#[derive(Debug, Clone, Copy)]
+pub enum DecodingPolicy {
+    /// Stop the decoding and return the error.
+    Strict,
+
+    /// Replace each invalid sequence with U+FFFD. In UTF-8 and CESU-8, an
+    /// invalid sequence is the maximal subpart of an ill-formed glyph, as
+    /// described in the WHATWG Encoding Standard.
+    Replace,
+
+    /// Remove the invalid sequences.
+    Skip,
+
+    /// Replace each invalid byte with its value written as `\xHH`. Invalid
+    /// code-points are written as `\u{HHHH}`.
+    Escape,
+
+    /// Replace each invalid sequence with the code-points returned by the
+    /// given function.
+    Callback(fn(&UnicodeEncodingError) -> Vec<u32>),
+}
+
+impl DecodingPolicy {
+    /// Gives the code-points that should replace the invalid sequence
+    /// described by the error. If the policy is strict, the error is
+    /// returned.
+    pub fn handle(&self, err: &UnicodeEncodingError) -> Result<Vec<u32>, UnicodeEncodingError> {
+        match self {
+            DecodingPolicy::Strict => Err(err.clone()),
+            DecodingPolicy::Replace => Ok(vec![REPLACEMENT_CHARACTER]),
+            DecodingPolicy::Skip => Ok(Vec::new()),
+            DecodingPolicy::Escape => {
+                let escaped: String = match err.position {
+                    Some(ErrorPosition::Codepoint(_)) => err.code_units.iter().map(|x| format!("\\u{{{:04X}}}", x)).collect(),
+                    _ => err.code_units.iter().map(|x| format!("\\x{:02X}", x)).collect(),
+                };
+                Ok(escaped.chars().map(|x| x as u32).collect())
+            },
+            DecodingPolicy::Callback(f) => Ok(f(err)),
+        }
+    }
+}
+
+/* ---------------------------- Helper functions ---------------------------- */
+
+/// A function decoding the glyph starting at the given index of some bytes,
+/// returning the glyph and its length.
+pub(crate) type GlyphDecoder = fn(&[u8], usize) -> Result<(u32, usize), UnicodeEncodingError>;
+
+/// Decodes a stream of bytes glyph by glyph with the given function, the
+/// invalid sequences are handled with the policy. The function's errors must
+/// be located in the bytes and their code units must be the invalid sequence
+/// to skip. Returns the glyphs and the number of invalid sequences.
+pub(crate) fn decode_glyphs_lossy(bytes: &[u8], glyph_decoder: impl Fn(&[u8], usize) -> Result<(u32, usize), UnicodeEncodingError>, policy: DecodingPolicy) -> Result<(Vec<u32>, usize), UnicodeEncodingError> {
+    let mut ret: Vec<u32> = Vec::new();
+    let mut count: usize = 0;
+    let mut index: usize = 0;
+    while index < bytes.len() {
+        match glyph_decoder(bytes, index) {
+            Ok((glyph, len)) => {
+                ret.push(glyph);
+                index += len;
+            },
+            Err(err) => {
+                ret.extend(policy.handle(&err)?);
+                count += 1;
+                index += std::cmp::max(1, err.code_units.len());
+            },
+        }
+    }
+    return Ok((ret, count));
+}
+
+/// Ensures that a list of code-points passes the checks from
+/// `Utf32::check_sanity_utf32` by handling the faulty code-points with the
+/// policy. Returns the code-points and the number of faulty ones.
+pub(crate) fn sanitize(data: &[u32], policy: DecodingPolicy) -> Result<(Vec<u32>, usize), UnicodeEncodingError> {
+    let mut ret: Vec<u32> = Vec::new();
+    let mut count: usize = 0;
+    for i in 0..data.len() {
+        let next = if i + 1 == data.len() {
+            0
+        } else {
+            data[i+1]
+        };
+        match compatible_codepoints(data[i], next) {
+            // When the faulty code-point is the next one, it will be handled
+            // on the next iteration.
+            Err(err) if err.position == Some(ErrorPosition::Codepoint(0)) => {
+                let err = err.shifted(i).with_code_units(&[data[i]]);
+                ret.extend(policy.handle(&err)?);
+                count += 1;
+            },
+            _ => ret.push(data[i]),
+        }
+    }
+    return Ok((ret, count));
+}
+
+/* --------------------------------- Testing -------------------------------- */
+
+#[test]
+fn test_policies() {
+    use crate::unicode_encoding::UnicodeEncodingErrorKind::*;
+    let err = UnicodeEncodingError::new(InvalidUtf8Prefix).at_byte(3).with_code_units(&[0xFFu8]);
+    assert!(DecodingPolicy::Strict.handle(&err).is_err());
+    assert_eq!(DecodingPolicy::Replace.handle(&err).unwrap(), vec![0xFFFD]);
+    assert_eq!(DecodingPolicy::Skip.handle(&err).unwrap(), Vec::<u32>::new());
+    assert_eq!(DecodingPolicy::Escape.handle(&err).unwrap(), "\\xFF".chars().map(|x| x as u32).collect::<Vec<u32>>());
+    assert_eq!(DecodingPolicy::Callback(|_| vec![0x3F]).handle(&err).unwrap(), vec![0x3F]);
+}
+
+#[test]
+fn test_sanitize() {
+    let (data, count) = sanitize(&[0x41, 0xD800, 0xDC00, 0x42, 0x200000], DecodingPolicy::Replace).unwrap();
+    assert_eq!(data, vec![0x41, 0xFFFD, 0xDC00, 0x42, 0xFFFD]);
+    assert_eq!(count, 2);
+    // Only a high surrogate followed by a low one is ambiguous.
+    let (data, count) = sanitize(&[0xD800, 0xD800, 0xDC00, 0xD800], DecodingPolicy::Replace).unwrap();
+    assert_eq!(data, vec![0xD800, 0xFFFD, 0xDC00, 0xD800]);
+    assert_eq!(count, 1);
+}
@@ -1,12 +1,10 @@
 extern crate unicode_converter;
 use clap::Parser;
 
-use unicode_converter::unicode_encoding::UnicodeEncodingError;
-use unicode_converter::unicode_encoding::UnicodeEncoding;
-use unicode_converter::utf_8::Utf8;
-use unicode_converter::utf_1::Utf1;
-use unicode_converter::cesu_8::Cesu8;
-use unicode_converter::utf_16::Utf16;
+use unicode_converter::lossy::DecodingPolicy;
+use unicode_converter::encoding::Encoding;
+use unicode_converter::mapping::CharsetMapping;
+use unicode_converter::detection;
 use unicode_converter::utf_32::Utf32;
 
 /* ------------------------------- Exit codes ------------------------------- */
@@ -20,6 +18,14 @@ const ERR_IO: i32 = 2;
 // The encoding used is supported but the input data does not comply to it.
 const ERR_BAD_ENCODING: i32 = 3;
 
+// A mapping file does not comply to its format.
+const ERR_BAD_MAPPING: i32 = 4;
+
+/* -------------------------------- Constants ------------------------------- */
+
+// Number of bytes looked at to detect the encoding of the input file.
+const DETECTION_PREFIX_SIZE: usize = 4096;
+
 /* ---------------------------------- main ---------------------------------- */
 
 fn main() {
@@ -28,32 +34,61 @@ fn main() {
         "-" => "/dev/stdin",
         x => x,
     };
-    let decoded_message: Utf32 = match try_to_read_with_encoding(input_file, &arg.decoding_input) {
-        Some(x) => match x {
-            Ok(y) => match y {
-                Ok(z) => z,
-                Err(z) => {
-                    eprintln!("Error, invalid {} file.", arg.input_file);
-                    eprintln!("The error is: {:?}", z);
-                    std::process::exit(ERR_BAD_ENCODING);
-                },
-            },
-            Err(y) => {
-                eprintln!("Error, unable to read input file: {}.", y);
-                std::process::exit(ERR_IO);
-            },
+    let policy = match &arg.on_invalid as &str {
+        "replace" => DecodingPolicy::Replace,
+        "skip" => DecodingPolicy::Skip,
+        "escape" => DecodingPolicy::Escape,
+        _ => DecodingPolicy::Strict,
+    };
+    let mappings: Vec<Encoding> = arg.mapping_file.iter().map(|x| load_mapping(x)).collect();
+    let auto_detection = arg.decoding_input.eq_ignore_ascii_case("auto");
+    let input_encoding = if auto_detection {
+        None
+    } else {
+        Some(get_encoding(&arg.decoding_input, &mappings))
+    };
+    let output_encoding = get_encoding(&arg.encoding_output, &mappings);
+    let input_bytes = match std::fs::read(input_file) {
+        Ok(x) => x,
+        Err(x) => {
+            eprintln!("Error, unable to read input file: {}.", x);
+            std::process::exit(ERR_IO);
         },
-        None => {
-            eprintln!("Error, unknown input encoding.");
-            std::process::exit(ERR_UNKNOW_ENCODING);
+    };
+    let (input_encoding, strip_bom) = match input_encoding {
+        Some(x) => (x, arg.strip_bom),
+        None => detect_encoding(&input_bytes, &arg.input_file, arg.strip_bom),
+    };
+    let (input_encoding, input_bytes) = if strip_bom {
+        input_encoding.strip_bom(&input_bytes)
+    } else {
+        (input_encoding, input_bytes.as_slice())
+    };
+    let decoded_message: Utf32 = match input_encoding.decode_lossy(input_bytes, policy) {
+        Ok((x, invalid_sequences)) => {
+            if invalid_sequences > 0 {
+                eprintln!("Warning, {} invalid sequences were found in {}.", invalid_sequences, arg.input_file);
+            }
+            x
+        },
+        Err(x) => {
+            eprintln!("Error, invalid {} file.", arg.input_file);
+            eprintln!("The error is: {}", x);
+            std::process::exit(ERR_BAD_ENCODING);
         },
     };
-    let encoded_stream = match try_to_encode_data(&decoded_message, &arg.encoding_output) {
-        Some(x) => x,
-        None => {
-            eprintln!("Error, unknown output encoding.");
-            std::process::exit(ERR_UNKNOW_ENCODING);
-        }
+    let encoded_stream = if arg.add_bom {
+        output_encoding.encode_with_bom(&decoded_message)
+    } else {
+        output_encoding.encode(&decoded_message)
+    };
+    let encoded_stream = match encoded_stream {
+        Ok(x) => x,
+        Err(x) => {
+            eprintln!("Error, the data can't be encoded in {}.", output_encoding);
+            eprintln!("The error is: {}", x);
+            std::process::exit(ERR_BAD_ENCODING);
+        },
     };
     match std::fs::write(&arg.output_file, &encoded_stream) {
         Ok(_) => {},
@@ -66,65 +101,68 @@ fn main() {
 
 /* ---------------------------- Helper functions ---------------------------- */
 
-/// Try to read a file with the encoding given as a string. If it works,
-/// returns it converted to UTF-32. The results are encapsulated the same was
-/// as the from_file function but in an option block where Node is returned if
-/// the type is not known
-fn try_to_read_with_encoding(filename: &str, encoding: &str)  -> Option<Result<Result<Utf32, UnicodeEncodingError>, std::io::Error>> {
-    macro_rules! ttrwe_case {
-        ($type: ty, $big_engian: expr) => {
-            {
-                let encoded = <$type>::from_file(filename, $big_engian);
-                match encoded {
-                    Ok(x) => match x {
-                        Ok(y) => Some(Ok(Ok(y.to_utf_32()))),
-                        Err(y) => Some(Ok(Err(y))),
-                    }
-                    Err(x) => Some(Err(x)),
-                }
-            }
-        }
-    }
-    match encoding {
-        "UTF-8" => ttrwe_case!(Utf8, false),
-        "UTF-1" => ttrwe_case!(Utf1, false),
-        "CESU-8" => ttrwe_case!(Cesu8, false),
-        "UTF-16" => ttrwe_case!(Utf16, false),
-        "UTF-32" => ttrwe_case!(Utf32, false),
-        "UTF-16_be" => ttrwe_case!(Utf16, true),
-        "UTF-32_be" => ttrwe_case!(Utf32, true),
-        _ => None,
+/// Finds the encoding with the given name, looking at the charsets loaded
+/// from mapping files first. If there is none, the program exits with an
+/// error.
+fn get_encoding(name: &str, mappings: &[Encoding]) -> Encoding {
+    let mapping = mappings.iter().find(|x| x.name().eq_ignore_ascii_case(name)).copied();
+    match mapping.or_else(|| Encoding::from_name(name)) {
+        Some(x) => x,
+        None => {
+            eprintln!("Error, unknown encoding {}.", name);
+            std::process::exit(ERR_UNKNOW_ENCODING);
+        },
     }
 }
 
-/// Try to convert an UTF-32 in the encoding given as an argument string.
-/// If the encoding is not valid, None is returned. Then, the data is converted
-/// to a string of bytes.
-fn try_to_encode_data(utf32: &Utf32, encoding: &str) -> Option<Vec<u8>> {
-    macro_rules! tted_case {
-        ($type: ty, $big_engian: expr) => {
-            Some(<$type>::from_utf_32(utf32).to_bytes($big_engian))
-        }
+/// Loads a charset from a mapping file. If the file can't be read, the program
+/// exits with an error.
+fn load_mapping(filename: &str) -> Encoding {
+    match CharsetMapping::from_file(filename) {
+        Ok(Ok(x)) => x.into_encoding(),
+        Ok(Err(x)) => {
+            eprintln!("Error, invalid mapping file {}: {}.", filename, x);
+            std::process::exit(ERR_BAD_MAPPING);
+        },
+        Err(x) => {
+            eprintln!("Error, unable to read mapping file: {}.", x);
+            std::process::exit(ERR_IO);
+        },
     }
-    match encoding {
-        "UTF-8" => tted_case!(Utf8, false),
-        "UTF-1" => tted_case!(Utf1, false),
-        "CESU-8" => tted_case!(Cesu8, false),
-        "UTF-16" => tted_case!(Utf16, false),
-        "UTF-32" => tted_case!(Utf32, false),
-        "UTF-16_be" => tted_case!(Utf16, true),
-        "UTF-32_be" => tted_case!(Utf32, true),
-        _ => None,
+}
+
+/// Guesses the encoding of the input file. If it can't be guessed, the
+/// program exits with an error. Returns the encoding and if its BOM should be
+/// removed.
+fn detect_encoding(bytes: &[u8], filename: &str, strip_bom: bool) -> (Encoding, bool) {
+    let prefix = &bytes[..std::cmp::min(bytes.len(), DETECTION_PREFIX_SIZE)];
+    match detection::detect(prefix).first() {
+        Some(x) => (x.encoding, strip_bom || x.has_bom),
+        None => {
+            eprintln!("Error, unable to detect the encoding of {}.", filename);
+            std::process::exit(ERR_BAD_ENCODING);
+        },
     }
 }
 
 /* -------------------------------- Arguments ------------------------------- */
 
-/// A tool to convert Unicode text files between multiple Unicode encodings.
-/// The available encodings are UTF-8, UTF-1, CESU-8, UTF-16, and UTF-32.
-/// By default, the data is assumed to be little-endian, but for encodings with
-/// multi-byte words such as UTF-16 or UTF-32, you can add the `_be` suffix to
-/// indicate that you want to work with big-endian data.
+/// A tool to convert Unicode text files between multiple Unicode encodings. The
+/// available encodings are UTF-8, UTF-1, UTF-EBCDIC, CESU-8, MUTF-8, WTF-8,
+/// UTF-7, UTF-7-IMAP, SCSU, BOCU-1, GB18030, UTF-16, and UTF-32. The legacy
+/// codepages ISO-8859-1 to ISO-8859-16, windows-1250 to windows-1258, KOI8-R,
+/// KOI8-U, IBM437, IBM850, and macintosh are available as well, and so are the
+/// East Asian encodings Shift_JIS, EUC-JP, EUC-KR, Big5, GBK, ISO-2022-JP,
+/// ISO-2022-KR, and HZ-GB-2312, the Latin encoding ISO_6937, also known as
+/// T.61, and the GSM-7 alphabet of the SMS, one septet per byte, or
+/// GSM-7-packed. For encodings with multi-byte words such as UTF-16 or UTF-32,
+/// you can use the `LE` or `BE` variants, such as `UTF-16LE`, or add the `_be`
+/// suffix to tell the endianness of the data. Without suffix, UTF-16 and UTF-32
+/// take their endianness from the BOM at the beginning of the data, or are
+/// big-endian if there is none, and the BOM is removed. The usual aliases of
+/// the encodings, such as `utf8` or `csUnicode`, are accepted and the case of
+/// the names is ignored. Other charsets can be loaded from mapping files with
+/// `--mapping-file`.
 #[derive(Parser, Debug)]
 #[clap(about, long_about = None)]
 struct Args {
@@ -132,7 +170,8 @@ struct Args {
     #[clap(short, long)]
     input_file: String,
 
-    /// Input file encoding
+    /// Input file encoding. Use `auto` to guess it from the content of the
+    /// file
     #[clap(short, long)]
     decoding_input: String,
 
@@ -143,5 +182,24 @@ struct Args {
     /// Output file encoding
     #[clap(short, long)]
     encoding_output: String,
+
+    /// What to do with invalid sequences in the input file
+    #[clap(long, default_value = "strict", possible_values = ["strict", "replace", "skip", "escape"])]
+    on_invalid: String,
+
+    /// Remove the BOM at the beginning of the input file, if any
+    #[clap(long)]
+    strip_bom: bool,
+
+    /// Write a BOM at the beginning of the output file
+    #[clap(long)]
+    add_bom: bool,
+
+    /// Load a charset from a mapping file, in the format of the Unicode
+    /// Consortium or as an ICU `.ucm` file. The charset can then be used as
+    /// input or output encoding with the name given by the file, or else the
+    /// name of the file without extension
+    #[clap(long)]
+    mapping_file: Vec<String>,
 }
 
@@ -0,0 +1,512 @@
+/// The `mapping` module loads charsets at runtime from mapping files, for the
+/// charsets that no library ships, such as the ones of old terminals. It reads
+/// the mapping files published by the Unicode Consortium, where each line is
+/// like `0x41<TAB>0x0041`, and the `.ucm` files of ICU.
+
+use crate::unicode_encoding::UnicodeEncodingErrorKind::*;
+use crate::unicode_encoding::UnicodeEncodingError;
+use crate::utf_32::Utf32;
+use crate::utf_8;
+use crate::utf_16;
+use crate::encoding::Encoding;
+use crate::incremental::{Decoder, Encoder, SanityChecker};
+use crate::lossy::{self, DecodingPolicy};
+
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A charset loaded from a mapping file. Each character is one or more bytes
+/// standing for a single code-point, so both single-byte and double-byte
+/// charsets can be described.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct CharsetMapping {
+    name: String,
+    /// The code-point of each character.
+    decoding: BTreeMap<Vec<u8>, u32>,
+    /// The character of each code-point, including the fallbacks only used
+    /// to encode.
+    encoding: BTreeMap<u32, Vec<u8>>,
+    /// The beginnings of the characters of more than one byte.
+    prefixes: BTreeSet<Vec<u8>>,
+    /// The bytes written in place of the code-points that have no character.
+    substitution: Vec<u8>,
+}
+
+impl CharsetMapping {
+    /// Creates an empty mapping with the given name.
+    pub fn new(name: &str) -> Self {
+        return CharsetMapping{name: name.to_string(), decoding: BTreeMap::new(), encoding: BTreeMap::new(),
+                              prefixes: BTreeSet::new(), substitution: vec![b'?']};
+    }
+
+    /// Adds a character to the mapping. A character can be used only to
+    /// decode or only to encode, as the fallbacks of the mapping files.
+    /// When several characters have the same code-point, the first one is
+    /// used to encode it.
+    pub fn add(&mut self, bytes: &[u8], glyph: u32, decode: bool, encode: bool) -> Result<(), MappingFileErrorKind> {
+        if glyph > 0x10FFFF || utf_16::is_surrogate(glyph) {
+            return Err(MappingFileErrorKind::InvalidCodepoint);
+        }
+        if bytes.is_empty() {
+            return Err(MappingFileErrorKind::InvalidLine);
+        }
+        if decode {
+            match self.decoding.get(bytes) {
+                Some(x) if *x == glyph => {},
+                Some(_) => return Err(MappingFileErrorKind::DuplicateCharacter),
+                None => {
+                    // A character can't be the beginning of an other one, or
+                    // it would never be read.
+                    if self.prefixes.contains(bytes) || (1..bytes.len()).any(|x| self.decoding.contains_key(&bytes[..x])) {
+                        return Err(MappingFileErrorKind::AmbiguousCharacter);
+                    }
+                    self.decoding.insert(bytes.to_vec(), glyph);
+                    self.prefixes.extend((1..bytes.len()).map(|x| bytes[..x].to_vec()));
+                },
+            }
+        }
+        if encode {
+            self.encoding.entry(glyph).or_insert_with(|| bytes.to_vec());
+        }
+        return Ok(());
+    }
+
+    /// Sets the bytes written by `encode_lossy` in place of the code-points
+    /// that have no character. It is `?` by default.
+    pub fn set_substitution(&mut self, bytes: &[u8]) {
+        self.substitution = bytes.to_vec();
+    }
+
+    /// Reads a mapping file in the format of the Unicode Consortium. Each
+    /// line is the bytes of a character and its code-point in hexadecimal,
+    /// such as `0x8140<TAB>0x3000`, and the comments start with `#`. The
+    /// lines without code-point, which tell the unused bytes, are ignored.
+    /// The files with more columns, such as `JIS0208.TXT` which gives the
+    /// JIS code of each character before its code-point, are rejected.
+    pub fn parse_unicode_org(text: &str, name: &str) -> Result<Self, MappingFileError> {
+        let mut ret = CharsetMapping::new(name);
+        for (i, line) in text.lines().enumerate() {
+            let mut fields = line.split('#').next().unwrap().split_whitespace();
+            let (bytes, glyph) = match (fields.next(), fields.next()) {
+                (Some(bytes), Some(glyph)) => (bytes, glyph),
+                _ => continue,
+            };
+            let error = |kind| MappingFileError{line: i + 1, kind: kind};
+            if fields.next().is_some() {
+                return Err(error(MappingFileErrorKind::TooManyColumns));
+            }
+            if glyph.contains('+') {
+                return Err(error(MappingFileErrorKind::MultipleCodepoints));
+            }
+            let bytes = parse_hex_bytes(bytes).ok_or(error(MappingFileErrorKind::InvalidLine))?;
+            let glyph = glyph.strip_prefix("0x").and_then(|x| u32::from_str_radix(x, 16).ok()).ok_or(error(MappingFileErrorKind::InvalidLine))?;
+            ret.add(&bytes, glyph, true, true).map_err(error)?;
+        }
+        ret.substitution = ret.encoding.get(&(b'?' as u32)).cloned().unwrap_or(vec![b'?']);
+        return Ok(ret);
+    }
+
+    /// Reads a `.ucm` file of ICU. The name is the `<code_set_name>` of the
+    /// file, or the given one if there is none, and the `<subchar>` of the
+    /// file is used as substitution. The precision of each character tells
+    /// if it is a round-trip mapping (`|0`), a fallback only used to encode
+    /// (`|1`) or only used to decode (`|3`). The characters standing for the
+    /// single-byte substitution (`|2`) are ignored.
+    pub fn parse_ucm(text: &str, name: &str) -> Result<Self, MappingFileError> {
+        let mut ret = CharsetMapping::new(name);
+        let mut substitution: Option<Vec<u8>> = None;
+        let mut in_charmap = false;
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            let error = |kind| MappingFileError{line: i + 1, kind: kind};
+            if line.is_empty() {
+                continue;
+            } else if line == "CHARMAP" {
+                in_charmap = true;
+            } else if line == "END CHARMAP" {
+                in_charmap = false;
+            } else if !in_charmap {
+                let (key, value) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+                match key {
+                    "<code_set_name>" => ret.name = value.trim().trim_matches('"').to_string(),
+                    "<subchar>" => substitution = Some(parse_ucm_bytes(value.trim()).ok_or(error(MappingFileErrorKind::InvalidLine))?),
+                    _ => {},
+                }
+            } else {
+                let (glyphs, rest) = line.split_once(char::is_whitespace).ok_or(error(MappingFileErrorKind::InvalidLine))?;
+                let mut fields = rest.split_whitespace();
+                let bytes = fields.next().and_then(parse_ucm_bytes).ok_or(error(MappingFileErrorKind::InvalidLine))?;
+                let (decode, encode) = match fields.next() {
+                    None | Some("|0") => (true, true),
+                    Some("|1") => (false, true),
+                    Some("|2") => continue,
+                    Some("|3") => (true, false),
+                    Some(_) => return Err(error(MappingFileErrorKind::InvalidLine)),
+                };
+                if glyphs.matches("<U").count() > 1 {
+                    return Err(error(MappingFileErrorKind::MultipleCodepoints));
+                }
+                let glyph = glyphs.strip_prefix("<U").and_then(|x| x.strip_suffix('>')).and_then(|x| u32::from_str_radix(x, 16).ok());
+                ret.add(&bytes, glyph.ok_or(error(MappingFileErrorKind::InvalidLine))?, decode, encode).map_err(error)?;
+            }
+        }
+        ret.substitution = substitution.or(ret.encoding.get(&(b'?' as u32)).cloned()).unwrap_or(vec![b'?']);
+        return Ok(ret);
+    }
+
+    /// Reads a mapping file. The files whose name ends with `.ucm` are read
+    /// as ICU files, the other ones in the format of the Unicode Consortium.
+    /// Unless the file tells it, the name of the charset is the name of the
+    /// file without its extension. If the file can't be read, an io error is
+    /// returned.
+    pub fn from_file(filename: &str) -> Result<Result<Self, MappingFileError>, std::io::Error> {
+        let text = std::fs::read_to_string(filename)?;
+        let path = std::path::Path::new(filename);
+        let name = path.file_stem().map(|x| x.to_string_lossy()).unwrap_or_default();
+        if path.extension().is_some_and(|x| x.eq_ignore_ascii_case("ucm")) {
+            return Ok(Self::parse_ucm(&text, &name));
+        }
+        return Ok(Self::parse_unicode_org(&text, &name));
+    }
+
+    /// Makes an `Encoding` out of the mapping, to use it like the encodings
+    /// of this crate. As the encodings can be copied freely, the mapping is
+    /// kept until the end of the program. The errors of the `Encoding` tell
+    /// the name of the charset.
+    pub fn into_encoding(self) -> Encoding {
+        return Encoding::Mapped(Box::leak(Box::new(self)));
+    }
+
+    /// The name of the charset.
+    pub fn name(&self) -> &str {
+        return &self.name;
+    }
+
+    /// The maximum number of bytes needed to encode a single code-point.
+    pub fn max_bytes_per_codepoint(&self) -> usize {
+        return self.encoding.values().map(|x| x.len()).max().unwrap_or(1);
+    }
+
+    /// Decodes bytes in the charset. As the name of the mapping is not kept
+    /// until the end of the program, the errors don't tell the encoding,
+    /// unlike the ones of `into_encoding`.
+    pub fn decode(&self, bytes: &[u8]) -> Result<Utf32, UnicodeEncodingError> {
+        let mut data: Vec<u32> = Vec::new();
+        let mut index: usize = 0;
+        while index < bytes.len() {
+            let (glyph, len) = self.decode_character(bytes, index)?;
+            data.push(glyph);
+            index += len;
+        }
+        return Ok(Utf32{data: data});
+    }
+
+    /// Decodes bytes in the charset, handling the invalid sequences with the
+    /// policy. The number of invalid sequences is returned along the data.
+    pub fn decode_lossy(&self, bytes: &[u8], policy: DecodingPolicy) -> Result<(Utf32, usize), UnicodeEncodingError> {
+        let (data, count) = lossy::decode_glyphs_lossy(bytes, |x, y| self.decode_character(x, y), policy)?;
+        return Ok((Utf32{data: data}, count));
+    }
+
+    /// Encodes UTF-32 data in the charset. An error locating the first
+    /// code-point that has no character is returned if there is one.
+    pub fn encode(&self, data: &Utf32) -> Result<Vec<u8>, UnicodeEncodingError> {
+        let mut ret: Vec<u8> = Vec::new();
+        for (i, glyph) in data.data.iter().enumerate() {
+            match self.encoding.get(glyph) {
+                Some(bytes) => ret.extend_from_slice(bytes),
+                None => return Err(UnicodeEncodingError::new(UnmappableCodepoint).at_codepoint(i).with_code_units(&[*glyph])),
+            }
+        }
+        return Ok(ret);
+    }
+
+    /// Encodes UTF-32 data in the charset, writing the substitution in place
+    /// of the code-points that have no character. The number of replaced
+    /// code-points is returned along the data.
+    pub fn encode_lossy(&self, data: &Utf32) -> (Vec<u8>, usize) {
+        let mut ret: Vec<u8> = Vec::new();
+        let mut count: usize = 0;
+        for glyph in &data.data {
+            match self.encoding.get(glyph) {
+                Some(bytes) => ret.extend_from_slice(bytes),
+                None => {
+                    ret.extend_from_slice(&self.substitution);
+                    count += 1;
+                },
+            }
+        }
+        return (ret, count);
+    }
+
+    /// Creates a `Decoder` to decode the charset chunk by chunk.
+    pub fn decoder(&'static self) -> Box<dyn Decoder> {
+        return Box::new(MappingDecoder{mapping: self, pending: Vec::new(), consumed: 0});
+    }
+
+    /// Creates an `Encoder` to encode the charset chunk by chunk.
+    pub fn encoder(&'static self) -> Box<dyn Encoder> {
+        return Box::new(MappingEncoder{mapping: self, consumed: 0, checker: SanityChecker::new()});
+    }
+
+    /// Decodes the character starting at the given index of some bytes,
+    /// returning its code-point and its length.
+    fn decode_character(&self, data: &[u8], start: usize) -> Result<(u32, usize), UnicodeEncodingError> {
+        let mut len: usize = 1;
+        loop {
+            let sequence = &data[start..start+len];
+            if let Some(glyph) = self.decoding.get(sequence) {
+                return Ok((*glyph, len));
+            }
+            // Only the first byte is skipped, the next ones might start a
+            // valid character.
+            if !self.prefixes.contains(sequence) {
+                let kind = if len == 1 {UndefinedCodepageByte} else {InvalidMultiByteSequence};
+                return Err(UnicodeEncodingError::new(kind).at_byte(start).with_code_units(&data[start..start+1]));
+            }
+            if start + len == data.len() {
+                return Err(UnicodeEncodingError::new(MissingEncodedBytes).at_byte(start).with_code_units(&data[start..]));
+            }
+            len += 1;
+        }
+    }
+}
+
+/// Only the name is shown, as the tables are big.
+impl std::fmt::Debug for CharsetMapping {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        return write!(f, "CharsetMapping({})", self.name);
+    }
+}
+
+/// The error returned when a mapping file can't be read. It tells the line
+/// of the problem, starting from 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MappingFileError {
+    /// The line where the problem is.
+    pub line: usize,
+
+    /// What is wrong with the line.
+    pub kind: MappingFileErrorKind,
+}
+
+impl std::fmt::Display for MappingFileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        return write!(f, "{} at line {}", self.kind, self.line);
+    }
+}
+
+impl std::error::Error for MappingFileError {}
+
+/// The problems found in mapping files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MappingFileErrorKind {
+    /// The line is not in the format of the file.
+    InvalidLine,
+
+    /// The code-point is a surrogate or is above U+10FFFF.
+    InvalidCodepoint,
+
+    /// A character stands for several code-points, which is not supported.
+    MultipleCodepoints,
+
+    /// The bytes of a character were already given to another code-point.
+    DuplicateCharacter,
+
+    /// A character is the beginning of another one.
+    AmbiguousCharacter,
+
+    /// The line has more columns than the bytes and the code-point.
+    TooManyColumns,
+}
+
+impl std::fmt::Display for MappingFileErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let description = match self {
+            MappingFileErrorKind::InvalidLine => "invalid line",
+            MappingFileErrorKind::InvalidCodepoint => "invalid code-point",
+            MappingFileErrorKind::MultipleCodepoints => "character mapped to several code-points",
+            MappingFileErrorKind::DuplicateCharacter => "character mapped twice",
+            MappingFileErrorKind::AmbiguousCharacter => "character starting another character",
+            MappingFileErrorKind::TooManyColumns => "too many columns",
+        };
+        return write!(f, "{}", description);
+    }
+}
+
+/* --------------------------- Incremental coding --------------------------- */
+
+/// Decodes data in a loaded charset chunk by chunk. The bytes of a character
+/// split between two chunks are kept until the next one.
+pub struct MappingDecoder {
+    mapping: &'static CharsetMapping,
+    pending: Vec<u8>,
+    consumed: usize,
+}
+
+impl Decoder for MappingDecoder {
+    fn decode(&mut self, bytes: &[u8]) -> Result<Utf32, UnicodeEncodingError> {
+        self.pending.extend_from_slice(bytes);
+        let mapping = self.mapping;
+        let data = utf_8::decode_available_glyphs(&mut self.pending, &mut self.consumed, |x, y| mapping.decode_character(x, y)).map_err(|x| x.in_encoding(mapping.name()))?;
+        return Ok(Utf32{data: data});
+    }
+
+    fn finish(&mut self) -> Result<Utf32, UnicodeEncodingError> {
+        if !self.pending.is_empty() {
+            return Err(UnicodeEncodingError::new(MissingEncodedBytes).at_byte(self.consumed).with_code_units(&self.pending).in_encoding(self.mapping.name()));
+        }
+        return Ok(Utf32{data: Vec::new()});
+    }
+}
+
+/// Encodes data in a loaded charset chunk by chunk.
+pub struct MappingEncoder {
+    mapping: &'static CharsetMapping,
+    consumed: usize,
+    checker: SanityChecker,
+}
+
+impl Encoder for MappingEncoder {
+    fn encode(&mut self, data: &Utf32) -> Result<Vec<u8>, UnicodeEncodingError> {
+        self.checker.push_all(data)?;
+        let ret = self.mapping.encode(data).map_err(|x| x.shifted(self.consumed).in_encoding(self.mapping.name()))?;
+        self.consumed += data.data.len();
+        return Ok(ret);
+    }
+
+    fn finish(&mut self) -> Result<Vec<u8>, UnicodeEncodingError> {
+        self.checker.finish()?;
+        return Ok(Vec::new());
+    }
+}
+
+/* ---------------------------- Helper functions ---------------------------- */
+
+/// Reads bytes written in hexadecimal, such as `0x8140`.
+fn parse_hex_bytes(text: &str) -> Option<Vec<u8>> {
+    let digits = text.strip_prefix("0x").or(text.strip_prefix("0X"))?;
+    if digits.is_empty() || !digits.chars().all(|x| x.is_ascii_hexdigit()) {
+        return None;
+    }
+    // An odd number of digits is completed by a leading zero.
+    let digits = if digits.len() % 2 == 1 {format!("0{}", digits)} else {digits.to_string()};
+    return (0..digits.len()).step_by(2).map(|x| u8::from_str_radix(&digits[x..x+2], 16).ok()).collect();
+}
+
+/// Reads bytes written as in the `.ucm` files, such as `\x81\x40`.
+fn parse_ucm_bytes(text: &str) -> Option<Vec<u8>> {
+    let text = text.replace('+', "");
+    let mut parts = text.split("\\x");
+    if parts.next() != Some("") {
+        return None;
+    }
+    return parts.map(|x| if x.len() == 2 {u8::from_str_radix(x, 16).ok()} else {None}).collect();
+}
+
+/* --------------------------------- Testing -------------------------------- */
+
+#[cfg(test)]
+const TERMINAL_MAPPING: &str = "# A made-up terminal charset
+0x41\t0x0041\t# LATIN CAPITAL LETTER A
+0x42\t0x0042
+0x3F\t0x003F
+0x80\t0x00E9\t# LATIN SMALL LETTER E WITH ACUTE
+0x81\t\t#UNDEFINED
+0x8A40\t0x3042\t# HIRAGANA LETTER A
+0x8A41\t0x3044
+0x8B40\t0x2603
+";
+
+#[test]
+fn test_unicode_org_mapping() {
+    use crate::unicode_encoding::UnicodeEncoding;
+    let mapping = CharsetMapping::parse_unicode_org(TERMINAL_MAPPING, "terminal").unwrap();
+    assert_eq!(mapping.name(), "terminal");
+    let bytes = [0x41, 0x80, 0x8A, 0x40, 0x8A, 0x41, 0x8B, 0x40];
+    let text = Utf32::from_string("Aéあい☃").unwrap();
+    assert!(mapping.decode(&bytes).unwrap() == text);
+    assert_eq!(mapping.encode(&text).unwrap(), bytes);
+    assert_eq!(mapping.max_bytes_per_codepoint(), 2);
+    let err = mapping.encode(&Utf32::from_string("AZ").unwrap()).err().unwrap();
+    assert_eq!(err.kind, UnmappableCodepoint);
+    assert_eq!(err.position, Some(crate::unicode_encoding::ErrorPosition::Codepoint(1)));
+    // The errors only tell the charset once it is an `Encoding`.
+    assert_eq!(err.encoding, None);
+    let err = mapping.clone().into_encoding().encode(&Utf32::from_string("AZ").unwrap()).err().unwrap();
+    assert_eq!(err.encoding, Some("terminal"));
+    assert_eq!(mapping.encode_lossy(&Utf32::from_string("AZé").unwrap()), (vec![0x41, 0x3F, 0x80], 1));
+}
+
+#[test]
+fn test_invalid_mapped_data() {
+    use crate::unicode_encoding::UnicodeEncoding;
+    let mapping = CharsetMapping::parse_unicode_org(TERMINAL_MAPPING, "terminal").unwrap();
+    assert_eq!(mapping.decode(&[0x81]).err().unwrap().kind, UndefinedCodepageByte);
+    assert_eq!(mapping.decode(&[0x8A]).err().unwrap().kind, MissingEncodedBytes);
+    // The byte following an invalid lead byte is read again.
+    let err = mapping.decode(&[0x8A, 0x42]).err().unwrap();
+    assert_eq!(err.kind, InvalidMultiByteSequence);
+    assert_eq!(err.code_units, vec![0x8A]);
+    let (decoded, count) = mapping.decode_lossy(&[0x8A, 0x42, 0x81, 0x41], DecodingPolicy::Replace).unwrap();
+    assert_eq!(count, 2);
+    assert_eq!(decoded.to_string().unwrap(), "\u{FFFD}B\u{FFFD}A");
+}
+
+#[test]
+fn test_ucm_mapping() {
+    use crate::unicode_encoding::UnicodeEncoding;
+    let ucm = "<code_set_name>               \"IBM-TERM\"
+<mb_cur_max>                  2
+<subchar>                     \\x6F
+CHARMAP
+<U0041>  \\xC1 |0
+<U00E9>  \\x51 |0
+<U00C9>  \\x51 |1
+<U3042>  \\x0E\\x44\\x81 |0
+<U001A>  \\x3F |2
+<U00A0>  \\x41 |3
+END CHARMAP
+";
+    let mapping = CharsetMapping::parse_ucm(ucm, "ibm-term").unwrap();
+    assert_eq!(mapping.name(), "IBM-TERM");
+    // The fallback of É is only used to encode.
+    assert_eq!(mapping.encode(&Utf32::from_string("AéÉあ").unwrap()).unwrap(), vec![0xC1, 0x51, 0x51, 0x0E, 0x44, 0x81]);
+    assert_eq!(mapping.decode(&[0xC1, 0x51, 0x41]).unwrap().to_string().unwrap(), "Aé\u{A0}");
+    assert_eq!(mapping.encode(&Utf32::from_string("\u{A0}").unwrap()).err().unwrap().kind, UnmappableCodepoint);
+    assert_eq!(mapping.encode_lossy(&Utf32::from_string("\u{1A}").unwrap()), (vec![0x6F], 1));
+}
+
+#[test]
+fn test_invalid_mapping_files() {
+    let err = CharsetMapping::parse_unicode_org("0x41\t0x0041\n0x42\t0xD800\n", "x").err().unwrap();
+    assert_eq!(err, MappingFileError{line: 2, kind: MappingFileErrorKind::InvalidCodepoint});
+    let err = CharsetMapping::parse_unicode_org("0x41\t0x0041\n0x41\t0x0042\n", "x").err().unwrap();
+    assert_eq!(err.kind, MappingFileErrorKind::DuplicateCharacter);
+    let err = CharsetMapping::parse_unicode_org("0x81\t0x0041\n0x8140\t0x3000\n", "x").err().unwrap();
+    assert_eq!(err.kind, MappingFileErrorKind::AmbiguousCharacter);
+    let err = CharsetMapping::parse_unicode_org("0x41\t0x0041+0x0301\n", "x").err().unwrap();
+    assert_eq!(err.kind, MappingFileErrorKind::MultipleCodepoints);
+    // The three columns of JIS0208.TXT would map the bytes to the JIS code.
+    let err = CharsetMapping::parse_unicode_org("0x8140\t0x2121\t0x3000\t# IDEOGRAPHIC SPACE\n", "x").err().unwrap();
+    assert_eq!(err, MappingFileError{line: 1, kind: MappingFileErrorKind::TooManyColumns});
+    let err = CharsetMapping::parse_unicode_org("A\t0x0041\n", "x").err().unwrap();
+    assert_eq!(err.to_string(), "invalid line at line 1");
+    let err = CharsetMapping::parse_ucm("CHARMAP\n<U0041> 41 |0\nEND CHARMAP\n", "x").err().unwrap();
+    assert_eq!(err, MappingFileError{line: 2, kind: MappingFileErrorKind::InvalidLine});
+}
+
+#[test]
+fn test_mapping_incremental() {
+    use crate::unicode_encoding::UnicodeEncoding;
+    let mapping = CharsetMapping::parse_unicode_org(TERMINAL_MAPPING, "terminal").unwrap();
+    let encoding = mapping.into_encoding();
+    assert_eq!(encoding.name(), "terminal");
+    let decoded = crate::incremental::decode_byte_per_byte(encoding.decoder().as_mut(), &[0x8A, 0x40, 0x41, 0x8B, 0x40]).unwrap();
+    assert_eq!(decoded.to_string().unwrap(), "あA☃");
+    let mut encoder = encoding.encoder();
+    assert_eq!(encoder.encode(&Utf32::from_string("Aあ").unwrap()).unwrap(), vec![0x41, 0x8A, 0x40]);
+    let err = encoder.encode(&Utf32::from_string("AZ").unwrap()).err().unwrap();
+    assert_eq!(err.position, Some(crate::unicode_encoding::ErrorPosition::Codepoint(3)));
+    assert!(encoding.bom().is_empty());
+    assert_eq!(encoding.strip_bom(&[0x41]), (encoding, &[0x41][..]));
+}
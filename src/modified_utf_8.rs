@@ -0,0 +1,162 @@
+/// The Modified UTF-8 module handles the variant of CESU-8 used by Java in
+/// its class files, by JNI and by the Android DEX files.
+
+use crate::unicode_encoding::UnicodeEncodingErrorKind::*;
+use crate::unicode_encoding::UnicodeEncodingError;
+use crate::unicode_encoding::UnicodeEncoding;
+use crate::utf_32::Utf32;
+use crate::utf_8;
+use crate::cesu_8::{self, Cesu8Decoder};
+use crate::incremental::{Decoder, Encoder, SanityChecker};
+use crate::lossy::{self, DecodingPolicy};
+
+/// A wrapper for Modified UTF-8 encoded bytes. The supplementary characters
+/// are encoded as pairs of surrogates as in CESU-8 and U+0000 is encoded as
+/// `C0 80` so the encoded data never contains a `00` byte.
+pub struct ModifiedUtf8 {
+    pub data: Vec<u8>
+}
+
+impl UnicodeEncoding for ModifiedUtf8 {
+    const NAME: &'static str = "MUTF-8";
+    type Options = ();
+
+    /// Convert UTF-32 data to Modified UTF-8.
+    fn from_utf_32(data_utf_32: &Utf32) -> Self {
+        let mut data: Vec<u8> = Vec::new();
+        for glyph in &data_utf_32.data {
+            if *glyph == 0 {
+                data.extend_from_slice(&ENCODED_NUL);
+            } else {
+                data.extend(cesu_8::utf_32_glyph_to_cesu_8(*glyph));
+            }
+        }
+        return ModifiedUtf8{data: data};
+    }
+
+    /// Convert Modified UTF-8 data to UTF-32.
+    fn try_to_utf_32(&self) -> Result<Utf32, UnicodeEncodingError> {
+        let mut glyphs: Vec<u32> = Vec::new();
+        let mut index: usize = 0;
+        while index < self.data.len() {
+            let (glyph, len) = modified_utf_8_glyph_to_utf_32(&self.data, index).map_err(|x| x.in_encoding(Self::NAME))?;
+            glyphs.push(glyph);
+            index += len;
+        }
+        return Ok(Utf32{data: cesu_8::pair_surrogates(&glyphs)});
+    }
+
+    /// Convert the instance of `ModifiedUtf8` to a vector of bytes.
+    /// No transformation is needed.
+    fn to_bytes(&self, _options: ()) -> Vec<u8> {
+        return self.data.clone();
+    }
+
+    /// Consider a stream of Modified UTF-8 encoded bytes and turn it into a
+    /// `ModifiedUtf8` type. The bytes are decoded to ensure they are valid.
+    fn from_bytes_no_check(bytes: &[u8], _options: ()) -> Result<Self, UnicodeEncodingError> {
+        let ret = ModifiedUtf8{data: bytes.to_vec()};
+        ret.try_to_utf_32()?;
+        return Ok(ret);
+    }
+
+    /// Decodes Modified UTF-8 glyph by glyph, replacing the invalid
+    /// sequences.
+    fn from_bytes_lossy(bytes: &[u8], _options: (), policy: DecodingPolicy) -> Result<(Self, usize), UnicodeEncodingError> {
+        let (glyphs, count) = lossy::decode_glyphs_lossy(bytes, modified_utf_8_glyph_to_utf_32, policy).map_err(|x| x.in_encoding(Self::NAME))?;
+        let (data, sanitized) = lossy::sanitize(&cesu_8::pair_surrogates(&glyphs), policy).map_err(|x| x.in_encoding(Self::NAME))?;
+        return Ok((Self::from_utf_32(&Utf32{data: data}), count + sanitized));
+    }
+
+    fn decoder(_options: ()) -> Box<dyn Decoder> {
+        return Box::new(Cesu8Decoder::with_glyph_decoder(modified_utf_8_glyph_to_utf_32, Self::NAME));
+    }
+
+    fn encoder(_options: ()) -> Box<dyn Encoder> {
+        return Box::new(ModifiedUtf8Encoder{checker: SanityChecker::new()});
+    }
+}
+
+/* --------------------------- Incremental coding --------------------------- */
+
+/// Encodes Modified UTF-8 data chunk by chunk.
+pub struct ModifiedUtf8Encoder {
+    checker: SanityChecker,
+}
+
+impl Encoder for ModifiedUtf8Encoder {
+    fn encode(&mut self, data: &Utf32) -> Result<Vec<u8>, UnicodeEncodingError> {
+        self.checker.push_all(data)?;
+        return Ok(ModifiedUtf8::from_utf_32(data).data);
+    }
+
+    fn finish(&mut self) -> Result<Vec<u8>, UnicodeEncodingError> {
+        self.checker.finish()?;
+        return Ok(Vec::new());
+    }
+}
+
+/* ---------------------------- Helper functions ---------------------------- */
+
+/// The two-byte overlong form used to encode U+0000.
+const ENCODED_NUL: [u8; 2] = [0xC0, 0x80];
+
+/// Decodes the first glyph of some Modified UTF-8 data. The surrogates are
+/// returned as they are and paired later. Only the shortest forms are
+/// accepted, except for U+0000 which must be `C0 80`, and the four-byte forms
+/// of UTF-8 are not used.
+fn modified_utf_8_glyph_to_utf_32(data: &[u8], start: usize) -> Result<(u32, usize), UnicodeEncodingError> {
+    match data[start] {
+        0x00 => Err(UnicodeEncodingError::new(RawNulByte).at_byte(start).with_code_units(&data[start..start+1])),
+        0xC0 if data.len() == start + 1 => Err(UnicodeEncodingError::new(MissingEncodedBytes).at_byte(start).with_code_units(&data[start..])),
+        0xC0 if data[start+1] == ENCODED_NUL[1] => Ok((0, 2)),
+        // The surrogates are encoded as any other code-point of the BMP.
+        0xED => utf_8::utf_8_glyph_to_utf_32(data, start),
+        0xF0..=0xF4 => Err(UnicodeEncodingError::new(InvalidUtf8Prefix).at_byte(start).with_code_units(&data[start..start+1])),
+        _ => utf_8::utf_8_glyph_to_utf_32_strict(data, start),
+    }
+}
+
+/* --------------------------------- Testing -------------------------------- */
+
+#[test]
+fn test_modified_utf_8_nul() {
+    let mutf8 = ModifiedUtf8::from_utf_32(&Utf32{data: vec![0x41, 0x00, 0x42]});
+    assert_eq!(mutf8.data, vec![0x41, 0xC0, 0x80, 0x42]);
+    assert_eq!(ModifiedUtf8::from_bytes(&mutf8.data, ()).unwrap().to_utf_32().data, vec![0x41, 0x00, 0x42]);
+    assert_eq!(ModifiedUtf8::from_bytes(&[0x41, 0x00], ()).err().unwrap().kind, RawNulByte);
+    // Other overlong forms are still rejected.
+    assert_eq!(ModifiedUtf8::from_bytes(&[0xC0, 0x81], ()).err().unwrap().kind, OverlongUtf8Encoding);
+}
+
+#[test]
+fn test_modified_utf_8_supplementary() {
+    let bytes: Vec<u8> = vec![0x45, 0xED, 0xA0, 0x81, 0xED, 0xB0, 0x80];
+    let mutf8 = ModifiedUtf8::from_bytes(&bytes, ()).unwrap();
+    assert_eq!(mutf8.to_utf_32().data, vec![0x45, 0x10400]);
+    assert_eq!(ModifiedUtf8::from_utf_32(&Utf32{data: vec![0x45, 0x10400]}).data, bytes);
+    // The four-byte forms of UTF-8 are not used.
+    assert_eq!(ModifiedUtf8::from_bytes(&[0xF0, 0x90, 0x90, 0x80], ()).err().unwrap().kind, InvalidUtf8Prefix);
+}
+
+#[test]
+fn test_modified_utf_8_incremental() {
+    let bytes: Vec<u8> = vec![0xC0, 0x80, 0xED, 0xA0, 0x81, 0xED, 0xB0, 0x80, 0xC3, 0xA9];
+    let mut decoder = ModifiedUtf8::decoder(());
+    let decoded = crate::incremental::decode_byte_per_byte(decoder.as_mut(), &bytes).unwrap();
+    assert_eq!(decoded.data, vec![0x00, 0x10400, 0xE9]);
+
+    let mut encoder = ModifiedUtf8::encoder(());
+    let mut encoded = encoder.encode(&Utf32{data: vec![0x00, 0x10400]}).unwrap();
+    encoded.extend(encoder.encode(&Utf32{data: vec![0xE9]}).unwrap());
+    encoded.extend(encoder.finish().unwrap());
+    assert_eq!(encoded, bytes);
+}
+
+#[test]
+fn test_modified_utf_8_lossy() {
+    let bytes: Vec<u8> = vec![0x41, 0x00, 0xC0, 0x80];
+    let (mutf8, count) = ModifiedUtf8::from_bytes_lossy(&bytes, (), DecodingPolicy::Replace).unwrap();
+    assert_eq!(mutf8.to_utf_32().data, vec![0x41, 0xFFFD, 0x00]);
+    assert_eq!(count, 1);
+}
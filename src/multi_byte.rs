@@ -0,0 +1,288 @@
+/// The `multi_byte` module handles the legacy encodings of East Asia where a
+/// character is one or more bytes, such as Shift_JIS or EUC-KR. Except
+/// GB18030, they can't encode every code-point, like the single-byte
+/// codepages.
+
+use crate::unicode_encoding::UnicodeEncodingErrorKind::*;
+use crate::unicode_encoding::UnicodeEncodingError;
+use crate::unicode_encoding::UnicodeEncoding;
+use crate::utf_32::Utf32;
+use crate::utf_8;
+use crate::incremental::{Decoder, Encoder, SanityChecker};
+use crate::lossy::{self, DecodingPolicy};
+
+use std::marker::PhantomData;
+
+/// The code-points returned by `MultiByteCodec::decode_character` from this
+/// value on stand for the sequences of `MultiByteCodec::SEQUENCES`.
+pub const SEQUENCE_BASE: u32 = 0x110000;
+
+/// A legacy multi-byte encoding, described by the functions reading and
+/// writing a character. The encodings shipped with this crate are `ShiftJis`,
+/// `EucJp`, `EucKr`, `Big5`, `Gbk` and `Gb18030`.
+pub trait MultiByteCodec {
+    /// The name of the encoding, used in error messages.
+    const NAME: &'static str;
+
+    /// The characters decoded to two code-points, such as the letters with
+    /// two diacritics of Big5.
+    const SEQUENCES: &'static [[u32; 2]] = &[];
+
+    /// Tells if U+FEFF is used as a BOM, which is only the case of GB18030.
+    const HAS_BOM: bool = false;
+
+    /// Decodes the character starting at the given index of some bytes,
+    /// returning its code-point and its length. A code-point of
+    /// `SEQUENCE_BASE + i` stands for the i-th sequence of `SEQUENCES`.
+    /// The errors must be located in the bytes and their code units must be
+    /// the invalid sequence to skip. A character cut by the end of the
+    /// bytes is reported with a `MissingEncodedBytes` error.
+    fn decode_character(data: &[u8], start: usize) -> Result<(u32, usize), UnicodeEncodingError>;
+
+    /// Writes the bytes of a code-point. Returns false if the code-point
+    /// can't be encoded.
+    fn encode_character(glyph: u32, bytes: &mut Vec<u8>) -> bool;
+}
+
+/// A wrapper for data encoded in a legacy multi-byte encoding. As with the
+/// codepages, encoding the code-points that have no character fails with
+/// `try_from_utf_32`, while `from_utf_32` replaces them with `?`.
+pub struct MultiByteEncoding<C: MultiByteCodec> {
+    pub data: Vec<u8>,
+    codec: PhantomData<C>,
+}
+
+impl<C: MultiByteCodec> MultiByteEncoding<C> {
+    /// Encodes UTF-32 data, replacing the code-points that have no character
+    /// with the given byte. The number of replaced code-points is returned
+    /// along the data.
+    pub fn from_utf_32_lossy(data_utf_32: &Utf32, replacement: u8) -> (Self, usize) {
+        let mut data: Vec<u8> = Vec::new();
+        let mut count: usize = 0;
+        for glyph in &data_utf_32.data {
+            if !C::encode_character(*glyph, &mut data) {
+                data.push(replacement);
+                count += 1;
+            }
+        }
+        return (MultiByteEncoding{data: data, codec: PhantomData}, count);
+    }
+}
+
+impl<C: MultiByteCodec> UnicodeEncoding for MultiByteEncoding<C> {
+    const NAME: &'static str = C::NAME;
+    type Options = ();
+
+    /// Convert UTF-32 data to the encoding. The code-points that have no
+    /// character are replaced with `?`.
+    fn from_utf_32(data_utf_32: &Utf32) -> Self {
+        return Self::from_utf_32_lossy(data_utf_32, REPLACEMENT_BYTE).0;
+    }
+
+    /// Convert UTF-32 data to the encoding. An error locating the first
+    /// code-point that has no character is returned if there is one.
+    fn try_from_utf_32(data_utf_32: &Utf32) -> Result<Self, UnicodeEncodingError> {
+        let data = encode_glyphs::<C>(&data_utf_32.data).map_err(|x| x.in_encoding(Self::NAME))?;
+        return Ok(MultiByteEncoding{data: data, codec: PhantomData});
+    }
+
+    /// Convert data in the encoding to UTF-32.
+    fn try_to_utf_32(&self) -> Result<Utf32, UnicodeEncodingError> {
+        let mut data: Vec<u32> = Vec::new();
+        let mut index: usize = 0;
+        while index < self.data.len() {
+            let (glyph, len) = C::decode_character(&self.data, index).map_err(|x| x.in_encoding(Self::NAME))?;
+            data.push(glyph);
+            index += len;
+        }
+        return Ok(Utf32{data: expand_sequences::<C>(data)});
+    }
+
+    /// Convert the instance of `MultiByteEncoding` to a vector of bytes.
+    /// No transformation is needed.
+    fn to_bytes(&self, _options: ()) -> Vec<u8> {
+        return self.data.clone();
+    }
+
+    /// Consider a stream of bytes in the encoding and turn it into a
+    /// `MultiByteEncoding` type. The bytes are decoded to ensure they are
+    /// valid.
+    fn from_bytes_no_check(bytes: &[u8], _options: ()) -> Result<Self, UnicodeEncodingError> {
+        let ret = MultiByteEncoding{data: bytes.to_vec(), codec: PhantomData};
+        ret.try_to_utf_32()?;
+        return Ok(ret);
+    }
+
+    /// Decodes the data character by character, replacing the invalid
+    /// sequences. The replacements that can't be encoded, such as U+FFFD, are
+    /// stored as `?`, use `decode_lossy` to keep them.
+    fn from_bytes_lossy(bytes: &[u8], options: (), policy: DecodingPolicy) -> Result<(Self, usize), UnicodeEncodingError> {
+        let (data, count) = Self::decode_lossy(bytes, options, policy)?;
+        return Ok((Self::from_utf_32(&data), count));
+    }
+
+    /// Decodes the data character by character to UTF-32, replacing the
+    /// invalid sequences.
+    fn decode_lossy(bytes: &[u8], _options: (), policy: DecodingPolicy) -> Result<(Utf32, usize), UnicodeEncodingError> {
+        let (data, count) = lossy::decode_glyphs_lossy(bytes, C::decode_character, policy).map_err(|x| x.in_encoding(Self::NAME))?;
+        return Ok((Utf32{data: expand_sequences::<C>(data)}, count));
+    }
+
+    /// The multi-byte encodings have no BOM, except GB18030.
+    fn bom(_options: ()) -> Vec<u8> {
+        let mut ret: Vec<u8> = Vec::new();
+        if C::HAS_BOM {
+            C::encode_character(0xFEFF, &mut ret);
+        }
+        return ret;
+    }
+
+    fn decoder(_options: ()) -> Box<dyn Decoder> where Self: 'static {
+        return Box::new(MultiByteDecoder::<C>{pending: Vec::new(), consumed: 0, codec: PhantomData});
+    }
+
+    fn encoder(_options: ()) -> Box<dyn Encoder> where Self: 'static {
+        return Box::new(MultiByteEncoder::<C>{consumed: 0, checker: SanityChecker::new(), codec: PhantomData});
+    }
+}
+
+/* --------------------------- Incremental coding --------------------------- */
+
+/// Decodes data in a multi-byte encoding chunk by chunk. The bytes of a
+/// character split between two chunks are kept until the next one.
+pub struct MultiByteDecoder<C: MultiByteCodec> {
+    pending: Vec<u8>,
+    consumed: usize,
+    codec: PhantomData<C>,
+}
+
+impl<C: MultiByteCodec> Decoder for MultiByteDecoder<C> {
+    fn decode(&mut self, bytes: &[u8]) -> Result<Utf32, UnicodeEncodingError> {
+        self.pending.extend_from_slice(bytes);
+        let data = utf_8::decode_available_glyphs(&mut self.pending, &mut self.consumed, C::decode_character).map_err(|x| x.in_encoding(C::NAME))?;
+        return Ok(Utf32{data: expand_sequences::<C>(data)});
+    }
+
+    fn finish(&mut self) -> Result<Utf32, UnicodeEncodingError> {
+        if !self.pending.is_empty() {
+            return Err(UnicodeEncodingError::new(MissingEncodedBytes).at_byte(self.consumed).with_code_units(&self.pending).in_encoding(C::NAME));
+        }
+        return Ok(Utf32{data: Vec::new()});
+    }
+}
+
+/// Encodes data in a multi-byte encoding chunk by chunk.
+pub struct MultiByteEncoder<C: MultiByteCodec> {
+    consumed: usize,
+    checker: SanityChecker,
+    codec: PhantomData<C>,
+}
+
+impl<C: MultiByteCodec> Encoder for MultiByteEncoder<C> {
+    fn encode(&mut self, data: &Utf32) -> Result<Vec<u8>, UnicodeEncodingError> {
+        self.checker.push_all(data)?;
+        let ret = MultiByteEncoding::<C>::try_from_utf_32(data).map_err(|x| x.shifted(self.consumed))?;
+        self.consumed += data.data.len();
+        return Ok(ret.data);
+    }
+
+    fn finish(&mut self) -> Result<Vec<u8>, UnicodeEncodingError> {
+        self.checker.finish()?;
+        return Ok(Vec::new());
+    }
+}
+
+/* ---------------------------- Helper functions ---------------------------- */
+
+/// The byte written in place of the code-points that have no character.
+const REPLACEMENT_BYTE: u8 = b'?';
+
+/// Encodes code-points, returning an error locating the first one that has
+/// no character.
+fn encode_glyphs<C: MultiByteCodec>(glyphs: &[u32]) -> Result<Vec<u8>, UnicodeEncodingError> {
+    let mut ret: Vec<u8> = Vec::new();
+    for (i, glyph) in glyphs.iter().enumerate() {
+        if !C::encode_character(*glyph, &mut ret) {
+            return Err(UnicodeEncodingError::new(UnmappableCodepoint).at_codepoint(i).with_code_units(&[*glyph]));
+        }
+    }
+    return Ok(ret);
+}
+
+/// Replaces the code-points standing for the sequences of a codec with the
+/// sequences.
+fn expand_sequences<C: MultiByteCodec>(glyphs: Vec<u32>) -> Vec<u32> {
+    if C::SEQUENCES.is_empty() {
+        return glyphs;
+    }
+    let mut ret: Vec<u32> = Vec::new();
+    for glyph in glyphs {
+        match glyph.checked_sub(SEQUENCE_BASE).and_then(|x| C::SEQUENCES.get(x as usize)) {
+            Some(sequence) => ret.extend_from_slice(sequence),
+            None => ret.push(glyph),
+        }
+    }
+    return ret;
+}
+
+/// The error for a character of two bytes starting at `start` that is not
+/// valid. As in the WHATWG Encoding Standard, an ASCII second byte is not a
+/// part of the invalid sequence, so it is read again as a character.
+pub(crate) fn invalid_pair(data: &[u8], start: usize) -> UnicodeEncodingError {
+    let len = if data[start+1] < 0x80 {1} else {2};
+    return UnicodeEncodingError::new(InvalidMultiByteSequence).at_byte(start).with_code_units(&data[start..start+len]);
+}
+
+/// The error for a byte that can't start a character.
+pub(crate) fn invalid_byte(data: &[u8], start: usize) -> UnicodeEncodingError {
+    return UnicodeEncodingError::new(InvalidMultiByteSequence).at_byte(start).with_code_units(&data[start..start+1]);
+}
+
+/// The error for a character cut by the end of the data.
+pub(crate) fn missing_bytes(data: &[u8], start: usize) -> UnicodeEncodingError {
+    return UnicodeEncodingError::new(MissingEncodedBytes).at_byte(start).with_code_units(&data[start..]);
+}
+
+/* --------------------------------- Testing -------------------------------- */
+
+#[test]
+fn test_multi_byte_errors() {
+    use crate::shift_jis::ShiftJis;
+    // An invalid character followed by ASCII only takes its lead byte.
+    let err = ShiftJis::from_bytes(&[0x41, 0x85, 0x41], ()).err().unwrap();
+    assert_eq!(err.kind, InvalidMultiByteSequence);
+    assert_eq!(err.position, Some(crate::unicode_encoding::ErrorPosition::Byte(1)));
+    assert_eq!(err.code_units, vec![0x85]);
+    let (decoded, count) = ShiftJis::from_bytes_lossy(&[0x41, 0x85, 0x41, 0x85, 0x9F, 0x82], (), DecodingPolicy::Escape).unwrap();
+    assert_eq!(count, 3);
+    assert_eq!(decoded.data, b"A\\x85A\\x85\\x9F\\x82");
+    let err = ShiftJis::from_bytes(&[0x82, 0xA0, 0x82], ()).err().unwrap();
+    assert_eq!(err.kind, MissingEncodedBytes);
+    assert_eq!(err.position, Some(crate::unicode_encoding::ErrorPosition::Byte(2)));
+}
+
+#[test]
+fn test_multi_byte_decode_lossy() {
+    use crate::encoding::Encoding;
+    for encoding in [Encoding::ShiftJis, Encoding::EucJp, Encoding::EucKr, Encoding::Big5, Encoding::Gbk] {
+        let (decoded, count) = encoding.decode_lossy(&[0x41, 0xFF, 0x42], DecodingPolicy::Replace).unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(decoded.data, vec![0x41, 0xFFFD, 0x42]);
+    }
+}
+
+#[test]
+fn test_multi_byte_incremental() {
+    use crate::euc_jp::EucJp;
+    let mut decoder = EucJp::decoder(());
+    let decoded = crate::incremental::decode_byte_per_byte(decoder.as_mut(), &[0xC6, 0xFC, 0x8E, 0xB1, 0x8F, 0xB0, 0xA1]).unwrap();
+    assert_eq!(decoded.to_string().unwrap(), "日ｱ丂");
+    let mut decoder = EucJp::decoder(());
+    decoder.decode(&[0x41, 0xC6]).unwrap();
+    assert_eq!(decoder.finish().err().unwrap().position, Some(crate::unicode_encoding::ErrorPosition::Byte(1)));
+    let mut encoder = EucJp::encoder(());
+    assert_eq!(encoder.encode(&Utf32::from_string("日本").unwrap()).unwrap(), vec![0xC6, 0xFC, 0xCB, 0xDC]);
+    let err = encoder.encode(&Utf32::from_string("a😎").unwrap()).err().unwrap();
+    assert_eq!(err.kind, UnmappableCodepoint);
+    assert_eq!(err.position, Some(crate::unicode_encoding::ErrorPosition::Codepoint(3)));
+}
@@ -0,0 +1,276 @@
+/// The Punycode module handles the encoding of Unicode strings into the
+/// letters, digits and hyphens allowed in host names, described in the
+/// RFC 3492, and its use in the internationalized domain names.
+
+use crate::unicode_encoding::UnicodeEncodingErrorKind::*;
+use crate::unicode_encoding::UnicodeEncodingError;
+use crate::unicode_encoding::UnicodeEncoding;
+use crate::utf_32::Utf32;
+
+/// A wrapper for Punycode encoded bytes. The ASCII characters of the string
+/// are copied first, followed by a `-` and by the positions and values of
+/// the other characters encoded as base 36 numbers.
+pub struct Punycode {
+    pub data: Vec<u8>
+}
+
+impl UnicodeEncoding for Punycode {
+    const NAME: &'static str = "Punycode";
+    type Options = ();
+
+    /// Convert UTF-32 data to Punycode.
+    fn from_utf_32(data_utf_32: &Utf32) -> Self {
+        let glyphs = &data_utf_32.data;
+        let mut data: Vec<u8> = glyphs.iter().filter(|x| is_basic(**x)).map(|x| *x as u8).collect();
+        let basic_count = data.len();
+        if basic_count > 0 {
+            data.push(DELIMITER);
+        }
+        let mut n = INITIAL_N;
+        let mut delta: u64 = 0;
+        let mut bias = INITIAL_BIAS;
+        let mut handled = basic_count;
+        while handled < glyphs.len() {
+            // The smallest code-point not handled yet.
+            let m = glyphs.iter().copied().filter(|x| *x >= n).min().unwrap_or(n);
+            delta += (m - n) as u64 * (handled as u64 + 1);
+            n = m;
+            for glyph in glyphs {
+                if *glyph < n {
+                    delta += 1;
+                }
+                if *glyph == n {
+                    let mut q = delta;
+                    let mut k = BASE;
+                    loop {
+                        let t = threshold(k, bias);
+                        if q < t {
+                            break;
+                        }
+                        data.push(encode_digit(t + (q - t) % (BASE - t)));
+                        q = (q - t) / (BASE - t);
+                        k += BASE;
+                    }
+                    data.push(encode_digit(q));
+                    bias = adapt(delta, handled as u64 + 1, handled == basic_count);
+                    delta = 0;
+                    handled += 1;
+                }
+            }
+            delta += 1;
+            n += 1;
+        }
+        return Punycode{data: data};
+    }
+
+    /// Convert Punycode data to UTF-32.
+    fn try_to_utf_32(&self) -> Result<Utf32, UnicodeEncodingError> {
+        let error = |kind, index: usize| UnicodeEncodingError::new(kind).at_byte(index).with_code_units(&self.data[index..index+1]).in_encoding(Self::NAME);
+        let basic_end = self.data.iter().rposition(|x| *x == DELIMITER).unwrap_or(0);
+        let mut glyphs: Vec<u32> = Vec::new();
+        for i in 0..basic_end {
+            if !is_basic(self.data[i] as u32) {
+                return Err(error(InvalidPunycodeByte, i));
+            }
+            glyphs.push(self.data[i] as u32);
+        }
+        let mut index = if basic_end > 0 {basic_end + 1} else {0};
+        let mut n = INITIAL_N as u64;
+        let mut i: u64 = 0;
+        let mut bias = INITIAL_BIAS;
+        while index < self.data.len() {
+            let start = index;
+            let old_i = i;
+            let mut w: u64 = 1;
+            let mut k = BASE;
+            loop {
+                if index == self.data.len() {
+                    return Err(UnicodeEncodingError::new(MissingEncodedBytes).at_byte(start).with_code_units(&self.data[start..]).in_encoding(Self::NAME));
+                }
+                let digit = decode_digit(self.data[index]).ok_or_else(|| error(InvalidPunycodeByte, index))?;
+                i += digit * w;
+                index += 1;
+                let t = threshold(k, bias);
+                if digit < t {
+                    break;
+                }
+                w *= BASE - t;
+                if i > MAX_VALUE || w > MAX_VALUE {
+                    return Err(error(PunycodeOverflow, start));
+                }
+                k += BASE;
+            }
+            let length = glyphs.len() as u64 + 1;
+            bias = adapt(i - old_i, length, old_i == 0);
+            n += i / length;
+            i %= length;
+            if n > 0x10FFFF {
+                return Err(error(CodepointAboveUnicodeRange, start));
+            }
+            glyphs.insert(i as usize, n as u32);
+            i += 1;
+        }
+        return Ok(Utf32{data: glyphs});
+    }
+
+    /// Convert the instance of `Punycode` to a vector of bytes.
+    /// No transformation is needed.
+    fn to_bytes(&self, _options: ()) -> Vec<u8> {
+        return self.data.clone();
+    }
+
+    /// Consider a stream of Punycode encoded bytes and turn it into a
+    /// `Punycode` type. The bytes are decoded to ensure they are valid.
+    fn from_bytes_no_check(bytes: &[u8], _options: ()) -> Result<Self, UnicodeEncodingError> {
+        let ret = Punycode{data: bytes.to_vec()};
+        ret.try_to_utf_32()?;
+        return Ok(ret);
+    }
+}
+
+/* ------------------------------ Domain names ------------------------------ */
+
+/// The prefix of the labels of a domain name encoded in Punycode.
+pub const ACE_PREFIX: &str = "xn--";
+
+/// The characters separating the labels of a domain name: the full stop, and
+/// the full stops of CJK text, as in the RFC 3490.
+const LABEL_SEPARATORS: [char; 4] = ['.', '\u{3002}', '\u{FF0E}', '\u{FF61}'];
+
+/// Converts a domain name to ASCII. Each label with non-ASCII characters is
+/// encoded in Punycode and prefixed with `xn--`. The full stops of CJK text
+/// are also used to split the labels. The characters are
+/// not mapped or normalized, so the labels should already be lowercase and
+/// in NFC.
+pub fn domain_to_ascii(domain: &str) -> String {
+    let labels: Vec<String> = domain.split(LABEL_SEPARATORS).map(|label| {
+        if label.is_ascii() {
+            return label.to_string();
+        }
+        let encoded = Punycode::from_utf_32(&Utf32{data: label.chars().map(|x| x as u32).collect()});
+        return format!("{}{}", ACE_PREFIX, String::from_utf8_lossy(&encoded.data));
+    }).collect();
+    return labels.join(".");
+}
+
+/// Converts a domain name from ASCII. Each label starting with `xn--`, with
+/// any case, is decoded from Punycode. The labels are split like in
+/// `domain_to_ascii`. An error is returned if such a label is not valid
+/// Punycode.
+pub fn domain_to_unicode(domain: &str) -> Result<String, UnicodeEncodingError> {
+    let mut labels: Vec<String> = Vec::new();
+    for label in domain.split(LABEL_SEPARATORS) {
+        match label.get(..ACE_PREFIX.len()) {
+            Some(prefix) if prefix.eq_ignore_ascii_case(ACE_PREFIX) => {
+                let decoded = Punycode::from_bytes(&label.as_bytes()[ACE_PREFIX.len()..], ())?;
+                labels.push(decoded.to_string()?);
+            },
+            _ => labels.push(label.to_string()),
+        }
+    }
+    return Ok(labels.join("."));
+}
+
+/* ---------------------------- Helper functions ---------------------------- */
+
+// Parameters of Punycode, from the section 5 of the RFC 3492.
+const BASE: u64 = 36;
+const T_MIN: u64 = 1;
+const T_MAX: u64 = 26;
+const SKEW: u64 = 38;
+const DAMP: u64 = 700;
+const INITIAL_BIAS: u64 = 72;
+const INITIAL_N: u32 = 0x80;
+const DELIMITER: u8 = b'-';
+
+/// The biggest value a decoded delta can take before being considered an
+/// overflow, as in the RFC 3492 which uses 32-bit integers.
+const MAX_VALUE: u64 = u32::MAX as u64;
+
+fn is_basic(glyph: u32) -> bool {
+    return glyph < INITIAL_N;
+}
+
+/// The threshold telling if a digit is the last one of a number.
+fn threshold(k: u64, bias: u64) -> u64 {
+    if k <= bias {
+        return T_MIN;
+    }
+    if k >= bias + T_MAX {
+        return T_MAX;
+    }
+    return k - bias;
+}
+
+/// Adapts the bias after each delta, as described in the section 6.1 of the
+/// RFC 3492.
+fn adapt(delta: u64, num_points: u64, first_time: bool) -> u64 {
+    let mut delta = if first_time {delta / DAMP} else {delta / 2};
+    delta += delta / num_points;
+    let mut k: u64 = 0;
+    while delta > ((BASE - T_MIN) * T_MAX) / 2 {
+        delta /= BASE - T_MIN;
+        k += BASE;
+    }
+    return k + (BASE - T_MIN + 1) * delta / (delta + SKEW);
+}
+
+/// The digits from 0 to 25 are the lowercase letters, the next ones are the
+/// decimal digits.
+fn encode_digit(digit: u64) -> u8 {
+    if digit < 26 {
+        return b'a' + digit as u8;
+    }
+    return b'0' + (digit - 26) as u8;
+}
+
+fn decode_digit(byte: u8) -> Option<u64> {
+    return match byte {
+        b'a'..=b'z' => Some((byte - b'a') as u64),
+        b'A'..=b'Z' => Some((byte - b'A') as u64),
+        b'0'..=b'9' => Some((byte - b'0') as u64 + 26),
+        _ => None,
+    };
+}
+
+/* --------------------------------- Testing -------------------------------- */
+
+#[test]
+fn test_punycode_rfc_examples() {
+    // Examples from the section 7.1 of the RFC 3492.
+    let examples = [("他们为什么不说中文", "ihqwcrb4cv8a8dqg056pqjye"),
+                    ("3年B組金八先生", "3B-ww4c5e180e575a65lsy2b"),
+                    ("bücher", "bcher-kva"),
+                    ("München", "Mnchen-3ya"),
+                    ("abc", "abc-")];
+    for (text, encoded) in examples {
+        assert_eq!(Punycode::from_string(text).unwrap().data, encoded.as_bytes());
+        assert_eq!(Punycode::from_bytes(encoded.as_bytes(), ()).unwrap().to_string().unwrap(), text);
+    }
+    // The digits are not case sensitive.
+    assert_eq!(Punycode::from_bytes(b"BCHER-KVA", ()).unwrap().to_string().unwrap(), "BüCHER");
+}
+
+#[test]
+fn test_invalid_punycode() {
+    assert_eq!(Punycode::from_bytes(b"bcher-k_a", ()).err().unwrap().kind, InvalidPunycodeByte);
+    assert_eq!(Punycode::from_bytes("bé-kva".as_bytes(), ()).err().unwrap().kind, InvalidPunycodeByte);
+    let err = Punycode::from_bytes(b"bcher-kv", ()).err().unwrap();
+    assert_eq!(err.kind, MissingEncodedBytes);
+    assert_eq!(err.position, Some(crate::unicode_encoding::ErrorPosition::Byte(6)));
+    assert_eq!(Punycode::from_bytes(b"99999999999", ()).err().unwrap().kind, PunycodeOverflow);
+}
+
+#[test]
+fn test_domain_names() {
+    assert_eq!(domain_to_ascii("bücher.example"), "xn--bcher-kva.example");
+    assert_eq!(domain_to_ascii("例え。テスト"), "xn--r8jz45g.xn--zckzah");
+    assert_eq!(domain_to_ascii("www.example.com"), "www.example.com");
+    assert_eq!(domain_to_unicode("XN--bcher-kva.example").unwrap(), "bücher.example");
+    assert_eq!(domain_to_unicode("xn--r8jz45g.xn--zckzah").unwrap(), "例え.テスト");
+    // The full stops of CJK text separate the labels both ways.
+    assert_eq!(domain_to_unicode("xn--r8jz45g\u{3002}xn--zckzah\u{FF61}jp").unwrap(), "例え.テスト.jp");
+    assert_eq!(domain_to_unicode("xn--bcher-kva\u{FF0E}example").unwrap(), "bücher.example");
+    assert_eq!(domain_to_unicode(&domain_to_ascii("例え．テスト")).unwrap(), "例え.テスト");
+    assert_eq!(domain_to_unicode("xn--bcher-k_a.example").err().unwrap().kind, InvalidPunycodeByte);
+}
@@ -0,0 +1,558 @@
+/// The SCSU module handles the __Standard Compression Scheme for Unicode__
+/// described in the Unicode Technical Standard #6.
+
+use crate::unicode_encoding::UnicodeEncodingErrorKind::*;
+use crate::unicode_encoding::UnicodeEncodingError;
+use crate::unicode_encoding::UnicodeEncoding;
+use crate::utf_32::Utf32;
+use crate::utf_16::{self, is_high_surrogate};
+use crate::cesu_8;
+use crate::incremental::{Decoder, Encoder, SanityChecker};
+use crate::lossy::{self, DecodingPolicy};
+
+/// A wrapper for SCSU compressed bytes. Most of the text is written with a
+/// single byte per character, taken from windows of 128 code-points that can
+/// be moved along the text. The other characters are written as UTF-16.
+pub struct Scsu {
+    pub data: Vec<u8>
+}
+
+impl UnicodeEncoding for Scsu {
+    const NAME: &'static str = "SCSU";
+    type Options = ();
+
+    /// Compress UTF-32 data to SCSU.
+    fn from_utf_32(data_utf_32: &Utf32) -> Self {
+        let mut encoder = ScsuEncoder::new();
+        let mut data: Vec<u8> = Vec::new();
+        for i in 0..data_utf_32.data.len() {
+            encoder.push_glyph(data_utf_32.data[i], data_utf_32.data.get(i+1).copied(), &mut data);
+        }
+        return Scsu{data: data};
+    }
+
+    /// Decompress SCSU data to UTF-32.
+    fn try_to_utf_32(&self) -> Result<Utf32, UnicodeEncodingError> {
+        let mut decoder = ScsuDecoder::new();
+        let mut ret = decoder.decode(&self.data)?;
+        ret.data.extend(decoder.finish()?.data);
+        return Ok(ret);
+    }
+
+    /// Convert the instance of `Scsu` to a vector of bytes.
+    /// No transformation is needed.
+    fn to_bytes(&self, _options: ()) -> Vec<u8> {
+        return self.data.clone();
+    }
+
+    /// Consider a stream of SCSU compressed bytes and turn it into a `Scsu`
+    /// type. The bytes are decompressed to ensure they are valid.
+    fn from_bytes_no_check(bytes: &[u8], _options: ()) -> Result<Self, UnicodeEncodingError> {
+        let ret = Scsu{data: bytes.to_vec()};
+        ret.try_to_utf_32()?;
+        return Ok(ret);
+    }
+
+    /// Decompresses the data instruction by instruction, replacing the
+    /// invalid ones. The windows are kept across the invalid instructions,
+    /// so the bytes following one are read as they would have been.
+    fn from_bytes_lossy(bytes: &[u8], _options: (), policy: DecodingPolicy) -> Result<(Self, usize), UnicodeEncodingError> {
+        let mut decoder = ScsuDecoder::new();
+        let mut data: Vec<u32> = Vec::new();
+        let mut count: usize = 0;
+        let mut index: usize = 0;
+        while index < bytes.len() {
+            match decoder.read_instruction(&bytes[index..]) {
+                Ok((values, len)) => {
+                    data.extend(values);
+                    index += len;
+                },
+                Err(err) => {
+                    let err = err.at_byte(index).in_encoding(Self::NAME);
+                    data.extend(policy.handle(&err)?);
+                    count += 1;
+                    index += std::cmp::max(1, err.code_units.len());
+                },
+            }
+        }
+        let (data, sanitized) = lossy::sanitize(&cesu_8::pair_surrogates(&data), policy).map_err(|x| x.in_encoding(Self::NAME))?;
+        return Ok((Self::from_utf_32(&Utf32{data: data}), count + sanitized));
+    }
+
+    /// The signature of SCSU is U+FEFF quoted as UTF-16, as recommended by
+    /// the standard.
+    fn bom(_options: ()) -> Vec<u8> {
+        return vec![SQU, 0xFE, 0xFF];
+    }
+
+    fn decoder(_options: ()) -> Box<dyn Decoder> {
+        return Box::new(ScsuDecoder::new());
+    }
+
+    fn encoder(_options: ()) -> Box<dyn Encoder> {
+        return Box::new(ScsuEncoder::new());
+    }
+}
+
+/* --------------------------- Incremental coding --------------------------- */
+
+/// The state shared by the compressor and the decompressor.
+struct ScsuState {
+    /// Tells if the bytes are read as UTF-16 instead of single bytes.
+    unicode_mode: bool,
+    /// The dynamic window used by the single bytes from 0x80 to 0xFF.
+    active_window: usize,
+    /// The first code-point of each dynamic window.
+    windows: [u32; 8],
+}
+
+impl ScsuState {
+    fn new() -> Self {
+        return ScsuState{unicode_mode: false, active_window: 0, windows: DEFAULT_DYNAMIC_WINDOWS};
+    }
+
+    /// Gives the dynamic window containing a code-point, if any. The active
+    /// window is preferred.
+    fn window_containing(&self, glyph: u32) -> Option<usize> {
+        let contains = |n: usize| glyph >= self.windows[n] && glyph < self.windows[n] + WINDOW_SIZE;
+        if contains(self.active_window) {
+            return Some(self.active_window);
+        }
+        return (0..8).find(|n| contains(*n));
+    }
+}
+
+/// Decompresses SCSU data chunk by chunk. The bytes of a tag and its
+/// arguments split between two chunks are kept until the next one.
+pub struct ScsuDecoder {
+    state: ScsuState,
+    pending_bytes: Vec<u8>,
+    pending_surrogate: Option<u32>,
+    consumed: usize,
+    checker: SanityChecker,
+}
+
+impl ScsuDecoder {
+    fn new() -> Self {
+        return ScsuDecoder{state: ScsuState::new(), pending_bytes: Vec::new(), pending_surrogate: None, consumed: 0, checker: SanityChecker::new()};
+    }
+
+    /// Reads the tag or character at the beginning of some bytes. Returns the
+    /// code-points or UTF-16 code units it gives and the number of bytes
+    /// read. The state is only changed if the bytes are complete and valid.
+    fn read_instruction(&mut self, data: &[u8]) -> Result<(Vec<u32>, usize), UnicodeEncodingError> {
+        let byte = data[0];
+        let needed = match (self.state.unicode_mode, byte) {
+            (false, SQ0..=SQ7) | (false, SD0..=SD7) => 2,
+            (false, SQU) | (false, SDX) => 3,
+            (false, _) => 1,
+            (true, UC0..=UC7) | (true, URS) => 1,
+            (true, UD0..=UD7) => 2,
+            (true, UQU) | (true, UDX) => 3,
+            (true, _) => 2,
+        };
+        if data.len() < needed {
+            return Err(UnicodeEncodingError::new(MissingEncodedBytes).with_code_units(data));
+        }
+        let reserved = || UnicodeEncodingError::new(ReservedScsuByte).with_code_units(&data[..needed]);
+        let unit = |i: usize| ((data[i] as u32) << 8) | data[i+1] as u32;
+        let mut ret: Vec<u32> = Vec::new();
+        if !self.state.unicode_mode {
+            match byte {
+                SQ0..=SQ7 => {
+                    let n = (byte - SQ0) as usize;
+                    let quoted = data[1] as u32;
+                    if quoted < 0x80 {
+                        ret.push(STATIC_WINDOWS[n] + quoted);
+                    } else {
+                        ret.push(self.state.windows[n] + quoted - 0x80);
+                    }
+                },
+                SDX => {
+                    let (n, offset) = extended_window(data[1], data[2]);
+                    self.state.windows[n] = offset;
+                    self.state.active_window = n;
+                },
+                SRS => return Err(reserved()),
+                SQU => ret.push(unit(1)),
+                SCU => self.state.unicode_mode = true,
+                SC0..=SC7 => self.state.active_window = (byte - SC0) as usize,
+                SD0..=SD7 => {
+                    let n = (byte - SD0) as usize;
+                    self.state.windows[n] = window_offset(data[1]).ok_or_else(reserved)?;
+                    self.state.active_window = n;
+                },
+                0x80..=0xFF => ret.push(self.state.windows[self.state.active_window] + byte as u32 - 0x80),
+                _ => ret.push(byte as u32),
+            }
+        } else {
+            match byte {
+                UC0..=UC7 => {
+                    self.state.active_window = (byte - UC0) as usize;
+                    self.state.unicode_mode = false;
+                },
+                UD0..=UD7 => {
+                    let n = (byte - UD0) as usize;
+                    self.state.windows[n] = window_offset(data[1]).ok_or_else(reserved)?;
+                    self.state.active_window = n;
+                    self.state.unicode_mode = false;
+                },
+                UQU => ret.push(unit(1)),
+                UDX => {
+                    let (n, offset) = extended_window(data[1], data[2]);
+                    self.state.windows[n] = offset;
+                    self.state.active_window = n;
+                    self.state.unicode_mode = false;
+                },
+                URS => return Err(reserved()),
+                _ => ret.push(unit(0)),
+            }
+        }
+        return Ok((ret, needed));
+    }
+}
+
+impl Decoder for ScsuDecoder {
+    fn decode(&mut self, bytes: &[u8]) -> Result<Utf32, UnicodeEncodingError> {
+        self.pending_bytes.extend_from_slice(bytes);
+        let mut glyphs: Vec<u32> = Vec::new();
+        if let Some(surrogate) = self.pending_surrogate.take() {
+            glyphs.push(surrogate);
+        }
+        let mut index: usize = 0;
+        while index < self.pending_bytes.len() {
+            let pending = std::mem::take(&mut self.pending_bytes);
+            let result = self.read_instruction(&pending[index..]);
+            self.pending_bytes = pending;
+            match result {
+                Ok((values, len)) => {
+                    glyphs.extend(values);
+                    index += len;
+                },
+                Err(x) if x.kind == MissingEncodedBytes => break,
+                Err(x) => return Err(x.at_byte(self.consumed + index).in_encoding(Scsu::NAME)),
+            }
+        }
+        self.pending_bytes.drain(..index);
+        self.consumed += index;
+        // The characters written as UTF-16 can be pairs of surrogates, a high
+        // surrogate at the end of the chunk might be paired with the next one.
+        if glyphs.last().is_some_and(|x| is_high_surrogate(*x)) {
+            self.pending_surrogate = glyphs.pop();
+        }
+        let ret = Utf32{data: cesu_8::pair_surrogates(&glyphs)};
+        self.checker.push_all(&ret).map_err(|x| x.in_encoding(Scsu::NAME))?;
+        return Ok(ret);
+    }
+
+    fn finish(&mut self) -> Result<Utf32, UnicodeEncodingError> {
+        if !self.pending_bytes.is_empty() {
+            return Err(UnicodeEncodingError::new(MissingEncodedBytes).at_byte(self.consumed).with_code_units(&self.pending_bytes).in_encoding(Scsu::NAME));
+        }
+        let ret = match self.pending_surrogate.take() {
+            Some(surrogate) => Utf32{data: vec![surrogate]},
+            None => Utf32{data: Vec::new()},
+        };
+        self.checker.push_all(&ret).map_err(|x| x.in_encoding(Scsu::NAME))?;
+        self.checker.finish()?;
+        return Ok(ret);
+    }
+}
+
+/// Compresses SCSU data chunk by chunk. The windows are chosen looking at the
+/// next character, which is not known at the end of a chunk, so the result
+/// can be slightly bigger than when the data is compressed at once.
+pub struct ScsuEncoder {
+    state: ScsuState,
+    /// When each dynamic window was last used, to redefine the least
+    /// recently used one.
+    last_use: [usize; 8],
+    glyph_count: usize,
+    checker: SanityChecker,
+}
+
+impl ScsuEncoder {
+    fn new() -> Self {
+        return ScsuEncoder{state: ScsuState::new(), last_use: [0; 8], glyph_count: 0, checker: SanityChecker::new()};
+    }
+
+    /// Compresses a single glyph. `next` is the following glyph, if known.
+    fn push_glyph(&mut self, glyph: u32, next: Option<u32>, bytes: &mut Vec<u8>) {
+        self.glyph_count += 1;
+        if self.state.unicode_mode {
+            // Going back to single bytes is only worth it if the next
+            // character does not need UTF-16 as well.
+            if needs_unicode_mode(glyph) || next.is_some_and(needs_unicode_mode) {
+                push_unicode_mode_units(glyph, bytes);
+                return;
+            }
+            self.state.unicode_mode = false;
+            match self.state.window_containing(glyph) {
+                Some(n) if !is_pass_through(glyph) => {
+                    bytes.push(UC0 + n as u8);
+                    self.use_window(n);
+                },
+                None if !is_pass_through(glyph) && glyph >= FIRST_WINDOWED => {
+                    let n = self.least_recently_used_window();
+                    self.define_window(n, glyph, UD0, UDX, bytes);
+                },
+                _ => bytes.push(UC0 + self.state.active_window as u8),
+            }
+        }
+        if is_pass_through(glyph) {
+            bytes.push(glyph as u8);
+        } else if glyph < FIRST_WINDOWED {
+            bytes.extend_from_slice(&[SQ0, glyph as u8]);
+        } else if let Some(n) = self.state.window_containing(glyph) {
+            if n != self.state.active_window {
+                bytes.push(SC0 + n as u8);
+            }
+            self.use_window(n);
+        } else if let Some(n) = static_window_containing(glyph).filter(|n| next.is_none_or(|x| static_window_containing(x) != Some(*n))) {
+            // An isolated character from a static window is quoted instead of
+            // moving a dynamic window.
+            bytes.extend_from_slice(&[SQ0 + n as u8, (glyph - STATIC_WINDOWS[n]) as u8]);
+            return;
+        } else if !needs_unicode_mode(glyph) {
+            let n = self.least_recently_used_window();
+            self.define_window(n, glyph, SD0, SDX, bytes);
+        } else if next.is_some_and(needs_unicode_mode) {
+            bytes.push(SCU);
+            self.state.unicode_mode = true;
+            push_unicode_mode_units(glyph, bytes);
+            return;
+        } else {
+            for code_unit in utf_16_code_units(glyph) {
+                bytes.push(SQU);
+                bytes.extend_from_slice(&code_unit.to_be_bytes());
+            }
+            return;
+        }
+        if glyph >= FIRST_WINDOWED {
+            bytes.push((glyph - self.state.windows[self.state.active_window] + 0x80) as u8);
+        }
+    }
+
+    /// Marks a window as the active one.
+    fn use_window(&mut self, n: usize) {
+        self.state.active_window = n;
+        self.last_use[n] = self.glyph_count;
+    }
+
+    fn least_recently_used_window(&self) -> usize {
+        return (0..8).min_by_key(|n| self.last_use[*n]).unwrap_or(0);
+    }
+
+    /// Moves the dynamic window `n` to contain the glyph and makes it the
+    /// active one. The tags are the ones of the current mode.
+    fn define_window(&mut self, n: usize, glyph: u32, define_tag: u8, extended_tag: u8, bytes: &mut Vec<u8>) {
+        match window_offset_index(glyph) {
+            Some(index) => {
+                bytes.extend_from_slice(&[define_tag + n as u8, index]);
+                self.state.windows[n] = window_offset(index).unwrap_or(0);
+            },
+            None => {
+                let offset = glyph & !(WINDOW_SIZE - 1);
+                let index = (offset - EXTENDED_WINDOWS_START) / WINDOW_SIZE;
+                bytes.extend_from_slice(&[extended_tag, ((n as u32) << 5 | index >> 8) as u8, index as u8]);
+                self.state.windows[n] = offset;
+            },
+        }
+        self.use_window(n);
+    }
+}
+
+impl Encoder for ScsuEncoder {
+    fn encode(&mut self, data: &Utf32) -> Result<Vec<u8>, UnicodeEncodingError> {
+        self.checker.push_all(data)?;
+        let mut bytes: Vec<u8> = Vec::new();
+        for i in 0..data.data.len() {
+            self.push_glyph(data.data[i], data.data.get(i+1).copied(), &mut bytes);
+        }
+        return Ok(bytes);
+    }
+
+    fn finish(&mut self) -> Result<Vec<u8>, UnicodeEncodingError> {
+        self.checker.finish()?;
+        return Ok(Vec::new());
+    }
+}
+
+/* ---------------------------- Helper functions ---------------------------- */
+
+// Tags of the single-byte mode.
+const SQ0: u8 = 0x01;
+const SQ7: u8 = 0x08;
+const SDX: u8 = 0x0B;
+const SRS: u8 = 0x0C;
+const SQU: u8 = 0x0E;
+const SCU: u8 = 0x0F;
+const SC0: u8 = 0x10;
+const SC7: u8 = 0x17;
+const SD0: u8 = 0x18;
+const SD7: u8 = 0x1F;
+
+// Tags of the Unicode mode.
+const UC0: u8 = 0xE0;
+const UC7: u8 = 0xE7;
+const UD0: u8 = 0xE8;
+const UD7: u8 = 0xEF;
+const UQU: u8 = 0xF0;
+const UDX: u8 = 0xF1;
+const URS: u8 = 0xF2;
+
+const WINDOW_SIZE: u32 = 0x80;
+const FIRST_WINDOWED: u32 = 0x80;
+const EXTENDED_WINDOWS_START: u32 = 0x10000;
+
+const STATIC_WINDOWS: [u32; 8] = [0x0000, 0x0080, 0x0100, 0x0300, 0x2000, 0x2080, 0x2100, 0x3000];
+const DEFAULT_DYNAMIC_WINDOWS: [u32; 8] = [0x0080, 0x00C0, 0x0400, 0x0600, 0x0900, 0x3040, 0x30A0, 0xFF00];
+
+/// Tells if a character is written as itself in the single-byte mode.
+fn is_pass_through(glyph: u32) -> bool {
+    return glyph == 0x00 || glyph == 0x09 || glyph == 0x0A || glyph == 0x0D || (0x20..0x80).contains(&glyph);
+}
+
+/// Tells if a character can't be found in a dynamic window and must be
+/// written as UTF-16. This is the case of the CJK ideographs, of Hangul and
+/// of the unpaired surrogates.
+fn needs_unicode_mode(glyph: u32) -> bool {
+    return (0x3400..0xE000).contains(&glyph);
+}
+
+/// Gives the static window containing a character, if any. The static window
+/// 0 is only used for the control characters, which are handled elsewhere.
+fn static_window_containing(glyph: u32) -> Option<usize> {
+    return (1..8).find(|n| glyph >= STATIC_WINDOWS[*n] && glyph < STATIC_WINDOWS[*n] + WINDOW_SIZE);
+}
+
+/// Gives the offset of a dynamic window from the byte defining it. `None` is
+/// returned for the reserved bytes.
+fn window_offset(index: u8) -> Option<u32> {
+    match index {
+        0x01..=0x67 => Some(index as u32 * WINDOW_SIZE),
+        0x68..=0xA7 => Some(index as u32 * WINDOW_SIZE + 0xAC00),
+        0xF9 => Some(0x00C0),
+        0xFA => Some(0x0250),
+        0xFB => Some(0x0370),
+        0xFC => Some(0x0530),
+        0xFD => Some(0x3040),
+        0xFE => Some(0x30A0),
+        0xFF => Some(0xFF60),
+        _ => None,
+    }
+}
+
+/// Gives the byte defining a dynamic window containing a character of the
+/// BMP, if there is one.
+fn window_offset_index(glyph: u32) -> Option<u8> {
+    if (FIRST_WINDOWED..0x3400).contains(&glyph) {
+        return Some((glyph / WINDOW_SIZE) as u8);
+    }
+    if (0xE000..EXTENDED_WINDOWS_START).contains(&glyph) {
+        return Some(((glyph - 0xAC00) / WINDOW_SIZE) as u8);
+    }
+    return None;
+}
+
+/// Reads the two bytes defining an extended window, above U+FFFF. Returns
+/// the index of the window and its offset.
+fn extended_window(high: u8, low: u8) -> (usize, u32) {
+    let index = (((high & 0x1F) as u32) << 8) | low as u32;
+    return ((high >> 5) as usize, EXTENDED_WINDOWS_START + index * WINDOW_SIZE);
+}
+
+fn utf_16_code_units(glyph: u32) -> Vec<u16> {
+    if glyph >= EXTENDED_WINDOWS_START {
+        let (high_surrogate, low_surrogate) = utf_16::glyph_into_surrogates(glyph);
+        return vec![high_surrogate, low_surrogate];
+    }
+    return vec![glyph as u16];
+}
+
+/// Writes a glyph as UTF-16 in the Unicode mode. The code units whose first
+/// byte would be read as a tag are quoted.
+fn push_unicode_mode_units(glyph: u32, bytes: &mut Vec<u8>) {
+    for code_unit in utf_16_code_units(glyph) {
+        let [high, low] = code_unit.to_be_bytes();
+        if (UC0..=URS).contains(&high) {
+            bytes.push(UQU);
+        }
+        bytes.extend_from_slice(&[high, low]);
+    }
+}
+
+/* --------------------------------- Testing -------------------------------- */
+
+#[test]
+fn test_scsu_standard_examples() {
+    // Examples from the section 9 of the UTS #6.
+    let german: Vec<u8> = vec![0xD6, 0x6C, 0x20, 0x66, 0x6C, 0x69, 0x65, 0xDF, 0x74];
+    assert_eq!(Scsu::from_bytes(&german, ()).unwrap().to_string().unwrap(), "Öl fließt");
+    assert_eq!(Scsu::from_string("Öl fließt").unwrap().data, german);
+
+    let russian: Vec<u8> = vec![0x12, 0x9C, 0xBE, 0xC1, 0xBA, 0xB2, 0xB0];
+    assert_eq!(Scsu::from_bytes(&russian, ()).unwrap().to_string().unwrap(), "Москва");
+    assert_eq!(Scsu::from_string("Москва").unwrap().data, russian);
+
+    let japanese: Vec<u8> = vec![0x08, 0x00, 0x1B, 0x4C, 0xEA, 0x16, 0xCA, 0xD3, 0x94, 0x0F, 0x53, 0xEF, 0x61, 0x1B, 0xE5, 0x84,
+                                 0xC4, 0x0F, 0x53, 0xEF, 0x61, 0x1B, 0xE5, 0x84, 0xC4, 0x16, 0xCA, 0xD3, 0x94, 0x08, 0x02, 0x0F,
+                                 0x53, 0x4A, 0x4E, 0x16, 0x7D, 0x00, 0x30, 0x82, 0x52, 0x4D, 0x30, 0x6B, 0x6D, 0x41, 0x88, 0x4C,
+                                 0xE5, 0x97, 0x9F, 0x08, 0x0C, 0x16, 0xCA, 0xD3, 0x94, 0x15, 0xAE, 0x0E, 0x6B, 0x4C, 0x08, 0x0D,
+                                 0x8C, 0xB4, 0xA3, 0x9F, 0xCA, 0x99, 0xCB, 0x8B, 0xC2, 0x97, 0xCC, 0xAA, 0x84, 0x08, 0x02, 0x0E,
+                                 0x7C, 0x73, 0xE2, 0x16, 0xA3, 0xB7, 0xCB, 0x93, 0xD3, 0xB4, 0xC5, 0xDC, 0x9F, 0x0E, 0x79, 0x3E,
+                                 0x06, 0xAE, 0xB1, 0x9D, 0x93, 0xD3, 0x08, 0x0C, 0xBE, 0xA3, 0x8F, 0x08, 0x88, 0xBE, 0xA3, 0x8D,
+                                 0xD3, 0xA8, 0xA3, 0x97, 0xC5, 0x17, 0x89, 0x08, 0x0D, 0x15, 0xD2, 0x08, 0x01, 0x93, 0xC8, 0xAA,
+                                 0x8F, 0x0E, 0x61, 0x1B, 0x99, 0xCB, 0x0E, 0x4E, 0xBA, 0x9F, 0xA1, 0xAE, 0x93, 0xA8, 0xA0, 0x08,
+                                 0x02, 0x08, 0x0C, 0xE2, 0x16, 0xA3, 0xB7, 0xCB, 0x0F, 0x4F, 0xE1, 0x80, 0x05, 0xEC, 0x60, 0x8D,
+                                 0xEA, 0x06, 0xD3, 0xE6, 0x0F, 0x8A, 0x00, 0x30, 0x44, 0x65, 0xB9, 0xE4, 0xFE, 0xE7, 0xC2, 0x06,
+                                 0xCB, 0x82];
+    let decoded = Scsu::from_bytes(&japanese, ()).unwrap().to_string().unwrap();
+    assert!(decoded.starts_with("\u{3000}♪リンゴ可愛いや可愛いやリンゴ。"));
+    assert!(decoded.ends_with("「アップル信者」なんて言い方まである。"));
+    let compressed = Scsu::from_string(&decoded).unwrap();
+    assert!(compressed.data.len() < japanese.len() + japanese.len() / 10);
+    assert_eq!(compressed.to_string().unwrap(), decoded);
+
+    // Static and dynamic windows, including an extended one.
+    let supplementary: Vec<u8> = vec![0x41, 0xDF, 0x12, 0x81, 0x03, 0x5F, 0x10, 0xDF, 0x1B, 0x03, 0xDF, 0x1C, 0x88, 0x80, 0x0B, 0xBF, 0xFF, 0xFF];
+    assert_eq!(Scsu::from_bytes(&supplementary, ()).unwrap().to_utf_32().data, vec![0x41, 0xDF, 0x401, 0x15F, 0xDF, 0x1DF, 0xF000, 0x10FFFF]);
+}
+
+#[test]
+fn test_scsu_round_trip() {
+    for s in ["Hello, world!\r\n", "aé€—ü", "日本語とEnglish", "😎🐤 a 😎", "\u{1}\u{E000}\u{FFFD}ㅢ"] {
+        let scsu = Scsu::from_string(s).unwrap();
+        assert_eq!(Scsu::from_bytes(&scsu.data, ()).unwrap().to_string().unwrap(), s);
+    }
+}
+
+#[test]
+fn test_invalid_scsu() {
+    assert_eq!(Scsu::from_bytes(&[0x41, SRS], ()).err().unwrap().kind, ReservedScsuByte);
+    assert_eq!(Scsu::from_bytes(&[SD0, 0x00], ()).err().unwrap().kind, ReservedScsuByte);
+    assert_eq!(Scsu::from_bytes(&[SCU, URS], ()).err().unwrap().kind, ReservedScsuByte);
+    let err = Scsu::from_bytes(&[0x41, SQU, 0x00], ()).err().unwrap();
+    assert_eq!(err.kind, MissingEncodedBytes);
+    assert_eq!(err.position, Some(crate::unicode_encoding::ErrorPosition::Byte(1)));
+}
+
+#[test]
+fn test_scsu_lossy() {
+    // The active window and the defined ones are kept after an error.
+    let (decoded, count) = Scsu::from_bytes_lossy(&[0x12, 0x80, SRS, 0x80], (), DecodingPolicy::Replace).unwrap();
+    assert_eq!(count, 1);
+    assert_eq!(decoded.try_to_utf_32().unwrap().data, vec![0x0400, 0xFFFD, 0x0400]);
+    let (decoded, count) = Scsu::from_bytes_lossy(&[SD0 + 1, 0x14, 0x81, SRS, 0x82, SQU, 0x00], (), DecodingPolicy::Replace).unwrap();
+    assert_eq!(count, 2);
+    assert_eq!(decoded.try_to_utf_32().unwrap().data, vec![0x0A01, 0xFFFD, 0x0A02, 0xFFFD]);
+}
+
+#[test]
+fn test_scsu_incremental() {
+    let s = "Москва 日本語 😎";
+    let bytes = Scsu::from_string(s).unwrap().data;
+    let mut decoder = Scsu::decoder(());
+    let decoded = crate::incremental::decode_byte_per_byte(decoder.as_mut(), &bytes).unwrap();
+    assert_eq!(decoded.to_string().unwrap(), s);
+}
@@ -0,0 +1,95 @@
+/// The Shift_JIS module handles the legacy Japanese encoding of Windows,
+/// mixing the half-width katakana of JIS X 0201 in one byte with the
+/// characters of JIS X 0208 in two bytes, as in the WHATWG Encoding Standard.
+
+use crate::multi_byte::{self, MultiByteCodec, MultiByteEncoding};
+use crate::unicode_encoding::UnicodeEncodingError;
+use crate::cjk_indexes::JIS0208_SHIFT_JIS;
+
+/// The Shift_JIS codec, with the NEC and IBM extensions of Windows.
+pub struct ShiftJisCodec;
+
+/// A wrapper for Shift_JIS encoded bytes.
+pub type ShiftJis = MultiByteEncoding<ShiftJisCodec>;
+
+impl MultiByteCodec for ShiftJisCodec {
+    const NAME: &'static str = "Shift_JIS";
+
+    fn decode_character(data: &[u8], start: usize) -> Result<(u32, usize), UnicodeEncodingError> {
+        let lead = data[start];
+        match lead {
+            0x00..=0x80 => return Ok((lead as u32, 1)),
+            0xA1..=0xDF => return Ok((HALF_WIDTH_KATAKANA + (lead - 0xA1) as u32, 1)),
+            0x81..=0x9F | 0xE0..=0xFC => {},
+            _ => return Err(multi_byte::invalid_byte(data, start)),
+        }
+        if start + 1 == data.len() {
+            return Err(multi_byte::missing_bytes(data, start));
+        }
+        let trail = data[start+1];
+        if !matches!(trail, 0x40..=0x7E | 0x80..=0xFC) {
+            return Err(multi_byte::invalid_pair(data, start));
+        }
+        let lead_offset = if lead < 0xA0 {0x81} else {0xC1};
+        let trail_offset = if trail < 0x7F {0x40} else {0x41};
+        let pointer = (lead - lead_offset) as usize * 188 + (trail - trail_offset) as usize;
+        if USER_DEFINED.contains(&pointer) {
+            return Ok((0xE000 + (pointer - USER_DEFINED.start()) as u32, 2));
+        }
+        return match JIS0208_SHIFT_JIS.code_point(pointer) {
+            Some(glyph) => Ok((glyph, 2)),
+            None => Err(multi_byte::invalid_pair(data, start)),
+        };
+    }
+
+    fn encode_character(glyph: u32, bytes: &mut Vec<u8>) -> bool {
+        match glyph {
+            0x00..=0x80 => bytes.push(glyph as u8),
+            0xA5 => bytes.push(0x5C),
+            0x203E => bytes.push(0x7E),
+            0xFF61..=0xFF9F => bytes.push((glyph - HALF_WIDTH_KATAKANA) as u8 + 0xA1),
+            _ => {
+                let glyph = if glyph == 0x2212 {0xFF0D} else {glyph};
+                let pointer = match JIS0208_SHIFT_JIS.pointer(glyph) {
+                    Some(pointer) => pointer,
+                    None => return false,
+                };
+                let lead = (pointer / 188) as u8;
+                let trail = (pointer % 188) as u8;
+                bytes.push(lead + if lead < 0x1F {0x81} else {0xC1});
+                bytes.push(trail + if trail < 0x3F {0x40} else {0x41});
+            },
+        }
+        return true;
+    }
+}
+
+/* ---------------------------- Helper functions ---------------------------- */
+
+/// The first half-width katakana, encoded as 0xA1.
+const HALF_WIDTH_KATAKANA: u32 = 0xFF61;
+
+/// The pointers of the characters defined by the users, decoded to the
+/// private use area.
+const USER_DEFINED: std::ops::RangeInclusive<usize> = 8836..=10715;
+
+/* --------------------------------- Testing -------------------------------- */
+
+#[test]
+fn test_shift_jis() {
+    use crate::unicode_encoding::UnicodeEncoding;
+    use crate::unicode_encoding::UnicodeEncodingErrorKind::*;
+    let bytes = [0x93, 0xFA, 0x96, 0x7B, 0x8C, 0xEA, 0xB1, 0x87, 0x40];
+    assert_eq!(ShiftJis::from_bytes(&bytes, ()).unwrap().to_string().unwrap(), "日本語ｱ①");
+    assert_eq!(ShiftJis::from_string("日本語ｱ①").unwrap().data, bytes);
+    // The NEC selection of the IBM extensions is only decoded.
+    assert_eq!(ShiftJis::from_bytes(&[0xED, 0x40], ()).unwrap().to_string().unwrap(), "纊");
+    assert_eq!(ShiftJis::from_string("纊ⅰ").unwrap().data, vec![0xFA, 0x5C, 0xFA, 0x40]);
+    assert_eq!(ShiftJis::from_string("¥‾−").unwrap().data, vec![0x5C, 0x7E, 0x81, 0x7C]);
+    assert_eq!(ShiftJis::from_bytes(&[0xF0, 0x40, 0xF9, 0xFC], ()).unwrap().to_utf_32().data, vec![0xE000, 0xE757]);
+    let err = ShiftJis::from_string("aé").err().unwrap();
+    assert_eq!(err.kind, UnmappableCodepoint);
+    assert_eq!(err.position, Some(crate::unicode_encoding::ErrorPosition::Codepoint(1)));
+    assert_eq!(ShiftJis::from_utf_32(&crate::utf_32::Utf32::from_string("aé").unwrap()).data, b"a?");
+    assert_eq!(ShiftJis::from_bytes(&[0xA0], ()).err().unwrap().kind, InvalidMultiByteSequence);
+}
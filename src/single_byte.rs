@@ -0,0 +1,267 @@
+/// The `single_byte` module handles the legacy codepages where each byte is a
+/// character, such as ISO-8859-1 or Windows-1252, from a table giving the
+/// code-point of each byte.
+
+use crate::unicode_encoding::UnicodeEncodingErrorKind::*;
+use crate::unicode_encoding::UnicodeEncodingError;
+use crate::unicode_encoding::UnicodeEncoding;
+use crate::utf_32::Utf32;
+use crate::incremental::{Decoder, Encoder, SanityChecker};
+use crate::lossy::{self, DecodingPolicy};
+
+use std::marker::PhantomData;
+
+/// The code-point of each of the 256 bytes of a codepage. The bytes that are
+/// not used by the codepage are `UNMAPPED`.
+pub type MappingTable = [u16; 256];
+
+/// The value of the bytes not used by a codepage in a `MappingTable`. U+FFFF
+/// is a noncharacter, so no codepage maps a byte to it.
+pub const UNMAPPED: u16 = 0xFFFF;
+
+/// A single-byte codepage, described by its name and its mapping table. The
+/// codepages shipped with this crate are in the `codepages` module.
+pub trait CodepageTable {
+    /// The name of the codepage, used in error messages.
+    const NAME: &'static str;
+
+    /// The code-point of each byte.
+    const TABLE: MappingTable;
+}
+
+/// A wrapper for data encoded in a single-byte codepage. Unlike the Unicode
+/// encodings, a codepage can only encode 256 characters at most: encoding
+/// other code-points fails with `try_from_utf_32`, while `from_utf_32`
+/// replaces them with `?`.
+pub struct SingleByteEncoding<C: CodepageTable> {
+    pub data: Vec<u8>,
+    codepage: PhantomData<C>,
+}
+
+impl<C: CodepageTable> SingleByteEncoding<C> {
+    /// Encodes UTF-32 data, replacing the code-points that are not in the
+    /// codepage with the given byte. The number of replaced code-points is
+    /// returned along the data.
+    pub fn from_utf_32_lossy(data_utf_32: &Utf32, replacement: u8) -> (Self, usize) {
+        let mut count: usize = 0;
+        let data: Vec<u8> = data_utf_32.data.iter().map(|x| {
+            match encode_glyph::<C>(*x) {
+                Some(byte) => byte,
+                None => {
+                    count += 1;
+                    replacement
+                },
+            }
+        }).collect();
+        return (SingleByteEncoding{data: data, codepage: PhantomData}, count);
+    }
+}
+
+impl<C: CodepageTable> UnicodeEncoding for SingleByteEncoding<C> {
+    const NAME: &'static str = C::NAME;
+    type Options = ();
+
+    /// Convert UTF-32 data to the codepage. The code-points that are not in
+    /// the codepage are replaced with `?`.
+    fn from_utf_32(data_utf_32: &Utf32) -> Self {
+        return Self::from_utf_32_lossy(data_utf_32, REPLACEMENT_BYTE).0;
+    }
+
+    /// Convert UTF-32 data to the codepage. An error locating the first
+    /// code-point that is not in the codepage is returned if there is one.
+    fn try_from_utf_32(data_utf_32: &Utf32) -> Result<Self, UnicodeEncodingError> {
+        let mut data: Vec<u8> = Vec::new();
+        for (i, glyph) in data_utf_32.data.iter().enumerate() {
+            match encode_glyph::<C>(*glyph) {
+                Some(byte) => data.push(byte),
+                None => return Err(UnicodeEncodingError::new(UnmappableCodepoint).at_codepoint(i).with_code_units(&[*glyph]).in_encoding(Self::NAME)),
+            }
+        }
+        return Ok(SingleByteEncoding{data: data, codepage: PhantomData});
+    }
+
+    /// Convert data in the codepage to UTF-32.
+    fn try_to_utf_32(&self) -> Result<Utf32, UnicodeEncodingError> {
+        let mut data: Vec<u32> = Vec::new();
+        for i in 0..self.data.len() {
+            let (glyph, _) = decode_byte::<C>(&self.data, i).map_err(|x| x.in_encoding(Self::NAME))?;
+            data.push(glyph);
+        }
+        return Ok(Utf32{data: data});
+    }
+
+    /// Convert the instance of `SingleByteEncoding` to a vector of bytes.
+    /// No transformation is needed.
+    fn to_bytes(&self, _options: ()) -> Vec<u8> {
+        return self.data.clone();
+    }
+
+    /// Consider a stream of bytes in the codepage and turn it into a
+    /// `SingleByteEncoding` type. The bytes are decoded to ensure they are
+    /// all used by the codepage.
+    fn from_bytes_no_check(bytes: &[u8], _options: ()) -> Result<Self, UnicodeEncodingError> {
+        let ret = SingleByteEncoding{data: bytes.to_vec(), codepage: PhantomData};
+        ret.try_to_utf_32()?;
+        return Ok(ret);
+    }
+
+    /// Decodes the data byte by byte, replacing the bytes not used by the
+    /// codepage. The replacements that are not in the codepage, such as
+    /// U+FFFD, are stored as `?`, use `decode_lossy` to keep them.
+    fn from_bytes_lossy(bytes: &[u8], options: (), policy: DecodingPolicy) -> Result<(Self, usize), UnicodeEncodingError> {
+        let (data, count) = Self::decode_lossy(bytes, options, policy)?;
+        return Ok((Self::from_utf_32(&data), count));
+    }
+
+    /// Decodes the data byte by byte to UTF-32, replacing the bytes not used
+    /// by the codepage.
+    fn decode_lossy(bytes: &[u8], _options: (), policy: DecodingPolicy) -> Result<(Utf32, usize), UnicodeEncodingError> {
+        let (data, count) = lossy::decode_glyphs_lossy(bytes, decode_byte::<C>, policy).map_err(|x| x.in_encoding(Self::NAME))?;
+        return Ok((Utf32{data: data}, count));
+    }
+
+    /// The codepages have no BOM.
+    fn bom(_options: ()) -> Vec<u8> {
+        return Vec::new();
+    }
+
+    fn decoder(_options: ()) -> Box<dyn Decoder> where Self: 'static {
+        return Box::new(SingleByteDecoder::<C>{consumed: 0, codepage: PhantomData});
+    }
+
+    fn encoder(_options: ()) -> Box<dyn Encoder> where Self: 'static {
+        return Box::new(SingleByteEncoder::<C>{consumed: 0, checker: SanityChecker::new(), codepage: PhantomData});
+    }
+}
+
+/* --------------------------- Incremental coding --------------------------- */
+
+/// Decodes data in a codepage chunk by chunk. As each byte is a character,
+/// nothing has to be kept between the chunks except the position.
+pub struct SingleByteDecoder<C: CodepageTable> {
+    consumed: usize,
+    codepage: PhantomData<C>,
+}
+
+impl<C: CodepageTable> Decoder for SingleByteDecoder<C> {
+    fn decode(&mut self, bytes: &[u8]) -> Result<Utf32, UnicodeEncodingError> {
+        let ret = SingleByteEncoding::<C>::from_bytes_no_check(bytes, ()).map_err(|x| x.shifted(self.consumed))?;
+        self.consumed += bytes.len();
+        return ret.try_to_utf_32();
+    }
+
+    fn finish(&mut self) -> Result<Utf32, UnicodeEncodingError> {
+        return Ok(Utf32{data: Vec::new()});
+    }
+}
+
+/// Encodes data in a codepage chunk by chunk.
+pub struct SingleByteEncoder<C: CodepageTable> {
+    consumed: usize,
+    checker: SanityChecker,
+    codepage: PhantomData<C>,
+}
+
+impl<C: CodepageTable> Encoder for SingleByteEncoder<C> {
+    fn encode(&mut self, data: &Utf32) -> Result<Vec<u8>, UnicodeEncodingError> {
+        self.checker.push_all(data)?;
+        let ret = SingleByteEncoding::<C>::try_from_utf_32(data).map_err(|x| x.shifted(self.consumed))?;
+        self.consumed += data.data.len();
+        return Ok(ret.data);
+    }
+
+    fn finish(&mut self) -> Result<Vec<u8>, UnicodeEncodingError> {
+        self.checker.finish()?;
+        return Ok(Vec::new());
+    }
+}
+
+/* ---------------------------- Helper functions ---------------------------- */
+
+/// The byte written in place of the code-points that are not in a codepage.
+const REPLACEMENT_BYTE: u8 = b'?';
+
+/// Decodes the byte at the given index of some data in a codepage.
+fn decode_byte<C: CodepageTable>(data: &[u8], start: usize) -> Result<(u32, usize), UnicodeEncodingError> {
+    let glyph = C::TABLE[data[start] as usize];
+    if glyph == UNMAPPED {
+        return Err(UnicodeEncodingError::new(UndefinedCodepageByte).at_byte(start).with_code_units(&data[start..start+1]));
+    }
+    return Ok((glyph as u32, 1));
+}
+
+/// Finds the byte of a code-point in a codepage, if there is one.
+fn encode_glyph<C: CodepageTable>(glyph: u32) -> Option<u8> {
+    if glyph >= UNMAPPED as u32 {
+        return None;
+    }
+    return C::TABLE.iter().position(|x| *x as u32 == glyph).map(|x| x as u8);
+}
+
+/* --------------------------------- Testing -------------------------------- */
+
+#[test]
+fn test_single_byte_decoding() {
+    use crate::codepages::*;
+    assert_eq!(SingleByteEncoding::<Iso8859_1>::from_bytes(&[0x41, 0xE9, 0xFF], ()).unwrap().to_string().unwrap(), "Aéÿ");
+    assert_eq!(SingleByteEncoding::<Windows1252>::from_bytes(&[0x80, 0x93, 0x94], ()).unwrap().to_string().unwrap(), "€“”");
+    assert_eq!(SingleByteEncoding::<Koi8R>::from_bytes(&[0xED, 0xCF, 0xD3, 0xCB, 0xD7, 0xC1], ()).unwrap().to_string().unwrap(), "Москва");
+    assert_eq!(SingleByteEncoding::<Windows1251>::from_bytes(&[0xCC, 0xEE, 0xF1, 0xEA, 0xE2, 0xE0], ()).unwrap().to_string().unwrap(), "Москва");
+    assert_eq!(SingleByteEncoding::<Ibm437>::from_bytes(&[0xB0, 0xC9, 0xE1], ()).unwrap().to_string().unwrap(), "░╔ß");
+    assert_eq!(SingleByteEncoding::<MacRoman>::from_bytes(&[0x8E, 0xA5, 0xDB], ()).unwrap().to_string().unwrap(), "é•€");
+    assert_eq!(SingleByteEncoding::<Iso8859_15>::from_bytes(&[0xA4], ()).unwrap().to_string().unwrap(), "€");
+}
+
+#[test]
+fn test_single_byte_encoding() {
+    use crate::codepages::*;
+    assert_eq!(SingleByteEncoding::<Windows1252>::from_string("Öl fließt €5").unwrap().data, vec![0xD6, 0x6C, 0x20, 0x66, 0x6C, 0x69, 0x65, 0xDF, 0x74, 0x20, 0x80, 0x35]);
+    assert_eq!(SingleByteEncoding::<Iso8859_5>::from_string("Москва").unwrap().data, vec![0xBC, 0xDE, 0xE1, 0xDA, 0xD2, 0xD0]);
+    let err = SingleByteEncoding::<Iso8859_1>::from_string("a€b").err().unwrap();
+    assert_eq!(err.kind, UnmappableCodepoint);
+    assert_eq!(err.position, Some(crate::unicode_encoding::ErrorPosition::Codepoint(1)));
+    assert_eq!(err.code_units, vec![0x20AC]);
+    let (latin1, count) = SingleByteEncoding::<Iso8859_1>::from_utf_32_lossy(&Utf32::from_string("a€😎b").unwrap(), b'?');
+    assert_eq!(latin1.data, b"a??b");
+    assert_eq!(count, 2);
+    assert_eq!(SingleByteEncoding::<Iso8859_1>::from_utf_32(&Utf32::from_string("a€").unwrap()).data, b"a?");
+}
+
+#[test]
+fn test_undefined_codepage_bytes() {
+    use crate::codepages::*;
+    let err = SingleByteEncoding::<Windows1252>::from_bytes(&[0x41, 0x81], ()).err().unwrap();
+    assert_eq!(err.kind, UndefinedCodepageByte);
+    assert_eq!(err.position, Some(crate::unicode_encoding::ErrorPosition::Byte(1)));
+    let (decoded, count) = SingleByteEncoding::<Windows1252>::from_bytes_lossy(&[0x41, 0x81, 0x80], (), DecodingPolicy::Escape).unwrap();
+    assert_eq!(count, 1);
+    assert_eq!(decoded.to_utf_32().data, Utf32::from_string("A\\x81€").unwrap().data);
+}
+
+#[test]
+fn test_single_byte_tables() {
+    use crate::codepages::Codepage;
+    // Every byte used by a codepage must be read back to itself.
+    for codepage in Codepage::ALL {
+        let table = codepage.table();
+        for byte in 0..=255u8 {
+            if table[byte as usize] != UNMAPPED {
+                assert_eq!(table.iter().position(|x| *x == table[byte as usize]), Some(byte as usize), "{}", codepage.name());
+            }
+        }
+        // The low half is ASCII.
+        assert!((0..0x80).all(|x| table[x] as usize == x));
+    }
+}
+
+#[test]
+fn test_single_byte_incremental() {
+    use crate::codepages::*;
+    let mut decoder = SingleByteEncoding::<Koi8U>::decoder(());
+    let decoded = crate::incremental::decode_byte_per_byte(decoder.as_mut(), &[0xB6, 0xA7, 0xB4]).unwrap();
+    assert_eq!(decoded.to_string().unwrap(), "ІїЄ");
+    let mut encoder = SingleByteEncoding::<Koi8U>::encoder(());
+    assert_eq!(encoder.encode(&Utf32::from_string("Ії").unwrap()).unwrap(), vec![0xB6, 0xA7]);
+    let err = encoder.encode(&Utf32::from_string("a€").unwrap()).err().unwrap();
+    assert_eq!(err.position, Some(crate::unicode_encoding::ErrorPosition::Codepoint(3)));
+}
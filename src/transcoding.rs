@@ -0,0 +1,159 @@
+/// The `transcoding` module contains adapters that wraps readers and writers
+/// of bytes to convert their content from one encoding to an other on the fly.
+
+use crate::unicode_encoding::UnicodeEncodingError;
+use crate::unicode_encoding::UnicodeEncoding;
+use crate::incremental::{Decoder, Encoder};
+
+use std::io::{Read, Write};
+
+/// Size of the chunks read from the inner reader.
+const CHUNK_SIZE: usize = 4096;
+
+/// Wraps a reader of data encoded in an encoding and reads it in an other
+/// encoding.
+pub struct TranscodingReader<R: Read> {
+    inner: R,
+    decoder: Box<dyn Decoder>,
+    encoder: Box<dyn Encoder>,
+    /// Bytes already converted but not read yet.
+    output: Vec<u8>,
+    output_index: usize,
+    finished: bool,
+}
+
+impl<R: Read> TranscodingReader<R> {
+    /// Wraps a reader of data encoded in the encoding `I` to read it encoded
+    /// in the encoding `O`.
+    pub fn new<I: UnicodeEncoding + 'static, O: UnicodeEncoding + 'static>(inner: R, input_options: I::Options, output_options: O::Options) -> Self {
+        return Self::from_coders(inner, I::decoder(input_options), O::encoder(output_options));
+    }
+
+    /// Wraps a reader with an already made decoder and encoder.
+    pub fn from_coders(inner: R, decoder: Box<dyn Decoder>, encoder: Box<dyn Encoder>) -> Self {
+        return TranscodingReader{inner: inner, decoder: decoder, encoder: encoder, output: Vec::new(), output_index: 0, finished: false};
+    }
+
+    /// Returns the wrapped reader.
+    pub fn into_inner(self) -> R {
+        return self.inner;
+    }
+
+    /// Reads a new chunk from the inner reader and converts it.
+    fn fill_output(&mut self) -> std::io::Result<()> {
+        let mut chunk = [0u8; CHUNK_SIZE];
+        let size = self.inner.read(&mut chunk)?;
+        self.output.clear();
+        self.output_index = 0;
+        if size == 0 {
+            self.finished = true;
+            let utf32 = self.decoder.finish().map_err(to_io_error)?;
+            self.output = self.encoder.encode(&utf32).map_err(to_io_error)?;
+            self.output.extend(self.encoder.finish().map_err(to_io_error)?);
+        } else {
+            let utf32 = self.decoder.decode(&chunk[..size]).map_err(to_io_error)?;
+            self.output = self.encoder.encode(&utf32).map_err(to_io_error)?;
+        }
+        return Ok(());
+    }
+}
+
+impl<R: Read> Read for TranscodingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.output_index == self.output.len() {
+            if self.finished {
+                return Ok(0);
+            }
+            self.fill_output()?;
+        }
+        let size = std::cmp::min(buf.len(), self.output.len() - self.output_index);
+        buf[..size].copy_from_slice(&self.output[self.output_index..self.output_index+size]);
+        self.output_index += size;
+        return Ok(size);
+    }
+}
+
+/// Wraps a writer expecting data in an encoding and lets data in an other
+/// encoding be written to it. The `finish` function must be called once
+/// everything is written to detect glyphs left unfinished.
+pub struct TranscodingWriter<W: Write> {
+    inner: W,
+    decoder: Box<dyn Decoder>,
+    encoder: Box<dyn Encoder>,
+}
+
+impl<W: Write> TranscodingWriter<W> {
+    /// Wraps a writer expecting data encoded in the encoding `O` to write
+    /// data encoded in the encoding `I` to it.
+    pub fn new<I: UnicodeEncoding + 'static, O: UnicodeEncoding + 'static>(inner: W, input_options: I::Options, output_options: O::Options) -> Self {
+        return Self::from_coders(inner, I::decoder(input_options), O::encoder(output_options));
+    }
+
+    /// Wraps a writer with an already made decoder and encoder.
+    pub fn from_coders(inner: W, decoder: Box<dyn Decoder>, encoder: Box<dyn Encoder>) -> Self {
+        return TranscodingWriter{inner: inner, decoder: decoder, encoder: encoder};
+    }
+
+    /// Writes the end of the converted data, flushes the wrapped writer and
+    /// returns it.
+    pub fn finish(mut self) -> std::io::Result<W> {
+        let utf32 = self.decoder.finish().map_err(to_io_error)?;
+        let mut bytes = self.encoder.encode(&utf32).map_err(to_io_error)?;
+        bytes.extend(self.encoder.finish().map_err(to_io_error)?);
+        self.inner.write_all(&bytes)?;
+        self.inner.flush()?;
+        return Ok(self.inner);
+    }
+}
+
+impl<W: Write> Write for TranscodingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let utf32 = self.decoder.decode(buf).map_err(to_io_error)?;
+        let bytes = self.encoder.encode(&utf32).map_err(to_io_error)?;
+        self.inner.write_all(&bytes)?;
+        return Ok(buf.len());
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        return self.inner.flush();
+    }
+}
+
+/* ---------------------------- Helper functions ---------------------------- */
+
+/// Wraps an encoding error into an IO error.
+fn to_io_error(err: UnicodeEncodingError) -> std::io::Error {
+    return std::io::Error::new(std::io::ErrorKind::InvalidData, err);
+}
+
+/* --------------------------------- Testing -------------------------------- */
+
+#[test]
+fn test_transcoding_reader() {
+    use crate::utf_8::Utf8;
+    use crate::utf_16::Utf16;
+    use crate::endianness::Endianness;
+    let s = "aeé¤ㅢㅟ😎🐤";
+    let utf16_bytes = Utf16::from_string(s).unwrap().to_bytes(Endianness::Big);
+    let mut reader = TranscodingReader::new::<Utf16, Utf8>(utf16_bytes.as_slice(), Endianness::Big, ());
+    let mut converted = String::new();
+    reader.read_to_string(&mut converted).unwrap();
+    assert_eq!(converted, s);
+
+    let mut reader = TranscodingReader::new::<Utf16, Utf8>(&utf16_bytes[..3], Endianness::Big, ());
+    assert!(reader.read_to_end(&mut Vec::new()).is_err());
+}
+
+#[test]
+fn test_transcoding_writer() {
+    use crate::utf_8::Utf8;
+    use crate::utf_32::Utf32;
+    use crate::endianness::Endianness;
+    let s = "aeé¤ㅢㅟ😎🐤";
+    let mut writer = TranscodingWriter::new::<Utf8, Utf32>(Vec::new(), (), Endianness::Little);
+    for byte in s.as_bytes() {
+        writer.write_all(&[*byte]).unwrap();
+    }
+    let converted = writer.finish().unwrap();
+    assert_eq!(converted, Utf32::from_string(s).unwrap().to_bytes(Endianness::Little));
+}
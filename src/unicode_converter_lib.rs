@@ -1,3 +1,9 @@
+// The code base favors explicit returns and spelled-out struct fields.
+#![allow(clippy::needless_return, clippy::redundant_field_names, clippy::empty_line_after_doc_comments,
+         clippy::ptr_arg, clippy::assign_op_pattern, clippy::manual_range_contains, clippy::identity_op,
+         clippy::absurd_extreme_comparisons, clippy::inherent_to_string, clippy::manual_ok_err,
+         clippy::manual_is_multiple_of, clippy::unnecessary_cast, clippy::char_lit_as_u8)]
+
 /// The `unicode_encoding` module contains the `UnicodeEncoding` trait that
 /// contains the common capabilities for all Unicode encodings.
 pub mod unicode_encoding;
@@ -18,9 +24,148 @@ pub mod utf_16;
 /// UTF-16: 8-Bit__.
 pub mod cesu_8;
 
+/// The Modified UTF-8 module handles the variant of CESU-8 used by Java in
+/// its class files, by JNI and by the Android DEX files.
+pub mod modified_utf_8;
+
+/// The WTF-8 module handles the __Wobbly Transformation Format − 8-bit__,
+/// a superset of UTF-8 that can carry the unpaired surrogates of ill-formed
+/// UTF-16, such as the strings of JavaScript or the file names of Windows.
+pub mod wtf_8;
+
 /// This module handle the now deprecated UTF-1 encoding.
 pub mod utf_1;
 
+/// The UTF-EBCDIC module handles the EBCDIC-friendly Unicode encoding
+/// described in the Unicode Technical Report #16, used on IBM mainframes.
+pub mod utf_ebcdic;
+
+/// The UTF-7 module handles the __7-bit Unicode Transformation Format__
+/// described in the RFC 2152, used by old mail software.
+pub mod utf_7;
+
+/// The IMAP UTF-7 module handles the modified UTF-7 used for the mailbox
+/// names of IMAP, described in the section 5.1.3 of the RFC 3501.
+pub mod imap_utf_7;
+
+/// The SCSU module handles the __Standard Compression Scheme for Unicode__
+/// described in the Unicode Technical Standard #6.
+pub mod scsu;
+
+/// The BOCU-1 module handles the __Binary Ordered Compression for Unicode__
+/// described in the Unicode Technical Note #6.
+pub mod bocu_1;
+
+/// The Punycode module handles the encoding of Unicode strings into the
+/// letters, digits and hyphens allowed in host names, described in the
+/// RFC 3492, and its use in the internationalized domain names.
+pub mod punycode;
+
+/// The `single_byte` module handles the legacy codepages where each byte is a
+/// character, such as ISO-8859-1 or Windows-1252, from a table giving the
+/// code-point of each byte.
+pub mod single_byte;
+
+/// The `codepages` module contains the tables of the legacy single-byte
+/// codepages, to be used with `SingleByteEncoding`.
+pub mod codepages;
+
+/// The `multi_byte` module handles the legacy encodings of East Asia where a
+/// character is one or more bytes, such as Shift_JIS or EUC-KR. Except
+/// GB18030, they can't encode every code-point, like the single-byte
+/// codepages.
+pub mod multi_byte;
+
+/// The Shift_JIS module handles the legacy Japanese encoding of Windows,
+/// mixing the half-width katakana of JIS X 0201 in one byte with the
+/// characters of JIS X 0208 in two bytes, as in the WHATWG Encoding Standard.
+pub mod shift_jis;
+
+/// The EUC-JP module handles the __Extended Unix Code__ for Japanese, where
+/// the characters of JIS X 0208 are two bytes, the half-width katakana are
+/// prefixed with 0x8E and the characters of JIS X 0212 with 0x8F.
+pub mod euc_jp;
+
+/// The EUC-KR module handles the legacy Korean encoding, as extended by
+/// Windows with the Unified Hangul Code to cover all the modern syllables.
+pub mod euc_kr;
+
+/// The Big5 module handles the legacy encoding of traditional Chinese, with
+/// the extensions of the Hong Kong Supplementary Character Set, as in the
+/// WHATWG Encoding Standard.
+pub mod big5;
+
+/// The GBK module handles the legacy encoding of simplified Chinese extending
+/// GB 2312, used by Windows as its codepage 936. GBK is the part of GB18030
+/// made of one and two bytes.
+pub mod gbk;
+
+/// The GB18030 module handles the Chinese national standard extending GBK
+/// with four-byte characters, which makes it able to encode every Unicode
+/// code-point.
+pub mod gb18030;
+
+/// The `iso_2022` module handles the stateful encodings of East Asia, where
+/// escape sequences switch between character sets, keeping the character set
+/// in use from one character to the next.
+pub mod iso_2022;
+
+/// The ISO-2022-JP module handles the Japanese encoding of the mails, where
+/// escape sequences switch between ASCII, JIS X 0201 and JIS X 0208.
+pub mod iso_2022_jp;
+
+/// The ISO-2022-KR module handles the Korean encoding of the mails, where the
+/// Shift Out and Shift In controls switch between ASCII and KS X 1001.
+pub mod iso_2022_kr;
+
+/// The HZ-GB-2312 module handles the encoding of simplified Chinese used in
+/// the mails and the news, where `~{` and `~}` switch between ASCII and
+/// GB 2312.
+pub mod hz_gb_2312;
+
+/// The GSM 7-bit module handles the alphabet of the SMS, with its extension
+/// table and national language shift tables, and tells how many messages a
+/// text takes.
+pub mod gsm_7;
+
+/// The ISO 6937 module handles the Latin encoding used by the DVB and by the
+/// `T61String` of LDAP, where the diacritics are bytes preceding the base
+/// letter.
+pub mod iso_6937;
+
+/// The `mapping` module loads charsets at runtime from the mapping files of
+/// the Unicode Consortium or of ICU, for the charsets not shipped with this
+/// crate.
+pub mod mapping;
+
+/// The `cjk_indexes` module contains the indexes of the legacy multi-byte
+/// encodings of East Asia, giving the code-point of each pointer.
+mod cjk_indexes;
+
+/// The `incremental` module contains the `Decoder` and `Encoder` traits used
+/// to convert data chunk by chunk, as it is read from or written to a stream.
+pub mod incremental;
+
+/// The `lossy` module contains the policies used to decode data that is not
+/// fully valid, replacing the invalid sequences instead of giving up.
+pub mod lossy;
+
+/// The `transcoding` module contains adapters that wraps readers and writers
+/// of bytes to convert their content from one encoding to an other on the fly.
+pub mod transcoding;
+
+/// The `endianness` module contains the `Endianness` type used to tell the
+/// order of the bytes of the encodings with multi-byte code units.
+pub mod endianness;
+
+/// The `encoding` module contains the `Encoding` enumeration used to choose
+/// an encoding at runtime, for example from its name in a configuration file.
+pub mod encoding;
+
+/// The `detection` module guesses the encoding of data whose encoding is not
+/// known.
+pub mod detection;
+
 /// This module is used to convert from stream of bytes to streams of numbers
 /// knowing and taking care about the endianness. It works with any number type
 /// that can be bit-cased to u64.
@@ -33,7 +178,12 @@ mod test {
     use crate::cesu_8::Cesu8;
     use crate::utf_8::Utf8;
     use crate::utf_1::Utf1;
+    use crate::utf_7::Utf7;
+    use crate::scsu::Scsu;
+    use crate::bocu_1::Bocu1;
+    use crate::gb18030::Gb18030;
     use crate::unicode_encoding::UnicodeEncoding;
+    use crate::endianness::Endianness;
 
 
     #[test]
@@ -46,28 +196,40 @@ mod test {
                 random_bytes.push(0);
             }
         }
-        let utf32_glyphs = Utf32::from_bytes(random_bytes.as_slice(), false).unwrap();
-        let converted_bytes = utf32_glyphs.to_bytes(false);
+        let utf32_glyphs = Utf32::from_bytes(random_bytes.as_slice(), Endianness::Little).unwrap();
+        let converted_bytes = utf32_glyphs.to_bytes(Endianness::Little);
         assert_eq!(converted_bytes, random_bytes);
     }
 
     /// Tests all types conversion.
     fn string_conv(reference: &str) {
         let utf32 = Utf32::from_string(reference).unwrap();
-        let conv_1 = utf32.to_string();
+        let conv_1 = utf32.to_string().unwrap();
         assert_eq!(reference, conv_1);
         let utf8 = Utf8::from_string(reference).unwrap();
-        let conv_2 = utf8.to_string();
+        let conv_2 = utf8.to_string().unwrap();
         assert_eq!(reference, conv_2);
         let utf16 = Utf16::from_string(reference).unwrap();
-        let conv_3 = utf16.to_string();
+        let conv_3 = utf16.to_string().unwrap();
         assert_eq!(reference, conv_3);
         let cesu8 = Cesu8::from_string(reference).unwrap();
-        let conv_4 = cesu8.to_string();
+        let conv_4 = cesu8.to_string().unwrap();
         assert_eq!(reference, conv_4);
         let utf1 = Utf1::from_string(reference).unwrap();
-        let conv_5 = utf1.to_string();
+        let conv_5 = utf1.to_string().unwrap();
         assert_eq!(reference, conv_5);
+        let utf7 = Utf7::from_string(reference).unwrap();
+        let conv_6 = utf7.to_string().unwrap();
+        assert_eq!(reference, conv_6);
+        let scsu = Scsu::from_string(reference).unwrap();
+        let conv_7 = scsu.to_string().unwrap();
+        assert_eq!(reference, conv_7);
+        let bocu1 = Bocu1::from_string(reference).unwrap();
+        let conv_8 = bocu1.to_string().unwrap();
+        assert_eq!(reference, conv_8);
+        let gb18030 = Gb18030::from_string(reference).unwrap();
+        let conv_9 = gb18030.to_string().unwrap();
+        assert_eq!(reference, conv_9);
     }
 
     #[test]
@@ -79,11 +241,11 @@ mod test {
     #[test]
     fn test_null() {
         let bytes: [u8; 2] = ['a' as u8, 0];
-        let utf8 = match Utf8::from_bytes(&bytes, false) {
+        let utf8 = match Utf8::from_bytes(&bytes, ()) {
             Ok(x) => x,
             Err(_) => {panic!("Error in utf8 from bytes.\n");}
         };
-        let s = utf8.to_string();
+        let s = utf8.to_string().unwrap();
         assert_eq!(s, "a\0");
     }
 
@@ -94,5 +256,20 @@ mod test {
         let conv = Utf32::from_string(utf8_str).unwrap();
         assert!(conv == unicode_codepoints);
     }
-}
 
+    #[test]
+    fn invalid_content_does_not_panic() {
+        let utf8 = Utf8{data: vec![0x61, 0xFF]};
+        assert!(utf8.try_to_utf_32().is_err());
+        assert!(utf8.convert_to::<Utf16>().is_err());
+        assert!(utf8.to_string().is_err());
+        assert!(!utf8.content_eq(&utf8));
+        let utf1 = Utf1{data: vec![0xFC, 0x21]};
+        assert!(utf1.convert_to::<Utf8>().is_err());
+        let cesu8 = Cesu8{data: Utf8{data: vec![0xC3]}};
+        assert!(cesu8.to_string().is_err());
+        let utf32 = Utf32{data: vec![0x41, 0x110000]};
+        assert!(utf32.convert_to::<Utf16>().is_err());
+        assert!(Utf8::try_from_utf_32(&Utf32{data: vec![0x4000000]}).is_err());
+    }
+}
@@ -3,9 +3,15 @@
 
 use crate::utf_32::Utf32;
 use crate::utf_8::Utf8;
+use crate::utf_16;
+use crate::incremental::{Decoder, Encoder, BufferedDecoder, BufferedEncoder};
+use crate::lossy::{self, DecodingPolicy};
 
 use std::fs;
 
+/// The code-point used as Byte Order Mark.
+pub const BOM_CODEPOINT: u32 = 0xFEFF;
+
 /// The `UnicodeEncoding` trait contains the basic function shared with all
 /// the other encodings in this crate. This is converting the data from and to
 /// UTF-32 and writing it to or reading it from a file. Furthermore, it is also
@@ -17,77 +23,193 @@ pub trait UnicodeEncoding {
     /// the encoding's type.
     fn from_utf_32(data_utf_32: &Utf32) -> Self;
 
-    /// The function `to_utf_32` converts data from the desired encoding to
-    /// UTF-32. It should always be implemented by the encoding's type.
-    fn to_utf_32(&self) -> Utf32;
+    /// The function `try_to_utf_32` converts data from the desired encoding
+    /// to UTF-32. If the content of the type is not valid, as it can be when
+    /// it has been filled by hand, an error is returned instead. It should
+    /// always be implemented by the encoding's type.
+    fn try_to_utf_32(&self) -> Result<Utf32, UnicodeEncodingError>;
 
     /// The function `from_bytes_no_check` takes a stream of bytes and
     /// interpret it as it was in the desired encoding. It should always be
     /// implemented by the encoding's type. This does not uses the check_sanity
     /// function.
-    fn from_bytes_no_check(bytes: &[u8], big_endian: bool) -> Result<Self, UnicodeEncodingError> where Self: Sized;
+    fn from_bytes_no_check(bytes: &[u8], options: Self::Options) -> Result<Self, UnicodeEncodingError> where Self: Sized;
 
     /// The function `to_bytes` takes the raw-data of encoded content
     /// and convert it to a vector of bytes. It should always be implemented by
     /// the encoding's type.
-    fn to_bytes(&self, big_endian: bool) -> Vec<u8>;
+    fn to_bytes(&self, options: Self::Options) -> Vec<u8>;
+
+    /// The options telling how the encoding is turned into bytes, such as the
+    /// endianness of UTF-16. Encodings without options use `()`.
+    type Options: Copy;
+
+    /// The name of the encoding, used in error messages.
+    const NAME: &'static str;
 
     // Functions implemented in this trait
+    /// The function `to_utf_32` converts data from the desired encoding to
+    /// UTF-32. It panics if the content of the type is not valid, use
+    /// `try_to_utf_32` when the content might not have been checked.
+    fn to_utf_32(&self) -> Utf32 {
+        match self.try_to_utf_32() {
+            Ok(x) => x,
+            Err(x) => panic!("[UNICODE ENCODING ERROR] {}. This should not have happen if the source was safely generated with from_string or from_bytes. This need to be corrected from the library's user side.", x),
+        }
+    }
+
+    /// The function `try_from_utf_32` works like `from_utf_32` but checks
+    /// the UTF-32 data first. An error is returned if it can't be encoded
+    /// instead of panicking.
+    fn try_from_utf_32(data_utf_32: &Utf32) -> Result<Self, UnicodeEncodingError> where Self: Sized {
+        data_utf_32.check_sanity_utf32().map_err(|x| x.in_encoding(Utf32::NAME))?;
+        return Ok(Self::from_utf_32(data_utf_32));
+    }
+
     /// Converts an Unicode encoded content and converts it to Rust's string.
-    fn to_string(&self) -> String {
-        let utf8 = self.convert_to::<Utf8>();
+    fn to_string(&self) -> Result<String, UnicodeEncodingError> {
+        let utf8 = self.convert_to::<Utf8>()?;
         return utf8.to_string();
     }
 
     /// Takes a Rust string and converts it into Unicode encoded content.
     fn from_string(s: &str) -> Result<Self, UnicodeEncodingError> where Self: Sized {
         let utf8 = Utf8::from_string(s)?;
-        return Ok(utf8.convert_to::<Self>());
+        return utf8.convert_to::<Self>();
     }
 
-    /// Converts from one Unicode encoding to an other.
-    fn convert_to<T: UnicodeEncoding> (&self) -> T {
-        let utf32 = self.to_utf_32();
-        let ret: T = T::from_utf_32(&utf32);
-        return ret;
+    /// Converts from one Unicode encoding to an other. An error is returned
+    /// if the content is not valid.
+    fn convert_to<T: UnicodeEncoding> (&self) -> Result<T, UnicodeEncodingError> {
+        let utf32 = self.try_to_utf_32()?;
+        return T::try_from_utf_32(&utf32);
     }
 
     /// Tell if the encoded content is equal to an other encoded content,
-    /// regardless of the chosen encoding.
+    /// regardless of the chosen encoding. Invalid content is never equal to
+    /// anything.
     fn content_eq<T: UnicodeEncoding>(&self, other: &T) -> bool {
-        return self.to_utf_32() == other.to_utf_32();
+        match (self.try_to_utf_32(), other.try_to_utf_32()) {
+            (Ok(x), Ok(y)) => x == y,
+            _ => false,
+        }
+    }
+
+    /// Tells if the content is well-formed Unicode: it is valid and contains
+    /// no unpaired surrogates. Such content can be converted to any encoding,
+    /// including Rust's strings.
+    fn is_well_formed(&self) -> bool {
+        match self.try_to_utf_32() {
+            Ok(x) => x.check_sanity_utf32().is_ok() && !x.data.iter().any(|y| utf_16::is_surrogate(*y)),
+            Err(_) => false,
+        }
     }
 
     /// Checks that the unicode data is valid.
-    fn check_sanity(&self) -> UnicodeEncodingError {
-        let utf32 = self.to_utf_32();
-        return utf32.check_sanity_utf32();
+    fn check_sanity(&self) -> Result<(), UnicodeEncodingError> {
+        let utf32 = self.try_to_utf_32()?;
+        return utf32.check_sanity_utf32().map_err(|x| x.in_encoding(Self::NAME));
     }
 
     /// The function `from_bytes` takes a stream of bytes and interpret it as
     /// it was in the desired encoding. It should always be implemented by the
     /// encoding's type.
-    fn from_bytes(bytes: &[u8], big_endian: bool) -> Result<Self, UnicodeEncodingError> where Self: Sized {
-        let ret: Self = Self::from_bytes_no_check(bytes, big_endian)?;
-        match ret.check_sanity() {
-            UnicodeEncodingError::NoError => Ok(ret),
-            x => Err(x),
+    fn from_bytes(bytes: &[u8], options: Self::Options) -> Result<Self, UnicodeEncodingError> where Self: Sized {
+        let ret: Self = Self::from_bytes_no_check(bytes, options)?;
+        ret.check_sanity()?;
+        return Ok(ret);
+    }
+
+    /// The function `from_bytes_lossy` takes a stream of bytes and interpret
+    /// it as it was in the desired encoding. The invalid sequences are handled
+    /// as told by the policy instead of stopping the decoding. The number of
+    /// invalid sequences met is returned along the data.
+    /// By default, the decoding is restarted after each invalid sequence,
+    /// encodings that can be decoded glyph by glyph should override this
+    /// function.
+    fn from_bytes_lossy(bytes: &[u8], options: Self::Options, policy: DecodingPolicy) -> Result<(Self, usize), UnicodeEncodingError> where Self: Sized {
+        let mut data: Vec<u32> = Vec::new();
+        let mut count: usize = 0;
+        let mut start: usize = 0;
+        while start < bytes.len() {
+            let err = match Self::from_bytes_no_check(&bytes[start..], options) {
+                Ok(x) => {
+                    data.extend(x.try_to_utf_32()?.data);
+                    break;
+                },
+                Err(x) => x.shifted(start),
+            };
+            let offset = match err.position {
+                Some(ErrorPosition::Byte(x)) if x >= start => x,
+                _ => return Err(err),
+            };
+            data.extend(Self::from_bytes_no_check(&bytes[start..offset], options)?.try_to_utf_32()?.data);
+            data.extend(policy.handle(&err)?);
+            count += 1;
+            start = offset + std::cmp::max(1, err.code_units.len());
+        }
+        let (data, sanitized) = lossy::sanitize(&data, policy).map_err(|x| x.in_encoding(Self::NAME))?;
+        return Ok((Self::from_utf_32(&Utf32{data: data}), count + sanitized));
+    }
+
+    /// The function `decode_lossy` works like `from_bytes_lossy` but returns
+    /// the decoded code-points. The encodings that can't encode every
+    /// code-point, such as the codepages, should override this function, so
+    /// that the code-points given by the policy, such as U+FFFD, are kept.
+    fn decode_lossy(bytes: &[u8], options: Self::Options, policy: DecodingPolicy) -> Result<(Utf32, usize), UnicodeEncodingError> where Self: Sized {
+        let (data, count) = Self::from_bytes_lossy(bytes, options, policy)?;
+        return Ok((data.try_to_utf_32()?, count));
+    }
+
+    /// The Byte Order Mark of the encoding, that is U+FEFF encoded with it.
+    fn bom(options: Self::Options) -> Vec<u8> where Self: Sized {
+        return Self::from_utf_32(&Utf32{data: vec![BOM_CODEPOINT]}).to_bytes(options);
+    }
+
+    /// Removes the BOM of the encoding at the beginning of a stream of bytes,
+    /// if there is one.
+    fn strip_bom(bytes: &[u8], options: Self::Options) -> &[u8] where Self: Sized {
+        let bom = Self::bom(options);
+        match bytes.strip_prefix(bom.as_slice()) {
+            Some(x) => x,
+            None => bytes,
         }
     }
 
+    /// Works like `to_bytes` but the bytes start with a BOM.
+    fn to_bytes_with_bom(&self, options: Self::Options) -> Vec<u8> where Self: Sized {
+        let mut ret = Self::bom(options);
+        ret.extend(self.to_bytes(options));
+        return ret;
+    }
+
     /// Reads a file containing data encoded in an Unicode. If the file can't
     /// be opened, an io error is returned. If the file can be open but the
     /// data is not valid, an UnicodeEncodingError will be returned. If
     /// everything goes well, the data is returned.
-    fn from_file(filename: &str, big_endian: bool) -> Result<Result<Self, UnicodeEncodingError>, std::io::Error> where Self: Sized {
+    fn from_file(filename: &str, options: Self::Options) -> Result<Result<Self, UnicodeEncodingError>, std::io::Error> where Self: Sized {
         let bytes = fs::read(filename)?;
-        return Ok(Self::from_bytes(&bytes, big_endian));
+        return Ok(Self::from_bytes(&bytes, options));
+    }
+
+    /// Creates a `Decoder` used to decode the encoding chunk by chunk. By
+    /// default, the bytes are buffered until the end of the stream, encodings
+    /// that can be decoded incrementally should override this function.
+    fn decoder(options: Self::Options) -> Box<dyn Decoder> where Self: Sized + 'static {
+        return Box::new(BufferedDecoder::<Self>::new(options));
+    }
+
+    /// Creates an `Encoder` used to encode the encoding chunk by chunk. By
+    /// default, the glyphs are buffered until the end of the stream, encodings
+    /// that can be encoded incrementally should override this function.
+    fn encoder(options: Self::Options) -> Box<dyn Encoder> where Self: Sized + 'static {
+        return Box::new(BufferedEncoder::<Self>::new(options));
     }
 
     /// Writes Unicode data to a file. If that can be done, None is returned.
     /// If there is an IO error, the IO error is returned in the `Some`.
-    fn to_file(data: &Self, filename: &str, big_endian: bool) -> Option<std::io::Error> {
-        let bytes = data.to_bytes(big_endian);
+    fn to_file(data: &Self, filename: &str, options: Self::Options) -> Option<std::io::Error> {
+        let bytes = data.to_bytes(options);
         match fs::write(filename, &bytes) {
             Ok(_) => None,
             Err(x) => Some(x),
@@ -95,11 +217,108 @@ pub trait UnicodeEncoding {
     }
 }
 
-#[derive(Debug)]
-pub enum UnicodeEncodingError {
-    /// Indicate an absence of error from a function checking for errors.
-    NoError,
+/// The error returned when some data is not valid. It tells what is wrong
+/// with the data and, when it is known, where the problem is, which code
+/// units are faulty and which encoding was being processed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnicodeEncodingError {
+    /// What is wrong with the data.
+    pub kind: UnicodeEncodingErrorKind,
+
+    /// Where the faulty data is.
+    pub position: Option<ErrorPosition>,
+
+    /// The faulty code units. They are bytes when the error is located in a
+    /// stream of bytes and code-points when it is located in a list of
+    /// code-points.
+    pub code_units: Vec<u32>,
+
+    /// Name of the encoding being processed.
+    pub encoding: Option<&'static str>,
+}
+
+impl UnicodeEncodingError {
+    /// Creates an error with no information other than its kind.
+    pub fn new(kind: UnicodeEncodingErrorKind) -> Self {
+        return UnicodeEncodingError{kind: kind, position: None, code_units: Vec::new(), encoding: None};
+    }
+
+    /// Sets the position of the error as an offset in a stream of bytes.
+    pub fn at_byte(mut self, offset: usize) -> Self {
+        self.position = Some(ErrorPosition::Byte(offset));
+        return self;
+    }
 
+    /// Sets the position of the error as an index in a list of code-points.
+    pub fn at_codepoint(mut self, index: usize) -> Self {
+        self.position = Some(ErrorPosition::Codepoint(index));
+        return self;
+    }
+
+    /// Sets the faulty code units.
+    pub fn with_code_units<T: Copy + Into<u32>>(mut self, code_units: &[T]) -> Self {
+        self.code_units = code_units.iter().map(|x| (*x).into()).collect();
+        return self;
+    }
+
+    /// Sets the name of the encoding being processed.
+    pub fn in_encoding(mut self, encoding: &'static str) -> Self {
+        self.encoding = Some(encoding);
+        return self;
+    }
+
+    /// Moves the position of the error further. This is used when the data
+    /// with the error was a part of a bigger stream.
+    pub(crate) fn shifted(mut self, offset: usize) -> Self {
+        self.position = match self.position {
+            Some(ErrorPosition::Byte(x)) => Some(ErrorPosition::Byte(x + offset)),
+            Some(ErrorPosition::Codepoint(x)) => Some(ErrorPosition::Codepoint(x + offset)),
+            None => None,
+        };
+        return self;
+    }
+}
+
+impl From<UnicodeEncodingErrorKind> for UnicodeEncodingError {
+    fn from(kind: UnicodeEncodingErrorKind) -> Self {
+        return UnicodeEncodingError::new(kind);
+    }
+}
+
+impl std::fmt::Display for UnicodeEncodingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.kind)?;
+        match self.position {
+            Some(ErrorPosition::Byte(x)) => write!(f, " at byte {}", x)?,
+            Some(ErrorPosition::Codepoint(x)) => write!(f, " at code-point {}", x)?,
+            None => {},
+        }
+        if let Some(encoding) = self.encoding {
+            write!(f, " while processing {}", encoding)?;
+        }
+        if !self.code_units.is_empty() {
+            let units: Vec<String> = self.code_units.iter().map(|x| format!("{:02X}", x)).collect();
+            write!(f, " (faulty code units: {})", units.join(" "))?;
+        }
+        return Ok(());
+    }
+}
+
+impl std::error::Error for UnicodeEncodingError {}
+
+/// Where an error is located in the data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorPosition {
+    /// Offset in a stream of bytes.
+    Byte(usize),
+
+    /// Index in a list of code-points.
+    Codepoint(usize),
+}
+
+/// The various kinds of problems that can make Unicode data invalid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnicodeEncodingErrorKind {
     /// Indicate that bits above the 21th bit in a code-point are not empty.
     /// This means that the code-point is invalid.
     InvalidCodepointTooManyBits,
@@ -118,8 +337,9 @@ pub enum UnicodeEncodingError {
     /// not contain a number of bytes that is a multiple of 4.
     InvalidStreamSize,
 
-    /// Two code-points that would be unpaired surrogates as UTF-16 are next to
-    /// each others, thus this would be ambiguous ti use as UTF-16.
+    /// An unpaired high surrogate is followed by an unpaired low surrogate,
+    /// thus this would be ambiguous ti use as UTF-16 as they would be read
+    /// back as a pair.
     AmbiguousUnpairedSurrogates,
 
     /// Used internally to managed unpaired surrogate.
@@ -128,5 +348,90 @@ pub enum UnicodeEncodingError {
     /// For variable length encoding such as UTF-8, there is not enough bytes
     /// to finish a glyph.
     MissingEncodedBytes,
+
+    /// A glyph is encoded with more bytes than needed, such as `C0 80` for
+    /// U+0000 in UTF-8.
+    OverlongUtf8Encoding,
+
+    /// A surrogate code-point is encoded directly in UTF-8, which is not
+    /// allowed by the Unicode Standard.
+    EncodedSurrogate,
+
+    /// A code-point is above U+10FFFF, the last Unicode code-point.
+    CodepointAboveUnicodeRange,
+
+    /// A byte can't be found in UTF-7 data, such as a byte above 0x7F.
+    InvalidUtf7Byte,
+
+    /// The bits left at the end of an UTF-7 base64 sequence are not the zero
+    /// padding of the last code unit.
+    InvalidUtf7Padding,
+
+    /// A `00` byte is found in Modified UTF-8 data, where U+0000 must be
+    /// encoded as `C0 80`.
+    RawNulByte,
+
+    /// A byte of UTF-EBCDIC data can't start a glyph, or is not a
+    /// continuation byte where one is needed.
+    InvalidUtfEbcdicSequence,
+
+    /// A reserved tag byte of SCSU, or a reserved byte defining the offset of
+    /// a dynamic window.
+    ReservedScsuByte,
+
+    /// A byte that can't be a trail byte of BOCU-1 is found where one is
+    /// needed.
+    InvalidBocu1TrailByte,
+
+    /// A byte of Punycode data is not a base 36 digit, or a non-ASCII byte is
+    /// found before the delimiter.
+    InvalidPunycodeByte,
+
+    /// A number encoded in Punycode is too big.
+    PunycodeOverflow,
+
+    /// A code-point can't be encoded in a legacy codepage, as it has no
+    /// character for it.
+    UnmappableCodepoint,
+
+    /// A byte is not used by the legacy codepage of the data.
+    UndefinedCodepageByte,
+
+    /// A sequence of bytes is not a character of the legacy multi-byte
+    /// encoding of the data.
+    InvalidMultiByteSequence,
+
+    /// An escape sequence of a stateful encoding, such as ISO-2022-JP, does
+    /// not designate a known character set.
+    InvalidEscapeSequence,
 }
 
+impl std::fmt::Display for UnicodeEncodingErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let description = match self {
+            UnicodeEncodingErrorKind::InvalidCodepointTooManyBits => "code-point too big",
+            UnicodeEncodingErrorKind::InvalidUtf8Prefix => "invalid UTF-8 prefix",
+            UnicodeEncodingErrorKind::IncoherentUtf8Codepoint => "incoherent UTF-8 code-point",
+            UnicodeEncodingErrorKind::InvalidStreamSize => "invalid stream size",
+            UnicodeEncodingErrorKind::AmbiguousUnpairedSurrogates => "ambiguous unpaired surrogates",
+            UnicodeEncodingErrorKind::UnpairedSurrogateNotification => "unpaired surrogate",
+            UnicodeEncodingErrorKind::MissingEncodedBytes => "missing encoded bytes",
+            UnicodeEncodingErrorKind::OverlongUtf8Encoding => "overlong UTF-8 encoding",
+            UnicodeEncodingErrorKind::EncodedSurrogate => "encoded surrogate",
+            UnicodeEncodingErrorKind::CodepointAboveUnicodeRange => "code-point above U+10FFFF",
+            UnicodeEncodingErrorKind::InvalidUtf7Byte => "invalid UTF-7 byte",
+            UnicodeEncodingErrorKind::InvalidUtf7Padding => "invalid UTF-7 padding",
+            UnicodeEncodingErrorKind::RawNulByte => "raw NUL byte",
+            UnicodeEncodingErrorKind::InvalidUtfEbcdicSequence => "invalid UTF-EBCDIC sequence",
+            UnicodeEncodingErrorKind::ReservedScsuByte => "reserved SCSU byte",
+            UnicodeEncodingErrorKind::InvalidBocu1TrailByte => "invalid BOCU-1 trail byte",
+            UnicodeEncodingErrorKind::InvalidPunycodeByte => "invalid Punycode byte",
+            UnicodeEncodingErrorKind::PunycodeOverflow => "Punycode overflow",
+            UnicodeEncodingErrorKind::UnmappableCodepoint => "code-point not in the codepage",
+            UnicodeEncodingErrorKind::UndefinedCodepageByte => "byte not in the codepage",
+            UnicodeEncodingErrorKind::InvalidMultiByteSequence => "invalid multi-byte sequence",
+            UnicodeEncodingErrorKind::InvalidEscapeSequence => "invalid escape sequence",
+        };
+        return write!(f, "{}", description);
+    }
+}
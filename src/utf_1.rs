@@ -1,9 +1,11 @@
 /// This module handle the now deprecated UTF-1 encoding.
 
-use crate::unicode_encoding::UnicodeEncodingError::*;
+use crate::unicode_encoding::UnicodeEncodingErrorKind::*;
 use crate::unicode_encoding::UnicodeEncodingError;
 use crate::unicode_encoding::UnicodeEncoding;
 use crate::utf_32::Utf32;
+use crate::incremental::{Decoder, Encoder, SanityChecker};
+use crate::lossy::{self, DecodingPolicy};
 
 /// A very basic wrapper for UTF-1 encoded data.
 pub struct Utf1 {
@@ -12,22 +14,22 @@ pub struct Utf1 {
 
 impl Utf1 {
     /// Goes through a whole UTF-1 data to ensure that it is valid.
-    fn check_sanity_utf1(&self) -> UnicodeEncodingError {
+    fn check_sanity_utf1(&self) -> Result<(), UnicodeEncodingError> {
         let mut index: usize = 0;
         while index < self.data.len() {
-            let (_glyph, len) = match utf_1_glyph_to_utf_32(&self.data, index) {
-                Err(x) => {return x;},
-                Ok(x) => x,
-            };
+            let (_glyph, len) = utf_1_glyph_to_utf_32(&self.data, index)?;
             index += len;
             check_index_ok(index, &self.data);
         }
-        return NoError;
+        return Ok(());
     }
 
 }
 
 impl UnicodeEncoding for Utf1 {
+    const NAME: &'static str = "UTF-1";
+    type Options = ();
+
     /// Convert UTF-32 data to UTF-1.
     fn from_utf_32(data_utf_32: &Utf32) -> Self {
         let mut data: Vec<u8> = Vec::new();
@@ -41,38 +43,103 @@ impl UnicodeEncoding for Utf1 {
     }
 
     /// Convert UFT-1 data to UTF-32.
-    fn to_utf_32(&self) -> Utf32 {
+    fn try_to_utf_32(&self) -> Result<Utf32, UnicodeEncodingError> {
         let mut index: usize = 0;
         let mut data: Vec<u32> = Vec::new();
         while index < self.data.len() {
-            let (glyph, len) = match utf_1_glyph_to_utf_32(&self.data, index) {
-                Err(_) => {panic!("[UNICODE ENCODING ERROR] Invalid UTF-1 glyph. This should not have happen as a MissingEncodedBytes should have been raised earlier..");},
-                Ok(x) => x,
-            };
+            let (glyph, len) = utf_1_glyph_to_utf_32(&self.data, index).map_err(|x| x.in_encoding(Self::NAME))?;
             data.push(glyph);
             index += len;
-            check_index_ok(index, &self.data);
         }
-        return Utf32{data: data};
+        return Ok(Utf32{data: data});
     }
 
     /// Convert the instance of `Utf1` type to a vector of byte.
     /// No transformation is needed.
-    fn to_bytes(&self, _big_endian: bool) -> Vec<u8> {
+    fn to_bytes(&self, _options: ()) -> Vec<u8> {
         let ret = self.data.clone();
         return ret;
     }
 
     /// Consider a stream of UTF-1 encoded byte and turn it into a `Utf8` type.
     /// It only copies the bytes
-    fn from_bytes_no_check(bytes: &[u8], _big_endian: bool) -> Result<Self, UnicodeEncodingError> {
+    fn from_bytes_no_check(bytes: &[u8], _options: ()) -> Result<Self, UnicodeEncodingError> {
         let ret = Utf1{data: bytes.to_vec()};
-        match ret.check_sanity_utf1() {
-            NoError =>  Ok(ret),
-            x => Err(x),
+        ret.check_sanity_utf1().map_err(|x| x.in_encoding(Self::NAME))?;
+        return Ok(ret);
+    }
+
+    /// Decodes UTF-1 glyph by glyph, replacing the invalid sequences.
+    fn from_bytes_lossy(bytes: &[u8], _options: (), policy: DecodingPolicy) -> Result<(Self, usize), UnicodeEncodingError> {
+        let (data, count) = lossy::decode_glyphs_lossy(bytes, utf_1_glyph_to_utf_32, policy).map_err(|x| x.in_encoding(Self::NAME))?;
+        let (data, sanitized) = lossy::sanitize(&data, policy).map_err(|x| x.in_encoding(Self::NAME))?;
+        return Ok((Self::from_utf_32(&Utf32{data: data}), count + sanitized));
+    }
+
+    fn decoder(_options: ()) -> Box<dyn Decoder> {
+        return Box::new(Utf1Decoder{pending: Vec::new(), consumed: 0, checker: SanityChecker::new()});
+    }
+
+    fn encoder(_options: ()) -> Box<dyn Encoder> {
+        return Box::new(Utf1Encoder{checker: SanityChecker::new()});
+    }
+}
+
+/* --------------------------- Incremental coding --------------------------- */
+
+/// Decodes UTF-1 data chunk by chunk.
+pub struct Utf1Decoder {
+    pending: Vec<u8>,
+    consumed: usize,
+    checker: SanityChecker,
+}
+
+impl Decoder for Utf1Decoder {
+    fn decode(&mut self, bytes: &[u8]) -> Result<Utf32, UnicodeEncodingError> {
+        self.pending.extend_from_slice(bytes);
+        let mut index: usize = 0;
+        let mut data: Vec<u32> = Vec::new();
+        while index < self.pending.len() {
+            match utf_1_glyph_to_utf_32(&self.pending, index) {
+                Ok((glyph, len)) => {
+                    data.push(glyph);
+                    index += len;
+                },
+                Err(x) if x.kind == MissingEncodedBytes => break,
+                Err(x) => return Err(x.shifted(self.consumed).in_encoding(Utf1::NAME)),
+            }
+        }
+        self.pending.drain(..index);
+        self.consumed += index;
+        let ret = Utf32{data: data};
+        self.checker.push_all(&ret).map_err(|x| x.in_encoding(Utf1::NAME))?;
+        return Ok(ret);
+    }
+
+    fn finish(&mut self) -> Result<Utf32, UnicodeEncodingError> {
+        if !self.pending.is_empty() {
+            return Err(UnicodeEncodingError::new(MissingEncodedBytes).at_byte(self.consumed).with_code_units(&self.pending).in_encoding(Utf1::NAME));
         }
+        self.checker.finish()?;
+        return Ok(Utf32{data: Vec::new()});
+    }
+}
+
+/// Encodes UTF-1 data chunk by chunk.
+pub struct Utf1Encoder {
+    checker: SanityChecker,
+}
+
+impl Encoder for Utf1Encoder {
+    fn encode(&mut self, data: &Utf32) -> Result<Vec<u8>, UnicodeEncodingError> {
+        self.checker.push_all(data)?;
+        return Ok(Utf1::from_utf_32(data).data);
+    }
+
+    fn finish(&mut self) -> Result<Vec<u8>, UnicodeEncodingError> {
+        self.checker.finish()?;
+        return Ok(Vec::new());
     }
-    
 }
 
 /* ---------------------------- Helper functions ---------------------------- */
@@ -86,6 +153,7 @@ const     THIRD_CATEGORY_GLYPH_LIMIT: u32 = 0x4016;
 const    FOURTH_CATEGORY_GLYPH_LIMIT: u32 = 0x38E2E;
 
 const                   UTF_1_MODULO: u32 = 0xBE;
+const                 LAST_CODEPOINT: u32 = 0x10FFFF;
 
 const      THIRD_CATEGORY_GLYPH_TERM: u32 = 0xA1;
 const     FOURTH_CATEGORY_GLYPH_TERM: u32 = 0xF6;
@@ -160,18 +228,18 @@ fn uu(z: u8) -> u32 {
 /// beginning of the new glyph. The return value are the number of char used to
 /// encode the glyph.
 /// If the glyph does not makes sense, an error will be raised.
-fn utf_1_glyph_to_utf_32(utf1_data: &Vec<u8>, start: usize) -> Result<(u32, usize), UnicodeEncodingError> {
+fn utf_1_glyph_to_utf_32(utf1_data: &[u8], start: usize) -> Result<(u32, usize), UnicodeEncodingError> {
     let first_byte = utf1_data[start] as u8;
     if first_byte < FIRST_CATEGORY_SEQUENCE_LIMIT as u8 {
         return Ok((first_byte as u32, 1));
     } else if first_byte == FIRST_CATEGORY_SEQUENCE_LIMIT as u8 {
         if utf1_data.len() < start + 2 {
-            return Err(MissingEncodedBytes);
+            return Err(UnicodeEncodingError::new(MissingEncodedBytes).at_byte(start).with_code_units(&utf1_data[start..]));
         }
         return Ok((utf1_data[start+1] as u32, 2));
     } else if first_byte < SECOND_CATEGORY_SEQUENCE_LIMIT as u8 {
         if utf1_data.len() < start + 2 {
-            return Err(MissingEncodedBytes);
+            return Err(UnicodeEncodingError::new(MissingEncodedBytes).at_byte(start).with_code_units(&utf1_data[start..]));
         }
         let mut ret = ((first_byte as u32)- 1 - FIRST_CATEGORY_SEQUENCE_LIMIT) * UTF_1_MODULO;
         ret += uu(utf1_data[start+1]);
@@ -179,7 +247,7 @@ fn utf_1_glyph_to_utf_32(utf1_data: &Vec<u8>, start: usize) -> Result<(u32, usiz
         return Ok((ret, 2));
     } else if first_byte < THIRD_CATEGORY_SEQUENCE_LIMIT as u8 {
         if utf1_data.len() < start + 3 {
-            return Err(MissingEncodedBytes);
+            return Err(UnicodeEncodingError::new(MissingEncodedBytes).at_byte(start).with_code_units(&utf1_data[start..]));
         }
         let mut ret = ((first_byte as u32) - SECOND_CATEGORY_SEQUENCE_LIMIT) * UTF_1_MODULO * UTF_1_MODULO;
         ret += uu(utf1_data[start+1]) * UTF_1_MODULO;
@@ -188,15 +256,21 @@ fn utf_1_glyph_to_utf_32(utf1_data: &Vec<u8>, start: usize) -> Result<(u32, usiz
         return Ok((ret, 3));
     } else {
         if utf1_data.len() < start + 5 {
-            return Err(MissingEncodedBytes);
+            return Err(UnicodeEncodingError::new(MissingEncodedBytes).at_byte(start).with_code_units(&utf1_data[start..]));
         }
-        let mut ret = ((first_byte as u32) - THIRD_CATEGORY_SEQUENCE_LIMIT) * UTF_1_MODULO * UTF_1_MODULO * UTF_1_MODULO * UTF_1_MODULO;
-        ret += uu(utf1_data[start+1]) * UTF_1_MODULO * UTF_1_MODULO * UTF_1_MODULO;
-        ret += uu(utf1_data[start+2]) * UTF_1_MODULO * UTF_1_MODULO;
-        ret += uu(utf1_data[start+3]) * UTF_1_MODULO;
-        ret += uu(utf1_data[start+4]);
-        ret += FOURTH_CATEGORY_GLYPH_LIMIT;
-        return Ok((ret, 5));
+        // The five bytes sequences can encode values that do not fit in 32
+        // bits, they are computed on 64 bits to reject them.
+        let modulo = UTF_1_MODULO as u64;
+        let mut ret = ((first_byte as u64) - THIRD_CATEGORY_SEQUENCE_LIMIT as u64) * modulo * modulo * modulo * modulo;
+        ret += (uu(utf1_data[start+1]) as u64) * modulo * modulo * modulo;
+        ret += (uu(utf1_data[start+2]) as u64) * modulo * modulo;
+        ret += (uu(utf1_data[start+3]) as u64) * modulo;
+        ret += uu(utf1_data[start+4]) as u64;
+        ret += FOURTH_CATEGORY_GLYPH_LIMIT as u64;
+        if ret > LAST_CODEPOINT as u64 {
+            return Err(UnicodeEncodingError::new(CodepointAboveUnicodeRange).at_byte(start).with_code_units(&utf1_data[start..start+5]));
+        }
+        return Ok((ret as u32, 5));
     }
 }
 
@@ -282,3 +356,27 @@ fn test_utf_32_to_utf_1_and_back() {
     double_conv(0x10FFFF);
 }
 
+
+#[test]
+/// Test that glyphs split across chunks are decoded.
+fn test_utf_1_incremental() {
+    let mut bytes: Vec<u8> = Vec::new();
+    for glyph in [0x45, 0xA0, 0x0FFF, 0x00FDEF, 0x10FFFF] {
+        bytes.extend(utf_32_glyph_to_utf_1(glyph));
+    }
+    let mut decoder = Utf1::decoder(());
+    let decoded = crate::incremental::decode_byte_per_byte(decoder.as_mut(), &bytes).unwrap();
+    assert_eq!(decoded.data, vec![0x45, 0xA0, 0x0FFF, 0x00FDEF, 0x10FFFF]);
+
+    let mut decoder = Utf1::decoder(());
+    decoder.decode(&bytes[..bytes.len()-1]).unwrap();
+    assert!(decoder.finish().is_err());
+}
+
+#[test]
+/// Test that five bytes sequences above U+10FFFF are rejected.
+fn test_utf_1_above_unicode_range() {
+    let err = utf_1_glyph_to_utf_32(&[0xFF, 0xFE, 0xFF, 0xFE, 0xFF], 0).err().unwrap();
+    assert_eq!(err.kind, CodepointAboveUnicodeRange);
+    assert!(utf_1_glyph_to_utf_32(&[0xFC, 0x21, 0x39, 0x6E, 0x6D], 0).is_err());
+}
@@ -1,10 +1,13 @@
 /// The UTF-16 module manipulates UTF-16 data.
 
-use crate::unicode_encoding::UnicodeEncodingError::*;
+use crate::unicode_encoding::UnicodeEncodingErrorKind::*;
+use crate::unicode_encoding::UnicodeEncodingErrorKind;
 use crate::unicode_encoding::UnicodeEncodingError;
 use crate::unicode_encoding::UnicodeEncoding;
 use crate::endian_aware_byte_streamer;
+use crate::endianness::Endianness;
 use crate::utf_32::Utf32;
+use crate::incremental::{Decoder, Encoder, SanityChecker};
 
 /// A very basic wrapper for UTF-16 encoded data.
 pub struct Utf16 {
@@ -20,6 +23,9 @@ fn transvase(vec1: &mut Vec<u16>, vec2: &Vec<u16>) {
 }
 
 impl UnicodeEncoding for Utf16 {
+    const NAME: &'static str = "UTF-16";
+    type Options = Endianness;
+
     /// Convert UTF-32 data to UTF-16.
     fn from_utf_32(data_utf_32: &Utf32) -> Self {
         let mut data: Vec<u16> = Vec::new();
@@ -33,8 +39,9 @@ impl UnicodeEncoding for Utf16 {
         return Utf16{data: data};
     }
 
-    /// Convert UTF-16 data to UTF-32.
-    fn to_utf_32(&self) -> Utf32 {
+    /// Convert UTF-16 data to UTF-32. Any sequence of 16-bit numbers can be
+    /// read as UTF-16, so this never fails.
+    fn try_to_utf_32(&self) -> Result<Utf32, UnicodeEncodingError> {
         let mut index: usize = 0;
         let mut data: Vec<u32> = Vec::new();
         while index < self.data.len() {
@@ -42,22 +49,109 @@ impl UnicodeEncoding for Utf16 {
             data.push(glyph);
             index += len;
         }
-        return Utf32{data: data};
+        return Ok(Utf32{data: data});
+    }
+
+    /// Converts a stream of byte that _should_ be encoded in UTF-16 into the
+    /// `Utf16` type. If the endianness is told by a BOM, it is removed.
+    fn from_bytes_no_check(bytes: &[u8], endianness: Endianness) -> Result<Self, UnicodeEncodingError> {
+        let (big_endian, bom_len) = endianness.resolve::<u16>(bytes);
+        let data = endian_aware_byte_streamer::from_bytes::<u16>(&bytes[bom_len..], big_endian).map_err(|x| x.shifted(bom_len).in_encoding(Self::NAME))?;
+        return Ok(Utf16{data: data});
     }
 
-    /// Converts a stream of byte that _should_ be encoded in UTF-32 into the
-    /// `Utf32` type.
-    fn from_bytes_no_check(bytes: &[u8], big_endian: bool) -> Result<Self, UnicodeEncodingError> {
-        let ret = Utf16{data: endian_aware_byte_streamer::from_bytes::<u16>(bytes, big_endian)?};
+    /// Converts an instance of the `Utf16` type into a vector of bytes that is
+    /// the UTF-16 encoded content.
+    fn to_bytes(&self, endianness: Endianness) -> Vec<u8> {
+        return endian_aware_byte_streamer::to_bytes::<u16>(&self.data, endianness.is_big_endian());
+    }
+
+    fn decoder(endianness: Endianness) -> Box<dyn Decoder> {
+        return Box::new(Utf16Decoder{endianness: endianness, pending_bytes: Vec::new(), pending_surrogate: None, consumed: 0, checker: SanityChecker::new()});
+    }
+
+    fn encoder(endianness: Endianness) -> Box<dyn Encoder> {
+        return Box::new(Utf16Encoder{endianness: endianness, checker: SanityChecker::new()});
+    }
+}
+
+/* --------------------------- Incremental coding --------------------------- */
+
+/// Decodes UTF-16 data chunk by chunk. A high surrogate at the end of a chunk
+/// is kept until the next one to see if it is paired. If the endianness is
+/// told by a BOM, the first code unit is kept until it can be read.
+pub struct Utf16Decoder {
+    endianness: Endianness,
+    pending_bytes: Vec<u8>,
+    pending_surrogate: Option<u16>,
+    consumed: usize,
+    checker: SanityChecker,
+}
+
+impl Decoder for Utf16Decoder {
+    fn decode(&mut self, bytes: &[u8]) -> Result<Utf32, UnicodeEncodingError> {
+        self.pending_bytes.extend_from_slice(bytes);
+        if self.endianness == Endianness::FromBom {
+            if self.pending_bytes.len() < 2 {
+                return Ok(Utf32{data: Vec::new()});
+            }
+            let (big_endian, bom_len) = self.endianness.resolve::<u16>(&self.pending_bytes);
+            self.pending_bytes.drain(..bom_len);
+            self.consumed += bom_len;
+            self.endianness = if big_endian {
+                Endianness::Big
+            } else {
+                Endianness::Little
+            };
+        }
+        let usable = self.pending_bytes.len() - self.pending_bytes.len() % 2;
+        let mut units: Vec<u16> = Vec::new();
+        if let Some(surrogate) = self.pending_surrogate.take() {
+            units.push(surrogate);
+        }
+        units.extend(endian_aware_byte_streamer::from_bytes::<u16>(&self.pending_bytes[..usable], self.endianness.is_big_endian())?);
+        self.pending_bytes.drain(..usable);
+        self.consumed += usable;
+        if let Some(last) = units.last() {
+            if last & SURROGATE_MASK == HIGH_SURROGATE {
+                self.pending_surrogate = units.pop();
+            }
+        }
+        let ret = Utf16{data: units}.try_to_utf_32()?;
+        self.checker.push_all(&ret).map_err(|x| x.in_encoding(Utf16::NAME))?;
         return Ok(ret);
     }
 
-    /// Converts an instance of the `Utf32` type into a vector of bytes that is
-    /// the UTF-32 encoded content.
-    fn to_bytes(&self, big_endian: bool) -> Vec<u8> {
-        return endian_aware_byte_streamer::to_bytes::<u16>(&self.data, big_endian);
+    fn finish(&mut self) -> Result<Utf32, UnicodeEncodingError> {
+        if !self.pending_bytes.is_empty() {
+            return Err(UnicodeEncodingError::new(InvalidStreamSize).at_byte(self.consumed).with_code_units(&self.pending_bytes).in_encoding(Utf16::NAME));
+        }
+        let ret = match self.pending_surrogate.take() {
+            Some(surrogate) => Utf32{data: vec![surrogate as u32]},
+            None => Utf32{data: Vec::new()},
+        };
+        self.checker.push_all(&ret).map_err(|x| x.in_encoding(Utf16::NAME))?;
+        self.checker.finish()?;
+        return Ok(ret);
     }
+}
 
+/// Encodes UTF-16 data chunk by chunk.
+pub struct Utf16Encoder {
+    endianness: Endianness,
+    checker: SanityChecker,
+}
+
+impl Encoder for Utf16Encoder {
+    fn encode(&mut self, data: &Utf32) -> Result<Vec<u8>, UnicodeEncodingError> {
+        self.checker.push_all(data)?;
+        return Ok(Utf16::from_utf_32(data).to_bytes(self.endianness));
+    }
+
+    fn finish(&mut self) -> Result<Vec<u8>, UnicodeEncodingError> {
+        self.checker.finish()?;
+        return Ok(Vec::new());
+    }
 }
 
 /* ---------------------------- Helper functions ---------------------------- */
@@ -73,9 +167,10 @@ const   SUPPLEMENTARY_PLANE_END: u32 = 0x0010_FFFF;
 const  HIGH_SURROGATE: u16 = 0xD800;
 const   LOW_SURROGATE: u16 = 0xDC00;
 const  SURROGATE_MASK: u16 = 0b11111100_00000000;
+const VALID_CODEPOINT_BITS: u32 = 0b00011111_11111111_11111111;
 
 /// Turns an UTF-32 glyph into a one or two 16 bit numbers as an UTF-16 value.
-fn utf_32_glyph_to_utf_16(glyph: u32) -> Result<Vec<u16>, UnicodeEncodingError>  {
+fn utf_32_glyph_to_utf_16(glyph: u32) -> Result<Vec<u16>, UnicodeEncodingErrorKind>  {
     let mut ret: Vec<u16> = Vec::new();
     if     (glyph >= BASIC_PLANE_1_START && glyph <= BASIC_PLANE_1_END)
         || (glyph >= BASIC_PLANE_2_START && glyph <= BASIC_PLANE_2_END) {
@@ -86,6 +181,8 @@ fn utf_32_glyph_to_utf_16(glyph: u32) -> Result<Vec<u16>, UnicodeEncodingError>
         ret.push(low_surrogate);
     } else if glyph > BASIC_PLANE_1_END && glyph < BASIC_PLANE_2_START {
         return Err(UnpairedSurrogateNotification);
+    } else if glyph <= VALID_CODEPOINT_BITS {
+        return Err(CodepointAboveUnicodeRange);
     } else {
         return Err(InvalidCodepointTooManyBits);
     }
@@ -93,25 +190,44 @@ fn utf_32_glyph_to_utf_16(glyph: u32) -> Result<Vec<u16>, UnicodeEncodingError>
 }
 
 /// Tells if two Unicode code-points can be next two another or if there could
-/// be an ambiguity when two unpaired surrogate are next two another. If the
-/// input text is proper Unicode, this should never happen.
+/// be an ambiguity when two unpaired surrogate are next two another. This is
+/// the case of a high surrogate followed by a low surrogate, as they would be
+/// read back as a pair. If the input text is proper Unicode, this should
+/// never happen.
 /// Doing so ensure that the text can be safely used as UTF-16.
-pub fn compatible_codepoints(glyph1: u32, glyph2: u32) -> UnicodeEncodingError {
+/// The position of the error, if any, is the index of the faulty code-point
+/// among the two.
+pub fn compatible_codepoints(glyph1: u32, glyph2: u32) -> Result<(), UnicodeEncodingError> {
     match utf_32_glyph_to_utf_16(glyph1) {
-        Err(InvalidCodepointTooManyBits) => InvalidCodepointTooManyBits,
         Err(UnpairedSurrogateNotification) => match utf_32_glyph_to_utf_16(glyph2) {
-            Err(UnpairedSurrogateNotification) => AmbiguousUnpairedSurrogates,
-            Err(x) => x,
-            Ok(_) => NoError,
+            Err(UnpairedSurrogateNotification) if is_high_surrogate(glyph1) && is_low_surrogate(glyph2) => Err(UnicodeEncodingError::new(AmbiguousUnpairedSurrogates).at_codepoint(0).with_code_units(&[glyph1, glyph2])),
+            Err(UnpairedSurrogateNotification) => Ok(()),
+            Err(x) => Err(UnicodeEncodingError::new(x).at_codepoint(1).with_code_units(&[glyph2])),
+            Ok(_) => Ok(()),
         },
-        Ok(_) => NoError,
-        Err(x) => x,
+        Ok(_) => Ok(()),
+        Err(x) => Err(UnicodeEncodingError::new(x).at_codepoint(0).with_code_units(&[glyph1])),
     }
 }
 
+/// Tells if an Unicode code-point is in the range reserved for surrogates.
+pub(crate) fn is_surrogate(glyph: u32) -> bool {
+    return glyph > BASIC_PLANE_1_END && glyph < BASIC_PLANE_2_START;
+}
+
+/// Tells if an Unicode code-point is a high surrogate, the first of a pair.
+pub(crate) fn is_high_surrogate(glyph: u32) -> bool {
+    return is_surrogate(glyph) && glyph as u16 & SURROGATE_MASK == HIGH_SURROGATE;
+}
+
+/// Tells if an Unicode code-point is a low surrogate, the second of a pair.
+pub(crate) fn is_low_surrogate(glyph: u32) -> bool {
+    return is_surrogate(glyph) && glyph as u16 & SURROGATE_MASK == LOW_SURROGATE;
+}
+
 /// Turns an Unicode code-point from the supplementary plane into a high
 /// surrogate and a low surrogate.
-fn glyph_into_surrogates(glyph: u32) -> (u16, u16) {
+pub(crate) fn glyph_into_surrogates(glyph: u32) -> (u16, u16) {
     let based_glyph = glyph - SUPPLEMENTARY_PLANE_START;
     let glyph_10_msb = (based_glyph >> 10) as u16;
     let glyph_10_lsb = (based_glyph - ((glyph_10_msb as u32) << 10)) as u16;
@@ -121,7 +237,7 @@ fn glyph_into_surrogates(glyph: u32) -> (u16, u16) {
 }
 
 /// Turns high and low surrogates into a UTF-32 glyph.
-fn surrogates_to_glyph(high_surrogate: u16, low_surrogate: u16) -> u32 {
+pub(crate) fn surrogates_to_glyph(high_surrogate: u16, low_surrogate: u16) -> u32 {
     let stripped_hs = high_surrogate & !SURROGATE_MASK; 
     let stripped_ls = low_surrogate & !SURROGATE_MASK; 
     let based_glyph = ((stripped_hs as u32) << 10) | (stripped_ls as u32);
@@ -181,3 +297,40 @@ fn test_invalid_utf16_glyphs_2() {
     utf_32_glyph_to_utf_16(glyph).unwrap();
 }
 
+
+#[test]
+/// Test that surrogate pairs split across chunks are decoded.
+fn test_utf_16_incremental() {
+    let bytes: [u8; 6] = [0x41, 0x00, 0x3D, 0xD8, 0x0E, 0xDE];
+    let mut decoder = Utf16::decoder(Endianness::Little);
+    let decoded = crate::incremental::decode_byte_per_byte(decoder.as_mut(), &bytes).unwrap();
+    assert_eq!(decoded.data, vec![0x41, 0x1F60E]);
+
+    let mut decoder = Utf16::decoder(Endianness::Little);
+    let decoded = decoder.decode(&bytes[0..4]).unwrap();
+    assert_eq!(decoded.data, vec![0x41]);
+    assert_eq!(decoder.finish().unwrap().data, vec![0xD83D]);
+
+    let mut decoder = Utf16::decoder(Endianness::Little);
+    decoder.decode(&bytes[0..3]).unwrap();
+    assert!(decoder.finish().is_err());
+}
+
+#[test]
+/// Test that the endianness is taken from the BOM.
+fn test_utf_16_bom() {
+    let bytes: [u8; 4] = [0xFF, 0xFE, 0x41, 0x00];
+    let utf16 = Utf16::from_bytes(&bytes, Endianness::FromBom).unwrap();
+    assert_eq!(utf16.data, vec![0x41]);
+    let utf16 = Utf16::from_bytes(&bytes, Endianness::Little).unwrap();
+    assert_eq!(utf16.data, vec![0xFEFF, 0x41]);
+    let utf16 = Utf16::from_bytes(&bytes[2..], Endianness::FromBom).unwrap();
+    assert_eq!(utf16.data, vec![0x4100]);
+    assert_eq!(Utf16::strip_bom(&bytes, Endianness::Little), &bytes[2..]);
+    assert_eq!(Utf16::strip_bom(&bytes, Endianness::Big), &bytes[..]);
+    assert_eq!(Utf16{data: vec![0x41]}.to_bytes_with_bom(Endianness::FromBom), vec![0xFE, 0xFF, 0x00, 0x41]);
+
+    let mut decoder = Utf16::decoder(Endianness::FromBom);
+    let decoded = crate::incremental::decode_byte_per_byte(decoder.as_mut(), &bytes).unwrap();
+    assert_eq!(decoded.data, vec![0x41]);
+}
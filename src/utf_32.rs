@@ -2,11 +2,13 @@
 /// only convert from and to UTF-32. Thus, this module only need to take care
 /// of writing and reading encoded values to and from a string of byte.
 
-use crate::unicode_encoding::UnicodeEncodingError::*;
+use crate::unicode_encoding::UnicodeEncodingErrorKind::*;
 use crate::unicode_encoding::UnicodeEncodingError;
 use crate::unicode_encoding::UnicodeEncoding;
 use crate::endian_aware_byte_streamer;
+use crate::endianness::Endianness;
 use crate::utf_16;
+use crate::incremental::{Decoder, Encoder, SanityChecker};
 
 /// A very basic wrapper for UTF-32 encoded data.
 pub struct Utf32 {
@@ -32,13 +34,18 @@ impl Utf32 {
     /// Check that all the Unicode code-points are valid or at least not too
     /// absurd. This should not be used except when implementing the generic
     /// check_sanity for all Unicode encoding.
-    pub fn check_sanity_utf32(&self) -> UnicodeEncodingError {
+    pub fn check_sanity_utf32(&self) -> Result<(), UnicodeEncodingError> {
         const VALID_CODEPOINT: u32 = 0b00011111_11111111_11111111;
+        const LAST_CODEPOINT: u32 = 0x10FFFF;
         for i in 0..self.data.len() {
             let glyph = self.data[i];
             // Ensure that there is not too much bits in the code-point.
             if glyph & !VALID_CODEPOINT != 0 {
-                return InvalidCodepointTooManyBits;
+                return Err(UnicodeEncodingError::new(InvalidCodepointTooManyBits).at_codepoint(i).with_code_units(&[glyph]));
+            }
+            // Ensure that the code-point is in the Unicode range.
+            if glyph > LAST_CODEPOINT {
+                return Err(UnicodeEncodingError::new(CodepointAboveUnicodeRange).at_codepoint(i).with_code_units(&[glyph]));
             }
             // Ensure that there is no ambiguous unpaired surrogates.
             let other_glyph = if i + 1 == self.data.len() {
@@ -46,16 +53,16 @@ impl Utf32 {
             } else {
                 self.data[i+1]
             };
-            match utf_16::compatible_codepoints(glyph, other_glyph) {
-                NoError => {},
-                x => return x,
-            }
+            utf_16::compatible_codepoints(glyph, other_glyph).map_err(|x| x.shifted(i))?;
         }
-        return NoError;
+        return Ok(());
     }
 }
 
 impl UnicodeEncoding for Utf32 {
+    const NAME: &'static str = "UTF-32";
+    type Options = Endianness;
+
     /// A quite dummy function to comply with the need of the UnicodeEncoding
     /// trait.
     fn from_utf_32(data_utf_32: &Utf32) -> Utf32 {
@@ -64,28 +71,125 @@ impl UnicodeEncoding for Utf32 {
 
     /// A quite dummy function to comply with the need of the UnicodeEncoding
     /// trait.
-    fn to_utf_32(&self) -> Utf32 {
-        return self.clone();
+    fn try_to_utf_32(&self) -> Result<Utf32, UnicodeEncodingError> {
+        return Ok(self.clone());
     }
 
     /// Converts a stream of byte that _should_ be encoded in UTF-32 into the
-    /// `Utf32` type.
-    fn from_bytes_no_check(bytes: &[u8], big_endian: bool) -> Result<Self, UnicodeEncodingError> {
-        let ret = Utf32{data: endian_aware_byte_streamer::from_bytes::<u32>(bytes, big_endian)?};
-        return Ok(ret);
+    /// `Utf32` type. If the endianness is told by a BOM, it is removed.
+    fn from_bytes_no_check(bytes: &[u8], endianness: Endianness) -> Result<Self, UnicodeEncodingError> {
+        let (big_endian, bom_len) = endianness.resolve::<u32>(bytes);
+        let data = endian_aware_byte_streamer::from_bytes::<u32>(&bytes[bom_len..], big_endian).map_err(|x| x.shifted(bom_len).in_encoding(Self::NAME))?;
+        return Ok(Utf32{data: data});
     }
 
     /// Converts an instance of the `Utf32` type into a vector of bytes that is
     /// the UTF-32 encoded content.
-    fn to_bytes(&self, big_endian: bool) -> Vec<u8> {
-        return endian_aware_byte_streamer::to_bytes::<u32>(&self.data, big_endian);
+    fn to_bytes(&self, endianness: Endianness) -> Vec<u8> {
+        return endian_aware_byte_streamer::to_bytes::<u32>(&self.data, endianness.is_big_endian());
+    }
+
+    fn decoder(endianness: Endianness) -> Box<dyn Decoder> {
+        return Box::new(Utf32Decoder{endianness: endianness, pending: Vec::new(), consumed: 0, checker: SanityChecker::new()});
+    }
+
+    fn encoder(endianness: Endianness) -> Box<dyn Encoder> {
+        return Box::new(Utf32Encoder{endianness: endianness, checker: SanityChecker::new()});
+    }
+}
+
+/* --------------------------- Incremental coding --------------------------- */
+
+/// Decodes UTF-32 data chunk by chunk. If the endianness is told by a BOM,
+/// the first code unit is kept until it can be read.
+pub struct Utf32Decoder {
+    endianness: Endianness,
+    pending: Vec<u8>,
+    consumed: usize,
+    checker: SanityChecker,
+}
+
+impl Decoder for Utf32Decoder {
+    fn decode(&mut self, bytes: &[u8]) -> Result<Utf32, UnicodeEncodingError> {
+        self.pending.extend_from_slice(bytes);
+        if self.endianness == Endianness::FromBom {
+            if self.pending.len() < 4 {
+                return Ok(Utf32{data: Vec::new()});
+            }
+            let (big_endian, bom_len) = self.endianness.resolve::<u32>(&self.pending);
+            self.pending.drain(..bom_len);
+            self.consumed += bom_len;
+            self.endianness = if big_endian {
+                Endianness::Big
+            } else {
+                Endianness::Little
+            };
+        }
+        let usable = self.pending.len() - self.pending.len() % 4;
+        let ret = Utf32::from_bytes_no_check(&self.pending[..usable], self.endianness).map_err(|x| x.shifted(self.consumed))?;
+        self.pending.drain(..usable);
+        self.consumed += usable;
+        self.checker.push_all(&ret).map_err(|x| x.in_encoding(Utf32::NAME))?;
+        return Ok(ret);
+    }
+
+    fn finish(&mut self) -> Result<Utf32, UnicodeEncodingError> {
+        if !self.pending.is_empty() {
+            return Err(UnicodeEncodingError::new(InvalidStreamSize).at_byte(self.consumed).with_code_units(&self.pending).in_encoding(Utf32::NAME));
+        }
+        self.checker.finish()?;
+        return Ok(Utf32{data: Vec::new()});
+    }
+}
+
+/// Encodes UTF-32 data chunk by chunk.
+pub struct Utf32Encoder {
+    endianness: Endianness,
+    checker: SanityChecker,
+}
+
+impl Encoder for Utf32Encoder {
+    fn encode(&mut self, data: &Utf32) -> Result<Vec<u8>, UnicodeEncodingError> {
+        self.checker.push_all(data)?;
+        return Ok(data.to_bytes(self.endianness));
+    }
+
+    fn finish(&mut self) -> Result<Vec<u8>, UnicodeEncodingError> {
+        self.checker.finish()?;
+        return Ok(Vec::new());
     }
 }
 
 #[test]
 fn test_data_content() {
     let data: [u8; 4] = [0, 1, 2, 3];
-    let utf_32_glyph = Utf32::from_bytes(data.as_slice(), true).unwrap();
+    let utf_32_glyph = Utf32::from_bytes(data.as_slice(), Endianness::Big).unwrap();
     assert_eq!(utf_32_glyph.data[0], 0x00010203);
 }
 
+
+#[test]
+fn test_utf_32_incremental() {
+    let bytes: [u8; 8] = [0, 0, 0, 0x41, 0, 1, 0xF6, 0x0E];
+    let mut decoder = Utf32::decoder(Endianness::Big);
+    let decoded = crate::incremental::decode_byte_per_byte(decoder.as_mut(), &bytes).unwrap();
+    assert_eq!(decoded.data, vec![0x41, 0x1F60E]);
+
+    let mut decoder = Utf32::decoder(Endianness::Big);
+    decoder.decode(&bytes[0..6]).unwrap();
+    assert!(decoder.finish().is_err());
+}
+
+#[test]
+fn test_check_sanity_position() {
+    use crate::unicode_encoding::ErrorPosition;
+    let err = Utf32{data: vec![0x41, 0xD800, 0xDC00]}.check_sanity_utf32().err().unwrap();
+    assert_eq!(err.kind, AmbiguousUnpairedSurrogates);
+    assert_eq!(err.position, Some(ErrorPosition::Codepoint(1)));
+    let err = Utf32{data: vec![0x41, 0x42, 0x200000]}.check_sanity_utf32().err().unwrap();
+    assert_eq!(err.kind, InvalidCodepointTooManyBits);
+    assert_eq!(err.position, Some(ErrorPosition::Codepoint(2)));
+    let err = Utf32{data: vec![0x41, 0x110000]}.check_sanity_utf32().err().unwrap();
+    assert_eq!(err.kind, CodepointAboveUnicodeRange);
+    assert_eq!(err.position, Some(ErrorPosition::Codepoint(1)));
+}
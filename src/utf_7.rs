@@ -0,0 +1,537 @@
+/// The UTF-7 module handles the __7-bit Unicode Transformation Format__
+/// described in the RFC 2152, used by old mail software.
+
+use crate::unicode_encoding::UnicodeEncodingErrorKind::*;
+use crate::unicode_encoding::UnicodeEncodingError;
+use crate::unicode_encoding::UnicodeEncoding;
+use crate::utf_32::Utf32;
+use crate::utf_16;
+use crate::imap_utf_7::ImapUtf7;
+use crate::incremental::{Decoder, Encoder, SanityChecker};
+use crate::lossy::{self, DecodingPolicy};
+
+/// A wrapper for UTF-7 encoded bytes.
+pub struct Utf7 {
+    pub data: Vec<u8>
+}
+
+/// Tells which ASCII characters are written directly when encoding UTF-7,
+/// the other ones are written in modified base64. Both are accepted when
+/// decoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Utf7DirectCharacters {
+    /// Only the characters of the Set D of the RFC 2152, the letters, the
+    /// digits and `'(),-./:?`, and the white-spaces are written directly.
+    /// This is safe to use in mail headers.
+    SetD,
+
+    /// The characters of the Set O, such as `!`, `#` or `@`, are written
+    /// directly as well.
+    SetO,
+}
+
+impl UnicodeEncoding for Utf7 {
+    const NAME: &'static str = "UTF-7";
+    type Options = Utf7DirectCharacters;
+
+    /// Convert UTF-32 data to UTF-7, only the characters of the Set D are
+    /// written directly.
+    fn from_utf_32(data_utf_32: &Utf32) -> Self {
+        return Utf7{data: encode_utf_7(&data_utf_32.data, Utf7Variant::Standard, Utf7DirectCharacters::SetD)};
+    }
+
+    /// Convert UTF-7 data to UTF-32.
+    fn try_to_utf_32(&self) -> Result<Utf32, UnicodeEncodingError> {
+        return decode_utf_7(&self.data, Utf7Variant::Standard);
+    }
+
+    /// Convert the instance of `Utf7` to a vector of bytes. As the same text
+    /// can be written in many ways in UTF-7, the text is encoded again to
+    /// only write directly the characters asked for.
+    fn to_bytes(&self, direct: Utf7DirectCharacters) -> Vec<u8> {
+        match self.try_to_utf_32() {
+            Ok(x) => encode_utf_7(&x.data, Utf7Variant::Standard, direct),
+            Err(_) => self.data.clone(),
+        }
+    }
+
+    /// Consider a stream of UTF-7 encoded bytes and turn it into a `Utf7`
+    /// type. The bytes are decoded to ensure they are valid UTF-7.
+    fn from_bytes_no_check(bytes: &[u8], _direct: Utf7DirectCharacters) -> Result<Self, UnicodeEncodingError> {
+        let ret = Utf7{data: bytes.to_vec()};
+        ret.try_to_utf_32()?;
+        return Ok(ret);
+    }
+
+    /// Decodes UTF-7 byte by byte, handling the invalid bytes and base64
+    /// sequences with the policy. An invalid byte in a base64 sequence does
+    /// not end it.
+    fn from_bytes_lossy(bytes: &[u8], _direct: Utf7DirectCharacters, policy: DecodingPolicy) -> Result<(Self, usize), UnicodeEncodingError> {
+        let (data, count) = decode_utf_7_lossy(bytes, Utf7Variant::Standard, policy)?;
+        return Ok((Self::from_utf_32(&data), count));
+    }
+
+    fn decoder(_direct: Utf7DirectCharacters) -> Box<dyn Decoder> {
+        return Box::new(Utf7Decoder::new(Utf7Variant::Standard));
+    }
+
+    fn encoder(direct: Utf7DirectCharacters) -> Box<dyn Encoder> {
+        return Box::new(Utf7Encoder::new(Utf7Variant::Standard, direct));
+    }
+}
+
+/* --------------------------- Incremental coding --------------------------- */
+
+/// The flavors of UTF-7 sharing the same decoder and encoder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Utf7Variant {
+    /// UTF-7 as described in the RFC 2152.
+    Standard,
+
+    /// The modified UTF-7 used for IMAP mailbox names, as described in the
+    /// section 5.1.3 of the RFC 3501.
+    Imap,
+}
+
+impl Utf7Variant {
+    /// The name of the encoding, used in error messages.
+    fn name(&self) -> &'static str {
+        match self {
+            Utf7Variant::Standard => Utf7::NAME,
+            Utf7Variant::Imap => ImapUtf7::NAME,
+        }
+    }
+
+    /// The character starting a base64 sequence.
+    fn shift_start(&self) -> u8 {
+        match self {
+            Utf7Variant::Standard => b'+',
+            Utf7Variant::Imap => b'&',
+        }
+    }
+
+    /// The base64 alphabet, IMAP uses `,` instead of `/`.
+    fn alphabet(&self) -> &'static [u8; 64] {
+        match self {
+            Utf7Variant::Standard => b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/",
+            Utf7Variant::Imap => b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+,",
+        }
+    }
+
+    /// Gives the 6-bit value of a character of the base64 alphabet.
+    fn base64_value(&self, byte: u8) -> Option<u32> {
+        return self.alphabet().iter().position(|x| *x == byte).map(|x| x as u32);
+    }
+
+    /// Tells if a byte can be found in the encoded data. IMAP only allows the
+    /// printable ASCII characters.
+    fn accepts_byte(&self, byte: u8) -> bool {
+        match self {
+            Utf7Variant::Standard => byte <= LAST_ASCII,
+            Utf7Variant::Imap => (FIRST_PRINTABLE..LAST_ASCII).contains(&byte),
+        }
+    }
+
+    /// Tells if a glyph can be written directly. IMAP writes all the
+    /// printable ASCII characters but the shift directly.
+    fn is_direct(&self, glyph: u32, direct: Utf7DirectCharacters) -> bool {
+        if glyph > LAST_ASCII as u32 {
+            return false;
+        }
+        let byte = glyph as u8;
+        match self {
+            Utf7Variant::Standard => SET_D.contains(&byte) || (direct == Utf7DirectCharacters::SetO && SET_O.contains(&byte)),
+            Utf7Variant::Imap => self.accepts_byte(byte) && byte != self.shift_start(),
+        }
+    }
+
+    /// Tells if a base64 sequence can be ended by any character that is not
+    /// in the base64 alphabet. IMAP always needs a `-`.
+    fn has_implicit_end(&self) -> bool {
+        return *self == Utf7Variant::Standard;
+    }
+}
+
+/// Decodes UTF-7 data chunk by chunk. The state of the base64 sequence being
+/// read is kept between the chunks.
+pub struct Utf7Decoder {
+    variant: Utf7Variant,
+    /// The bytes of the base64 sequence being read, starting with the `+`.
+    /// `None` when the characters are written directly.
+    sequence: Option<Vec<u8>>,
+    /// Offset of the `+` starting the base64 sequence.
+    sequence_start: usize,
+    /// The bits read in the base64 sequence that do not make a whole UTF-16
+    /// code unit yet.
+    bits: u32,
+    bit_count: u32,
+    /// A high surrogate waiting for the low surrogate it might be paired with.
+    high_surrogate: Option<u16>,
+    consumed: usize,
+    checker: SanityChecker,
+}
+
+impl Utf7Decoder {
+    pub(crate) fn new(variant: Utf7Variant) -> Self {
+        return Utf7Decoder{variant: variant, sequence: None, sequence_start: 0, bits: 0, bit_count: 0, high_surrogate: None, consumed: 0, checker: SanityChecker::new()};
+    }
+
+    /// Reads a byte outside of a base64 sequence.
+    fn read_direct(&mut self, byte: u8, data: &mut Vec<u32>) {
+        if byte == self.variant.shift_start() {
+            self.sequence = Some(vec![byte]);
+            self.sequence_start = self.consumed;
+            self.bits = 0;
+            self.bit_count = 0;
+        } else {
+            data.push(byte as u32);
+        }
+    }
+
+    /// Adds an UTF-16 code unit read in a base64 sequence to the decoded
+    /// data, pairing the surrogates.
+    fn push_code_unit(&mut self, code_unit: u16, data: &mut Vec<u32>) {
+        if let Some(high_surrogate) = self.high_surrogate.take() {
+            if utf_16::is_low_surrogate(code_unit as u32) {
+                data.push(utf_16::surrogates_to_glyph(high_surrogate, code_unit));
+                return;
+            }
+            data.push(high_surrogate as u32);
+        }
+        if utf_16::is_high_surrogate(code_unit as u32) {
+            self.high_surrogate = Some(code_unit);
+        } else {
+            data.push(code_unit as u32);
+        }
+    }
+
+    /// Ends the base64 sequence being read. `terminator` is the `-` ending
+    /// it explicitly, if any. The bits left must be the zero padding of the
+    /// last code unit.
+    fn close_sequence(&mut self, terminator: Option<u8>, data: &mut Vec<u32>) -> Result<(), UnicodeEncodingError> {
+        let mut sequence = self.sequence.take().unwrap_or_default();
+        let high_surrogate = self.high_surrogate.take();
+        if let Some(x) = terminator {
+            sequence.push(x);
+        }
+        if sequence.len() == 1 {
+            return Err(UnicodeEncodingError::new(MissingEncodedBytes).at_byte(self.sequence_start).with_code_units(&sequence).in_encoding(self.variant.name()));
+        }
+        if sequence == [self.variant.shift_start(), SHIFT_END] {
+            data.push(self.variant.shift_start() as u32);
+            return Ok(());
+        }
+        if self.bit_count >= 6 || self.bits != 0 {
+            return Err(UnicodeEncodingError::new(InvalidUtf7Padding).at_byte(self.sequence_start).with_code_units(&sequence).in_encoding(self.variant.name()));
+        }
+        if let Some(high_surrogate) = high_surrogate {
+            data.push(high_surrogate as u32);
+        }
+        return Ok(());
+    }
+
+    /// Reads a single byte. On error, the decoder is left ready to read the
+    /// next byte: an invalid byte is skipped without ending the base64
+    /// sequence, and an invalid base64 sequence is dropped.
+    fn read_byte(&mut self, byte: u8, data: &mut Vec<u32>) -> Result<(), UnicodeEncodingError> {
+        if !self.variant.accepts_byte(byte) {
+            return Err(UnicodeEncodingError::new(InvalidUtf7Byte).at_byte(self.consumed).with_code_units(&[byte]).in_encoding(self.variant.name()));
+        }
+        match (&mut self.sequence, self.variant.base64_value(byte)) {
+            (None, _) => self.read_direct(byte, data),
+            (Some(sequence), Some(value)) => {
+                sequence.push(byte);
+                self.bits = (self.bits << 6) | value;
+                self.bit_count += 6;
+                if self.bit_count >= 16 {
+                    self.bit_count -= 16;
+                    let code_unit = (self.bits >> self.bit_count) as u16;
+                    self.bits &= (1 << self.bit_count) - 1;
+                    self.push_code_unit(code_unit, data);
+                }
+            },
+            (Some(_), None) if byte == SHIFT_END => self.close_sequence(Some(byte), data)?,
+            (Some(_), None) if !self.variant.has_implicit_end() => {
+                return Err(UnicodeEncodingError::new(InvalidUtf7Byte).at_byte(self.consumed).with_code_units(&[byte]).in_encoding(self.variant.name()));
+            },
+            (Some(_), None) => {
+                let closed = self.close_sequence(None, data);
+                self.read_direct(byte, data);
+                closed?;
+            },
+        }
+        return Ok(());
+    }
+
+    /// Ends the base64 sequence left open at the end of the data, if any.
+    fn finish_sequence(&mut self, data: &mut Vec<u32>) -> Result<(), UnicodeEncodingError> {
+        if self.sequence.is_some() {
+            if !self.variant.has_implicit_end() {
+                let sequence = self.sequence.take().unwrap_or_default();
+                self.high_surrogate = None;
+                return Err(UnicodeEncodingError::new(MissingEncodedBytes).at_byte(self.sequence_start).with_code_units(&sequence).in_encoding(self.variant.name()));
+            }
+            self.close_sequence(None, data)?;
+        }
+        return Ok(());
+    }
+}
+
+impl Decoder for Utf7Decoder {
+    fn decode(&mut self, bytes: &[u8]) -> Result<Utf32, UnicodeEncodingError> {
+        let mut data: Vec<u32> = Vec::new();
+        for byte in bytes {
+            self.read_byte(*byte, &mut data)?;
+            self.consumed += 1;
+        }
+        let ret = Utf32{data: data};
+        self.checker.push_all(&ret).map_err(|x| x.in_encoding(self.variant.name()))?;
+        return Ok(ret);
+    }
+
+    fn finish(&mut self) -> Result<Utf32, UnicodeEncodingError> {
+        let mut data: Vec<u32> = Vec::new();
+        self.finish_sequence(&mut data)?;
+        let ret = Utf32{data: data};
+        self.checker.push_all(&ret).map_err(|x| x.in_encoding(self.variant.name()))?;
+        self.checker.finish()?;
+        return Ok(ret);
+    }
+}
+
+/// Encodes UTF-7 data chunk by chunk. A base64 sequence left open at the end
+/// of a chunk is continued by the next one.
+pub struct Utf7Encoder {
+    variant: Utf7Variant,
+    direct: Utf7DirectCharacters,
+    /// Tells if a base64 sequence is being written.
+    shifted: bool,
+    /// The bits of the code units not written yet as base64.
+    bits: u32,
+    bit_count: u32,
+    checker: SanityChecker,
+}
+
+impl Utf7Encoder {
+    pub(crate) fn new(variant: Utf7Variant, direct: Utf7DirectCharacters) -> Self {
+        return Utf7Encoder{variant: variant, direct: direct, shifted: false, bits: 0, bit_count: 0, checker: SanityChecker::new()};
+    }
+
+    /// Writes the remaining bits, padded with zeros, and ends the base64
+    /// sequence. Outside of IMAP, the `-` ending it is only needed when the
+    /// next character could be mistaken for a part of the sequence.
+    fn close_sequence(&mut self, next: Option<u32>, bytes: &mut Vec<u8>) {
+        if self.bit_count > 0 {
+            bytes.push(self.variant.alphabet()[((self.bits << (6 - self.bit_count)) & 0x3F) as usize]);
+        }
+        self.bits = 0;
+        self.bit_count = 0;
+        self.shifted = false;
+        let needs_terminator = match next {
+            Some(_) if !self.variant.has_implicit_end() => true,
+            Some(x) => x == SHIFT_END as u32 || (x <= LAST_ASCII as u32 && self.variant.base64_value(x as u8).is_some()),
+            None => true,
+        };
+        if needs_terminator {
+            bytes.push(SHIFT_END);
+        }
+    }
+
+    /// Encodes a single glyph. The shift character itself is written as the
+    /// shift followed by `-`, except in a base64 sequence of the standard
+    /// UTF-7 where it is cheaper to keep it in base64.
+    fn push_glyph(&mut self, glyph: u32, bytes: &mut Vec<u8>) {
+        let shift_start = self.variant.shift_start();
+        if self.variant.is_direct(glyph, self.direct) {
+            if self.shifted {
+                self.close_sequence(Some(glyph), bytes);
+            }
+            bytes.push(glyph as u8);
+        } else if glyph == shift_start as u32 && (!self.shifted || !self.variant.has_implicit_end()) {
+            if self.shifted {
+                self.close_sequence(Some(glyph), bytes);
+            }
+            bytes.extend_from_slice(&[shift_start, SHIFT_END]);
+        } else {
+            if !self.shifted {
+                bytes.push(shift_start);
+                self.shifted = true;
+            }
+            let code_units = if glyph > BASIC_PLANE_END {
+                let (high_surrogate, low_surrogate) = utf_16::glyph_into_surrogates(glyph);
+                vec![high_surrogate, low_surrogate]
+            } else {
+                vec![glyph as u16]
+            };
+            for code_unit in code_units {
+                self.bits = (self.bits << 16) | code_unit as u32;
+                self.bit_count += 16;
+                while self.bit_count >= 6 {
+                    self.bit_count -= 6;
+                    bytes.push(self.variant.alphabet()[((self.bits >> self.bit_count) & 0x3F) as usize]);
+                }
+                self.bits &= (1 << self.bit_count) - 1;
+            }
+        }
+    }
+}
+
+impl Encoder for Utf7Encoder {
+    fn encode(&mut self, data: &Utf32) -> Result<Vec<u8>, UnicodeEncodingError> {
+        self.checker.push_all(data)?;
+        let mut bytes: Vec<u8> = Vec::new();
+        for glyph in &data.data {
+            self.push_glyph(*glyph, &mut bytes);
+        }
+        return Ok(bytes);
+    }
+
+    fn finish(&mut self) -> Result<Vec<u8>, UnicodeEncodingError> {
+        self.checker.finish()?;
+        let mut bytes: Vec<u8> = Vec::new();
+        if self.shifted {
+            self.close_sequence(None, &mut bytes);
+        }
+        return Ok(bytes);
+    }
+}
+
+/* ---------------------------- Helper functions ---------------------------- */
+
+const SHIFT_END: u8 = b'-';
+const FIRST_PRINTABLE: u8 = 0x20;
+const LAST_ASCII: u8 = 0x7F;
+const BASIC_PLANE_END: u32 = 0xFFFF;
+
+/// The characters of the Set D of the RFC 2152, written directly.
+const SET_D: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789'(),-./:? \t\r\n";
+
+/// The characters of the Set O of the RFC 2152, optionally written directly.
+const SET_O: &[u8] = b"!\"#$%&*;<=>@[]^_`{|}";
+
+/// Decodes a whole stream of UTF-7 bytes.
+pub(crate) fn decode_utf_7(bytes: &[u8], variant: Utf7Variant) -> Result<Utf32, UnicodeEncodingError> {
+    let mut decoder = Utf7Decoder::new(variant);
+    let mut ret = decoder.decode(bytes)?;
+    ret.data.extend(decoder.finish()?.data);
+    return Ok(ret);
+}
+
+/// Decodes a whole stream of UTF-7 bytes, handling the invalid sequences
+/// with the policy. The decoding goes on from the byte following an error
+/// with the state the decoder had, so it takes linear time. Returns the
+/// glyphs and the number of invalid sequences.
+pub(crate) fn decode_utf_7_lossy(bytes: &[u8], variant: Utf7Variant, policy: DecodingPolicy) -> Result<(Utf32, usize), UnicodeEncodingError> {
+    let mut decoder = Utf7Decoder::new(variant);
+    let mut data: Vec<u32> = Vec::new();
+    let mut count: usize = 0;
+    for byte in bytes {
+        let end = data.len();
+        if let Err(err) = decoder.read_byte(*byte, &mut data) {
+            // The character read from a byte ending an invalid base64
+            // sequence goes after the replacement of the sequence.
+            let read = data.split_off(end);
+            data.extend(policy.handle(&err)?);
+            data.extend(read);
+            count += 1;
+        }
+        decoder.consumed += 1;
+    }
+    if let Err(err) = decoder.finish_sequence(&mut data) {
+        data.extend(policy.handle(&err)?);
+        count += 1;
+    }
+    let (data, sanitized) = lossy::sanitize(&data, policy).map_err(|x| x.in_encoding(variant.name()))?;
+    return Ok((Utf32{data: data}, count + sanitized));
+}
+
+/// Encodes a whole list of glyphs as UTF-7.
+pub(crate) fn encode_utf_7(glyphs: &[u32], variant: Utf7Variant, direct: Utf7DirectCharacters) -> Vec<u8> {
+    let mut encoder = Utf7Encoder::new(variant, direct);
+    let mut ret: Vec<u8> = Vec::new();
+    for glyph in glyphs {
+        encoder.push_glyph(*glyph, &mut ret);
+    }
+    if encoder.shifted {
+        encoder.close_sequence(None, &mut ret);
+    }
+    return ret;
+}
+
+/* --------------------------------- Testing -------------------------------- */
+
+#[test]
+fn test_utf_7_rfc_examples() {
+    // Examples from the RFC 2152.
+    let utf7 = Utf7::from_bytes(b"Hi Mom -+Jjo--!", Utf7DirectCharacters::SetO).unwrap();
+    assert_eq!(utf7.to_string().unwrap(), "Hi Mom -☺-!");
+    let utf7 = Utf7::from_bytes(b"A+ImIDkQ.", Utf7DirectCharacters::SetD).unwrap();
+    assert_eq!(utf7.to_utf_32().data, vec![0x41, 0x2262, 0x391, 0x2E]);
+    let utf7 = Utf7::from_bytes("+ZeVnLIqe-".as_bytes(), Utf7DirectCharacters::SetD).unwrap();
+    assert_eq!(utf7.to_string().unwrap(), "日本語");
+
+    let utf7 = Utf7::from_string("Hi Mom -☺-!").unwrap();
+    assert_eq!(utf7.to_bytes(Utf7DirectCharacters::SetO), b"Hi Mom -+Jjo--!");
+    assert_eq!(utf7.to_bytes(Utf7DirectCharacters::SetD), b"Hi Mom -+Jjo--+ACE-");
+    assert_eq!(Utf7::from_string("A≢Α.").unwrap().data, b"A+ImIDkQ.");
+}
+
+#[test]
+fn test_utf_7_plus_sign() {
+    assert_eq!(Utf7::from_string("1+1").unwrap().data, b"1+-1");
+    assert_eq!(Utf7::from_bytes(b"1+-1", Utf7DirectCharacters::SetD).unwrap().to_string().unwrap(), "1+1");
+    assert_eq!(Utf7::from_string("é+").unwrap().data, b"+AOkAKw-");
+    assert_eq!(Utf7::from_bytes(b"+AOkAKw-", Utf7DirectCharacters::SetD).unwrap().to_string().unwrap(), "é+");
+}
+
+#[test]
+fn test_utf_7_surrogates() {
+    let s = "a😎b";
+    let utf7 = Utf7::from_string(s).unwrap();
+    assert_eq!(utf7.data, b"a+2D3eDg-b");
+    assert_eq!(utf7.to_string().unwrap(), s);
+}
+
+#[test]
+fn test_invalid_utf_7() {
+    let err = Utf7::from_bytes("aé".as_bytes(), Utf7DirectCharacters::SetD).err().unwrap();
+    assert_eq!(err.kind, InvalidUtf7Byte);
+    assert_eq!(err.position, Some(crate::unicode_encoding::ErrorPosition::Byte(1)));
+    // The bits left at the end of the sequence are not zero.
+    let err = Utf7::from_bytes(b"a+AOl-", Utf7DirectCharacters::SetD).err().unwrap();
+    assert_eq!(err.kind, InvalidUtf7Padding);
+    assert_eq!(err.position, Some(crate::unicode_encoding::ErrorPosition::Byte(1)));
+    assert_eq!(Utf7::from_bytes(b"a+", Utf7DirectCharacters::SetD).err().unwrap().kind, MissingEncodedBytes);
+    assert_eq!(Utf7::from_bytes(b"a+!", Utf7DirectCharacters::SetD).err().unwrap().kind, MissingEncodedBytes);
+}
+
+#[test]
+fn test_utf_7_lossy() {
+    // An invalid byte in a base64 sequence does not end it, and an invalid
+    // sequence is dropped without hiding the next characters.
+    let bytes = b"a\x80b+AOk\x80A6Q-c+AOl-d+!";
+    let (decoded, count) = Utf7::from_bytes_lossy(bytes, Utf7DirectCharacters::SetD, DecodingPolicy::Replace).unwrap();
+    assert_eq!(count, 4);
+    assert_eq!(decoded.to_string().unwrap(), "a\u{FFFD}b\u{E9}\u{FFFD}\u{E9}c\u{E9}\u{FFFD}d\u{FFFD}!");
+    let (decoded, count) = Utf7::from_bytes_lossy(b"+2D3-a", Utf7DirectCharacters::SetD, DecodingPolicy::Skip).unwrap();
+    assert_eq!(count, 1);
+    assert_eq!(decoded.to_string().unwrap(), "a");
+}
+
+#[test]
+fn test_utf_7_incremental() {
+    let s = "Hi Mom -☺-! 😎+";
+    let bytes = Utf7::from_string(s).unwrap().data;
+    let mut decoder = Utf7::decoder(Utf7DirectCharacters::SetD);
+    let decoded = crate::incremental::decode_byte_per_byte(decoder.as_mut(), &bytes).unwrap();
+    assert_eq!(decoded.to_string().unwrap(), s);
+
+    let glyphs = Utf32::from_string(s).unwrap();
+    let mut encoder = Utf7::encoder(Utf7DirectCharacters::SetD);
+    let mut encoded: Vec<u8> = Vec::new();
+    for glyph in glyphs.data {
+        encoded.extend(encoder.encode(&Utf32{data: vec![glyph]}).unwrap());
+    }
+    encoded.extend(encoder.finish().unwrap());
+    assert_eq!(encoded, bytes);
+}
@@ -1,51 +1,112 @@
 /// The UTF-8 module is quite important as it can be used to convert Rust's
 /// strings into the other unicode encoding types.
 
-use crate::unicode_encoding::UnicodeEncodingError::*;
+use crate::unicode_encoding::UnicodeEncodingErrorKind::*;
 use crate::unicode_encoding::UnicodeEncodingError;
 use crate::unicode_encoding::UnicodeEncoding;
 use crate::utf_32::Utf32;
+use crate::incremental::{Decoder, Encoder, SanityChecker};
+use crate::lossy::{self, DecodingPolicy, GlyphDecoder};
 
 /// A very basic wrapper for UTF-8 encoded data.
 pub struct Utf8 {
     pub data: Vec<u8>
 }
 
+/// Tells how strictly UTF-8 data is checked when it is decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Utf8Conformance {
+    /// Only the well-formed sequences from the table 3-7 of the Unicode
+    /// Standard are accepted. Overlong forms, encoded surrogates, and
+    /// code-points above U+10FFFF are rejected. This is the default.
+    Strict,
+
+    /// All the sequences with a valid prefix and the right number of
+    /// continuation bytes are accepted. This is needed to read encodings
+    /// derived from UTF-8 such as CESU-8.
+    Lenient,
+}
+
+impl Utf8Conformance {
+    /// Gives the function used to decode glyphs with this conformance.
+    fn glyph_decoder(&self) -> GlyphDecoder {
+        match self {
+            Utf8Conformance::Strict => utf_8_glyph_to_utf_32_strict,
+            Utf8Conformance::Lenient => utf_8_glyph_to_utf_32,
+        }
+    }
+}
+
 impl Utf8 {
     /// Converts a Rust String into `Utf8` struct.
     pub fn from_string(s: &str) -> Result<Self, UnicodeEncodingError> {
         let utf = Utf8{data: s.to_string().as_bytes().to_vec()};
-        match utf.check_sanity() {
-            NoError => return Ok(utf),
-            x => return Err(x),
-        }
+        utf.check_sanity()?;
+        return Ok(utf);
     }
 
-    /// Converts a `Utf8` struct to a Rust string
-    pub fn to_string(&self) -> String {
-        let bytes = self.data.clone();
-        let ret = std::str::from_utf8(&bytes).unwrap().to_string(); // As we are manipulating sanitized UTF-8 data it _should_ be OK to use unwrap here.
-        return ret;
+    /// Converts a `Utf8` struct to a Rust string. An error is returned if
+    /// the data is not strictly valid UTF-8.
+    pub fn to_string(&self) -> Result<String, UnicodeEncodingError> {
+        self.check_sanity_utf8(Utf8Conformance::Strict).map_err(|x| x.in_encoding(Self::NAME))?;
+        // As the data have been checked, nothing will be replaced.
+        return Ok(String::from_utf8_lossy(&self.data).into_owned());
+    }
+
+    /// Works like `from_bytes` but lets the conformance of the data be
+    /// chosen. The overlong forms accepted by `Utf8Conformance::Lenient` are
+    /// rewritten in their shortest form, so that the returned data is read
+    /// back the same way by the other functions, which are strict.
+    pub fn from_bytes_with_conformance(bytes: &[u8], conformance: Utf8Conformance) -> Result<Self, UnicodeEncodingError> {
+        let utf32 = Utf8{data: bytes.to_vec()}.decode_with_conformance(conformance).map_err(|x| x.in_encoding(Self::NAME))?;
+        utf32.check_sanity_utf32().map_err(|x| x.in_encoding(Self::NAME))?;
+        return Ok(Self::from_utf_32(&utf32));
+    }
+
+    /// Works like `decoder` but lets the conformance of the data be chosen.
+    pub fn decoder_with_conformance(conformance: Utf8Conformance) -> Box<dyn Decoder> {
+        return Box::new(Utf8Decoder::with_glyph_decoder(conformance.glyph_decoder(), Self::NAME));
+    }
+
+    /// Consider a stream of bytes as lenient UTF-8 without checking the
+    /// code-points. This is used to read the encodings derived from UTF-8.
+    pub(crate) fn from_bytes_lenient_no_check(bytes: &[u8]) -> Result<Self, UnicodeEncodingError> {
+        let ret = Utf8{data: bytes.to_vec()};
+        ret.check_sanity_utf8(Utf8Conformance::Lenient).map_err(|x| x.in_encoding(Self::NAME))?;
+        return Ok(ret);
+    }
+
+    /// Decodes the data to UTF-32 with the given conformance. This is used
+    /// to read the encodings derived from UTF-8 leniently.
+    pub(crate) fn decode_with_conformance(&self, conformance: Utf8Conformance) -> Result<Utf32, UnicodeEncodingError> {
+        let glyph_decoder = conformance.glyph_decoder();
+        let mut index: usize = 0;
+        let mut data: Vec<u32> = Vec::new();
+        while index < self.data.len() {
+            let (glyph, len) = glyph_decoder(&self.data, index)?;
+            data.push(glyph);
+            index += len;
+        }
+        return Ok(Utf32{data: data});
     }
 
     /// Goes through a whole UTF-8 data to ensure that it is valid.
-    fn check_sanity_utf8(&self) -> UnicodeEncodingError {
+    fn check_sanity_utf8(&self, conformance: Utf8Conformance) -> Result<(), UnicodeEncodingError> {
+        let glyph_decoder = conformance.glyph_decoder();
         let mut index: usize = 0;
         while index < self.data.len() {
-            let (_glyph, len) = match utf_8_glyph_to_utf_32(&self.data, index) {
-                Err(x) => {return x;},
-                Ok(x) => x,
-            };
+            let (_glyph, len) = glyph_decoder(&self.data, index)?;
             index += len;
             check_index_ok(index, &self.data);
         }
-        return NoError;
+        return Ok(());
     }
-
-
 }
 
 impl UnicodeEncoding for Utf8 {
+    const NAME: &'static str = "UTF-8";
+    type Options = ();
+
     /// Convert UTF-32 data to UTF-8.
     fn from_utf_32(data_utf_32: &Utf32) -> Self {
         let mut data: Vec<u8> = Vec::new();
@@ -58,39 +119,118 @@ impl UnicodeEncoding for Utf8 {
         return utf;
     }
 
-    /// Convert UFT-8 data to UTF-32.
-    fn to_utf_32(&self) -> Utf32 {
-        let mut index: usize = 0;
-        let mut data: Vec<u32> = Vec::new();
-        while index < self.data.len() {
-            let (glyph, len) = match utf_8_glyph_to_utf_32(&self.data, index) {
-                Err(_) => {panic!("[UNICODE ENCODING ERROR] Invalid UTF-8 glyph. This should not have happen if the source was safely generated with from_string or from_bytes. This could happen if from_string_no_check was used. This need to be corrected from the library's user side.");},
-                Ok(x) => x,
-            };
-            data.push(glyph);
-            index += len;
-            check_index_ok(index, &self.data);
-        }
-        return Utf32{data: data};
+    /// Convert UFT-8 data to UTF-32. The data is decoded strictly, as with
+    /// `from_bytes`, even when the struct is built directly.
+    fn try_to_utf_32(&self) -> Result<Utf32, UnicodeEncodingError> {
+        return self.decode_with_conformance(Utf8Conformance::Strict).map_err(|x| x.in_encoding(Self::NAME));
     }
 
     /// Convert the instance of `Utf8` type to a vector of byte.
     /// No transformation is needed.
-    fn to_bytes(&self, _big_endian: bool) -> Vec<u8> {
+    fn to_bytes(&self, _options: ()) -> Vec<u8> {
         let ret = self.data.clone();
         return ret;
     }
 
     /// Consider a stream of UTF-8 encoded byte and turn it into a `Utf8` type.
     /// It only copies the bytes.
-    fn from_bytes_no_check(bytes: &[u8], _big_endian: bool) -> Result<Self, UnicodeEncodingError> {
+    fn from_bytes_no_check(bytes: &[u8], _options: ()) -> Result<Self, UnicodeEncodingError> {
         let ret = Utf8{data: bytes.to_vec()};
-        match ret.check_sanity_utf8() {
-            NoError => {return Ok(ret);},
-            x => {return Err(x)},
+        ret.check_sanity_utf8(Utf8Conformance::Strict).map_err(|x| x.in_encoding(Self::NAME))?;
+        return Ok(ret);
+    }
+
+    /// Decodes UTF-8 glyph by glyph, replacing the maximal subparts of the
+    /// invalid sequences.
+    fn from_bytes_lossy(bytes: &[u8], _options: (), policy: DecodingPolicy) -> Result<(Self, usize), UnicodeEncodingError> {
+        let (data, count) = lossy::decode_glyphs_lossy(bytes, utf_8_glyph_to_utf_32_strict, policy).map_err(|x| x.in_encoding(Self::NAME))?;
+        let (data, sanitized) = lossy::sanitize(&data, policy).map_err(|x| x.in_encoding(Self::NAME))?;
+        return Ok((Self::from_utf_32(&Utf32{data: data}), count + sanitized));
+    }
+
+    fn decoder(_options: ()) -> Box<dyn Decoder> {
+        return Self::decoder_with_conformance(Utf8Conformance::Strict);
+    }
+
+    fn encoder(_options: ()) -> Box<dyn Encoder> {
+        return Box::new(Utf8Encoder{checker: SanityChecker::new()});
+    }
+}
+
+/* --------------------------- Incremental coding --------------------------- */
+
+/// Decodes UTF-8 data chunk by chunk.
+pub struct Utf8Decoder {
+    pending: Vec<u8>,
+    consumed: usize,
+    glyph_decoder: GlyphDecoder,
+    /// Name of the encoding decoded, as the decoder is shared with the
+    /// encodings derived from UTF-8.
+    name: &'static str,
+    checker: SanityChecker,
+}
+
+impl Utf8Decoder {
+    /// Creates a decoder reading the glyphs with `glyph_decoder`.
+    pub(crate) fn with_glyph_decoder(glyph_decoder: GlyphDecoder, name: &'static str) -> Self {
+        return Utf8Decoder{pending: Vec::new(), consumed: 0, glyph_decoder: glyph_decoder, name: name, checker: SanityChecker::new()};
+    }
+}
+
+impl Decoder for Utf8Decoder {
+    fn decode(&mut self, bytes: &[u8]) -> Result<Utf32, UnicodeEncodingError> {
+        self.pending.extend_from_slice(bytes);
+        let ret = Utf32{data: decode_available_glyphs(&mut self.pending, &mut self.consumed, self.glyph_decoder).map_err(|x| x.in_encoding(self.name))?};
+        self.checker.push_all(&ret).map_err(|x| x.in_encoding(self.name))?;
+        return Ok(ret);
+    }
+
+    fn finish(&mut self) -> Result<Utf32, UnicodeEncodingError> {
+        if !self.pending.is_empty() {
+            return Err(UnicodeEncodingError::new(MissingEncodedBytes).at_byte(self.consumed).with_code_units(&self.pending).in_encoding(self.name));
         }
+        self.checker.finish()?;
+        return Ok(Utf32{data: Vec::new()});
     }
+}
+
+/// Encodes UTF-8 data chunk by chunk.
+pub struct Utf8Encoder {
+    checker: SanityChecker,
+}
 
+impl Encoder for Utf8Encoder {
+    fn encode(&mut self, data: &Utf32) -> Result<Vec<u8>, UnicodeEncodingError> {
+        self.checker.push_all(data)?;
+        return Ok(Utf8::from_utf_32(data).data);
+    }
+
+    fn finish(&mut self) -> Result<Vec<u8>, UnicodeEncodingError> {
+        self.checker.finish()?;
+        return Ok(Vec::new());
+    }
+}
+
+/// Decodes all the complete glyphs at the beginning of a buffer of UTF-8
+/// bytes and removes them from it. The bytes of an incomplete glyph at the
+/// end of the buffer are left in it. The number of bytes removed is added to
+/// `consumed`, which is used to locate errors.
+pub(crate) fn decode_available_glyphs(buffer: &mut Vec<u8>, consumed: &mut usize, glyph_decoder: impl Fn(&[u8], usize) -> Result<(u32, usize), UnicodeEncodingError>) -> Result<Vec<u32>, UnicodeEncodingError> {
+    let mut index: usize = 0;
+    let mut ret: Vec<u32> = Vec::new();
+    while index < buffer.len() {
+        match glyph_decoder(buffer, index) {
+            Ok((glyph, len)) => {
+                ret.push(glyph);
+                index += len;
+            },
+            Err(x) if x.kind == MissingEncodedBytes => break,
+            Err(x) => return Err(x.shifted(*consumed)),
+        }
+    }
+    buffer.drain(..index);
+    *consumed += index;
+    return Ok(ret);
 }
 
 /* ---------------------------- Helper functions ---------------------------- */
@@ -157,8 +297,11 @@ fn utf_32_glyph_to_utf_8(glyph: u32) -> Vec<u8> {
 /// The inputs are a stream of UTF-8 encoded data and the index of the
 /// beginning of the new glyph. The return value are the number of char used to
 /// encode the glyph.
-/// If the glyph does not makes sense, an error will be raised.
-fn utf_8_glyph_to_utf_32(utf8_data: &Vec<u8>, start: usize) -> Result<(u32, usize), UnicodeEncodingError> {
+/// If the glyph does not makes sense, an error locating it will be raised.
+/// The faulty code units of the error are the maximal subpart of the invalid
+/// sequence, that is the longest sequence of bytes that could have been the
+/// beginning of a valid glyph, or a single byte if there is none.
+pub(crate) fn utf_8_glyph_to_utf_32(utf8_data: &[u8], start: usize) -> Result<(u32, usize), UnicodeEncodingError> {
     let mut glyph_len = 0;
     let mut glyph: u32 = 0;
     for i in 0..4 {
@@ -169,14 +312,14 @@ fn utf_8_glyph_to_utf_32(utf8_data: &Vec<u8>, start: usize) -> Result<(u32, usiz
         }
     }
     if glyph_len == 0 {
-        return Err(InvalidUtf8Prefix);
-    }
-    if glyph_len + start > utf8_data.len() {
-        return Err(MissingEncodedBytes);
+        return Err(UnicodeEncodingError::new(InvalidUtf8Prefix).at_byte(start).with_code_units(&utf8_data[start..start+1]));
     }
     for i in 1..glyph_len {
+        if start + i >= utf8_data.len() {
+            return Err(UnicodeEncodingError::new(MissingEncodedBytes).at_byte(start).with_code_units(&utf8_data[start..]));
+        }
         if utf8_data[start+i] & CNT_BYTE_GLYPH_MASK != CNT_BYTE_GLYPH_CODE {
-            return Err(IncoherentUtf8Codepoint);
+            return Err(UnicodeEncodingError::new(IncoherentUtf8Codepoint).at_byte(start).with_code_units(&utf8_data[start..start+i]));
         }
         glyph <<= 6;
         glyph |= (utf8_data[start+i] & !CNT_BYTE_GLYPH_MASK) as u32;
@@ -184,6 +327,51 @@ fn utf_8_glyph_to_utf_32(utf8_data: &Vec<u8>, start: usize) -> Result<(u32, usiz
     return Ok((glyph, glyph_len));
 }
 
+/// Works like `utf_8_glyph_to_utf_32` but only accepts the well-formed byte
+/// sequences listed in the table 3-7 of the Unicode Standard. The second byte
+/// of a glyph is restricted depending on the first one to reject overlong
+/// forms, encoded surrogates, and code-points above U+10FFFF.
+pub(crate) fn utf_8_glyph_to_utf_32_strict(utf8_data: &[u8], start: usize) -> Result<(u32, usize), UnicodeEncodingError> {
+    let first_byte = utf8_data[start];
+    let first_byte_error = |kind| Err(UnicodeEncodingError::new(kind).at_byte(start).with_code_units(&utf8_data[start..start+1]));
+    // Length of the glyph, valid range of the second byte and kind of the
+    // error if the second byte is a continuation byte outside of the range.
+    let (glyph_len, second_min, second_max, second_error) = match first_byte {
+        0x00..=0x7F => return Ok((first_byte as u32, 1)),
+        0xC0..=0xC1 => return first_byte_error(OverlongUtf8Encoding),
+        0xC2..=0xDF => (2, 0x80, 0xBF, IncoherentUtf8Codepoint),
+        0xE0        => (3, 0xA0, 0xBF, OverlongUtf8Encoding),
+        0xE1..=0xEC => (3, 0x80, 0xBF, IncoherentUtf8Codepoint),
+        0xED        => (3, 0x80, 0x9F, EncodedSurrogate),
+        0xEE..=0xEF => (3, 0x80, 0xBF, IncoherentUtf8Codepoint),
+        0xF0        => (4, 0x90, 0xBF, OverlongUtf8Encoding),
+        0xF1..=0xF3 => (4, 0x80, 0xBF, IncoherentUtf8Codepoint),
+        0xF4        => (4, 0x80, 0x8F, CodepointAboveUnicodeRange),
+        0xF5..=0xF7 => return first_byte_error(CodepointAboveUnicodeRange),
+        _           => return first_byte_error(InvalidUtf8Prefix),
+    };
+    for i in 1..glyph_len {
+        if start + i >= utf8_data.len() {
+            return Err(UnicodeEncodingError::new(MissingEncodedBytes).at_byte(start).with_code_units(&utf8_data[start..]));
+        }
+        let byte = utf8_data[start+i];
+        let (min, max) = if i == 1 {
+            (second_min, second_max)
+        } else {
+            (0x80, 0xBF)
+        };
+        if byte < min || byte > max {
+            let kind = if i == 1 && byte & CNT_BYTE_GLYPH_MASK == CNT_BYTE_GLYPH_CODE {
+                second_error
+            } else {
+                IncoherentUtf8Codepoint
+            };
+            return Err(UnicodeEncodingError::new(kind).at_byte(start).with_code_units(&utf8_data[start..start+i]));
+        }
+    }
+    return utf_8_glyph_to_utf_32(utf8_data, start);
+}
+
 /// Ensure that an index that is going to be used is not too big. If so, a
 /// panic is caused. This should not be needed as there is already checks to
 /// ensure that there is no missing bytes in UTF-8 glyphs.
@@ -217,7 +405,7 @@ fn test_utf_32_to_utf_8_and_back() {
 fn str_to_utf_8_and_back() {
     let s = "Laé§çà→̉ỏ";
     let conv = Utf8::from_string(s).unwrap();
-    let conv_back = conv.to_string();
+    let conv_back = conv.to_string().unwrap();
     assert_eq!(s, conv_back);
 }
 
@@ -279,3 +467,90 @@ fn test_utf_32_to_utf_8_and_back_plus() {
     double_conv(0x10FFFF);
 }
 
+
+#[test]
+/// Test that glyphs split across chunks are decoded.
+fn test_utf_8_incremental() {
+    let s = "aé→😎";
+    let mut decoder = Utf8::decoder(());
+    let decoded = crate::incremental::decode_byte_per_byte(decoder.as_mut(), s.as_bytes()).unwrap();
+    assert!(decoded == Utf32::from_string(s).unwrap());
+
+    let mut decoder = Utf8::decoder(());
+    decoder.decode(&[0x61, 0xF0, 0x9F]).unwrap();
+    assert!(decoder.finish().is_err());
+
+    let mut encoder = Utf8::encoder(());
+    let mut encoded = encoder.encode(&Utf32{data: vec![0x61, 0xE9]}).unwrap();
+    encoded.extend(encoder.encode(&Utf32{data: vec![0x1F60E]}).unwrap());
+    encoded.extend(encoder.finish().unwrap());
+    assert_eq!(encoded, "aé😎".as_bytes());
+}
+
+#[test]
+/// Test that errors tell where the invalid data is.
+fn test_utf_8_error_position() {
+    use crate::unicode_encoding::ErrorPosition;
+    let bytes: [u8; 5] = [0x61, 0x62, 0xC3, 0x41, 0x62];
+    let err = Utf8::from_bytes(&bytes, ()).err().unwrap();
+    assert_eq!(err.kind, IncoherentUtf8Codepoint);
+    assert_eq!(err.position, Some(ErrorPosition::Byte(2)));
+    assert_eq!(err.code_units, vec![0xC3]);
+    assert_eq!(err.encoding, Some("UTF-8"));
+    assert_eq!(format!("{}", err), "incoherent UTF-8 code-point at byte 2 while processing UTF-8 (faulty code units: C3)");
+
+    let mut decoder = Utf8::decoder(());
+    decoder.decode(&bytes[..2]).unwrap();
+    let err = decoder.decode(&bytes[2..]).err().unwrap();
+    assert_eq!(err.position, Some(ErrorPosition::Byte(2)));
+}
+
+#[test]
+/// Test that invalid sequences are replaced by their maximal subparts.
+fn test_utf_8_lossy() {
+    let bytes: [u8; 8] = [0x61, 0xF0, 0x9F, 0x41, 0x80, 0xE2, 0x82, 0xAC];
+    let (utf8, count) = Utf8::from_bytes_lossy(&bytes, (), DecodingPolicy::Replace).unwrap();
+    assert_eq!(utf8.to_string().unwrap(), "a\u{FFFD}A\u{FFFD}€");
+    assert_eq!(count, 2);
+    let (utf8, _) = Utf8::from_bytes_lossy(&bytes, (), DecodingPolicy::Escape).unwrap();
+    assert_eq!(utf8.to_string().unwrap(), "a\\xF0\\x9FA\\x80€");
+    assert!(Utf8::from_bytes_lossy(&bytes, (), DecodingPolicy::Strict).is_err());
+}
+
+#[test]
+/// Test that ill-formed sequences are rejected in strict mode only.
+fn test_utf_8_conformance() {
+    fn check(bytes: &[u8], kind: crate::unicode_encoding::UnicodeEncodingErrorKind, lenient_ok: bool) {
+        let err = Utf8::from_bytes(bytes, ()).err().unwrap();
+        assert_eq!(err.kind, kind);
+        assert_eq!(Utf8::from_bytes_with_conformance(bytes, Utf8Conformance::Lenient).is_ok(), lenient_ok);
+    }
+    check(&[0x2F, 0xC0, 0xAF], OverlongUtf8Encoding, true);
+    check(&[0xE0, 0x80, 0xAF], OverlongUtf8Encoding, true);
+    check(&[0xF0, 0x80, 0x80, 0xAF], OverlongUtf8Encoding, true);
+    check(&[0xED, 0xA0, 0x80], EncodedSurrogate, true);
+    // Code-points above U+10FFFF are never valid Unicode, even when they
+    // are decoded leniently.
+    check(&[0xF4, 0x90, 0x80, 0x80], CodepointAboveUnicodeRange, false);
+    check(&[0xF7, 0xBF, 0xBF, 0xBF], CodepointAboveUnicodeRange, false);
+    assert!(Utf8::from_bytes(&[0xED, 0x9F, 0xBF, 0xF4, 0x8F, 0xBF, 0xBF], ()).is_ok());
+
+    let (utf8, count) = Utf8::from_bytes_lossy(&[0xE0, 0x80, 0xAF, 0x41], (), DecodingPolicy::Replace).unwrap();
+    assert_eq!(utf8.to_string().unwrap(), "\u{FFFD}\u{FFFD}\u{FFFD}A");
+    assert_eq!(count, 3);
+    // The overlong forms read leniently are rewritten in their shortest form.
+    assert_eq!(Utf8::from_bytes_with_conformance(&[0x2F, 0xC0, 0xAF], Utf8Conformance::Lenient).unwrap().data, b"//");
+}
+
+#[test]
+/// Test that ill-formed sequences are rejected when the struct is built
+/// directly rather than through `from_bytes`.
+fn test_utf_8_struct_literal_is_strict() {
+    use crate::utf_16::Utf16;
+    for bytes in [vec![0x61, 0xC0, 0xAF], vec![0x61, 0xED, 0xA0, 0x80], vec![0x61, 0xF4, 0x90, 0x80, 0x80]] {
+        let utf8 = Utf8{data: bytes};
+        assert!(utf8.check_sanity().is_err());
+        assert!(!utf8.is_well_formed());
+        assert!(utf8.convert_to::<Utf16>().is_err());
+    }
+}
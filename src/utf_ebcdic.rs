@@ -0,0 +1,274 @@
+/// The UTF-EBCDIC module handles the EBCDIC-friendly Unicode encoding
+/// described in the Unicode Technical Report #16, used on IBM mainframes.
+
+use crate::unicode_encoding::UnicodeEncodingErrorKind::*;
+use crate::unicode_encoding::UnicodeEncodingError;
+use crate::unicode_encoding::UnicodeEncoding;
+use crate::utf_32::Utf32;
+use crate::utf_8::Utf8Decoder;
+use crate::incremental::{Decoder, Encoder, SanityChecker};
+use crate::lossy::{self, DecodingPolicy};
+
+/// A wrapper for UTF-EBCDIC encoded bytes. The code-points are first encoded
+/// in the I8 intermediate form, a variant of UTF-8 whose single bytes go up to
+/// 0x9F, then each byte is mapped to an EBCDIC-friendly byte.
+pub struct UtfEbcdic {
+    pub data: Vec<u8>
+}
+
+impl UnicodeEncoding for UtfEbcdic {
+    const NAME: &'static str = "UTF-EBCDIC";
+    type Options = ();
+
+    /// Convert UTF-32 data to UTF-EBCDIC.
+    fn from_utf_32(data_utf_32: &Utf32) -> Self {
+        let mut data: Vec<u8> = Vec::new();
+        for glyph in &data_utf_32.data {
+            for i8_byte in utf_32_glyph_to_i8(*glyph) {
+                data.push(I8_TO_EBCDIC[i8_byte as usize]);
+            }
+        }
+        return UtfEbcdic{data: data};
+    }
+
+    /// Convert UTF-EBCDIC data to UTF-32.
+    fn try_to_utf_32(&self) -> Result<Utf32, UnicodeEncodingError> {
+        let mut index: usize = 0;
+        let mut data: Vec<u32> = Vec::new();
+        while index < self.data.len() {
+            let (glyph, len) = utf_ebcdic_glyph_to_utf_32(&self.data, index).map_err(|x| x.in_encoding(Self::NAME))?;
+            data.push(glyph);
+            index += len;
+        }
+        return Ok(Utf32{data: data});
+    }
+
+    /// Convert the instance of `UtfEbcdic` to a vector of bytes.
+    /// No transformation is needed.
+    fn to_bytes(&self, _options: ()) -> Vec<u8> {
+        return self.data.clone();
+    }
+
+    /// Consider a stream of UTF-EBCDIC encoded bytes and turn it into an
+    /// `UtfEbcdic` type. The bytes are decoded to ensure they are valid.
+    fn from_bytes_no_check(bytes: &[u8], _options: ()) -> Result<Self, UnicodeEncodingError> {
+        let ret = UtfEbcdic{data: bytes.to_vec()};
+        ret.try_to_utf_32()?;
+        return Ok(ret);
+    }
+
+    /// Decodes UTF-EBCDIC glyph by glyph, replacing the invalid sequences.
+    fn from_bytes_lossy(bytes: &[u8], _options: (), policy: DecodingPolicy) -> Result<(Self, usize), UnicodeEncodingError> {
+        let (data, count) = lossy::decode_glyphs_lossy(bytes, utf_ebcdic_glyph_to_utf_32, policy).map_err(|x| x.in_encoding(Self::NAME))?;
+        let (data, sanitized) = lossy::sanitize(&data, policy).map_err(|x| x.in_encoding(Self::NAME))?;
+        return Ok((Self::from_utf_32(&Utf32{data: data}), count + sanitized));
+    }
+
+    fn decoder(_options: ()) -> Box<dyn Decoder> {
+        // The glyphs are read as in UTF-8, only the decoding of a glyph differs.
+        return Box::new(Utf8Decoder::with_glyph_decoder(utf_ebcdic_glyph_to_utf_32, Self::NAME));
+    }
+
+    fn encoder(_options: ()) -> Box<dyn Encoder> {
+        return Box::new(UtfEbcdicEncoder{checker: SanityChecker::new()});
+    }
+}
+
+/* --------------------------- Incremental coding --------------------------- */
+
+/// Encodes UTF-EBCDIC data chunk by chunk.
+pub struct UtfEbcdicEncoder {
+    checker: SanityChecker,
+}
+
+impl Encoder for UtfEbcdicEncoder {
+    fn encode(&mut self, data: &Utf32) -> Result<Vec<u8>, UnicodeEncodingError> {
+        self.checker.push_all(data)?;
+        return Ok(UtfEbcdic::from_utf_32(data).data);
+    }
+
+    fn finish(&mut self) -> Result<Vec<u8>, UnicodeEncodingError> {
+        self.checker.finish()?;
+        return Ok(Vec::new());
+    }
+}
+
+/* ---------------------------- Helper functions ---------------------------- */
+
+// Constants used to manipulate the I8 intermediate form.
+const  SINGLE_BYTE_LIMIT: u32 = 0xA0;
+const    TWO_BYTES_LIMIT: u32 = 0x400;
+const  THREE_BYTES_LIMIT: u32 = 0x4000;
+const   FOUR_BYTES_LIMIT: u32 = 0x40000;
+const     LAST_CODEPOINT: u32 = 0x10FFFF;
+const   CNT_BYTE_MASK: u8 = 0b1110_0000;
+const   CNT_BYTE_CODE: u8 = 0b1010_0000;
+const   CNT_BYTE_BITS: u32 = 5;
+
+/// The first byte of the I8 sequences of each length, from two to five
+/// bytes, with the mask telling the bits used for the length.
+const LEAD_BYTES: [(u8, u8); 4] = [(0b1100_0000, 0b1110_0000),
+                                   (0b1110_0000, 0b1111_0000),
+                                   (0b1111_0000, 0b1111_1000),
+                                   (0b1111_1000, 0b1111_1100)];
+
+/// The smallest code-point encoded with each number of bytes, used to reject
+/// the overlong forms.
+const LENGTH_MINIMUMS: [u32; 5] = [0, SINGLE_BYTE_LIMIT, TWO_BYTES_LIMIT, THREE_BYTES_LIMIT, FOUR_BYTES_LIMIT];
+
+/// The mapping from the bytes of the I8 intermediate form to UTF-EBCDIC, from
+/// the table 3 of the Unicode Technical Report #16. The single bytes are
+/// mapped as in the EBCDIC code page 1047, the others fill the unused bytes
+/// in order.
+const I8_TO_EBCDIC: [u8; 256] = [
+    0x00, 0x01, 0x02, 0x03, 0x37, 0x2D, 0x2E, 0x2F, 0x16, 0x05, 0x15, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F,
+    0x10, 0x11, 0x12, 0x13, 0x3C, 0x3D, 0x32, 0x26, 0x18, 0x19, 0x3F, 0x27, 0x1C, 0x1D, 0x1E, 0x1F,
+    0x40, 0x5A, 0x7F, 0x7B, 0x5B, 0x6C, 0x50, 0x7D, 0x4D, 0x5D, 0x5C, 0x4E, 0x6B, 0x60, 0x4B, 0x61,
+    0xF0, 0xF1, 0xF2, 0xF3, 0xF4, 0xF5, 0xF6, 0xF7, 0xF8, 0xF9, 0x7A, 0x5E, 0x4C, 0x7E, 0x6E, 0x6F,
+    0x7C, 0xC1, 0xC2, 0xC3, 0xC4, 0xC5, 0xC6, 0xC7, 0xC8, 0xC9, 0xD1, 0xD2, 0xD3, 0xD4, 0xD5, 0xD6,
+    0xD7, 0xD8, 0xD9, 0xE2, 0xE3, 0xE4, 0xE5, 0xE6, 0xE7, 0xE8, 0xE9, 0xAD, 0xE0, 0xBD, 0x5F, 0x6D,
+    0x79, 0x81, 0x82, 0x83, 0x84, 0x85, 0x86, 0x87, 0x88, 0x89, 0x91, 0x92, 0x93, 0x94, 0x95, 0x96,
+    0x97, 0x98, 0x99, 0xA2, 0xA3, 0xA4, 0xA5, 0xA6, 0xA7, 0xA8, 0xA9, 0xC0, 0x4F, 0xD0, 0xA1, 0x07,
+    0x20, 0x21, 0x22, 0x23, 0x24, 0x25, 0x06, 0x17, 0x28, 0x29, 0x2A, 0x2B, 0x2C, 0x09, 0x0A, 0x1B,
+    0x30, 0x31, 0x1A, 0x33, 0x34, 0x35, 0x36, 0x08, 0x38, 0x39, 0x3A, 0x3B, 0x04, 0x14, 0x3E, 0xFF,
+    0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49, 0x4A, 0x51, 0x52, 0x53, 0x54, 0x55, 0x56,
+    0x57, 0x58, 0x59, 0x62, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68, 0x69, 0x6A, 0x70, 0x71, 0x72, 0x73,
+    0x74, 0x75, 0x76, 0x77, 0x78, 0x80, 0x8A, 0x8B, 0x8C, 0x8D, 0x8E, 0x8F, 0x90, 0x9A, 0x9B, 0x9C,
+    0x9D, 0x9E, 0x9F, 0xA0, 0xAA, 0xAB, 0xAC, 0xAE, 0xAF, 0xB0, 0xB1, 0xB2, 0xB3, 0xB4, 0xB5, 0xB6,
+    0xB7, 0xB8, 0xB9, 0xBA, 0xBB, 0xBC, 0xBE, 0xBF, 0xCA, 0xCB, 0xCC, 0xCD, 0xCE, 0xCF, 0xDA, 0xDB,
+    0xDC, 0xDD, 0xDE, 0xDF, 0xE1, 0xEA, 0xEB, 0xEC, 0xED, 0xEE, 0xEF, 0xFA, 0xFB, 0xFC, 0xFD, 0xFE,
+];
+
+/// The mapping from UTF-EBCDIC to the bytes of the I8 intermediate form.
+const EBCDIC_TO_I8: [u8; 256] = invert_mapping(&I8_TO_EBCDIC);
+
+/// Inverts a byte mapping that is a permutation.
+const fn invert_mapping(mapping: &[u8; 256]) -> [u8; 256] {
+    let mut ret = [0u8; 256];
+    let mut i = 0;
+    while i < 256 {
+        ret[mapping[i] as usize] = i as u8;
+        i += 1;
+    }
+    return ret;
+}
+
+/// Convert an UTF-32 glyph into the equivalent bytes of the I8 form.
+fn utf_32_glyph_to_i8(glyph: u32) -> Vec<u8> {
+    if glyph < SINGLE_BYTE_LIMIT {
+        return vec![glyph as u8];
+    }
+    let len = if glyph < TWO_BYTES_LIMIT {
+        2
+    } else if glyph < THREE_BYTES_LIMIT {
+        3
+    } else if glyph < FOUR_BYTES_LIMIT {
+        4
+    } else {
+        5
+    };
+    let (lead_code, lead_mask) = LEAD_BYTES[len - 2];
+    let mut ret: Vec<u8> = Vec::new();
+    let lead_bits = glyph >> (CNT_BYTE_BITS * (len as u32 - 1));
+    ret.push(lead_code | (lead_bits as u8 & !lead_mask));
+    for i in (0..len-1).rev() {
+        let bits = (glyph >> (CNT_BYTE_BITS * i as u32)) & 0x1F;
+        ret.push(CNT_BYTE_CODE | bits as u8);
+    }
+    return ret;
+}
+
+/// Convert an UTF-EBCDIC glyph into UTF-32 and tells how many bytes are
+/// making this glyph. The bytes are mapped back to the I8 form, which is
+/// decoded as UTF-8 with five bits in each continuation byte. The overlong
+/// forms, the encoded surrogates, and the code-points above U+10FFFF are
+/// rejected.
+fn utf_ebcdic_glyph_to_utf_32(data: &[u8], start: usize) -> Result<(u32, usize), UnicodeEncodingError> {
+    let lead = EBCDIC_TO_I8[data[start] as usize];
+    if (lead as u32) < SINGLE_BYTE_LIMIT {
+        return Ok((lead as u32, 1));
+    }
+    let mut glyph_len = 0;
+    let mut glyph: u32 = 0;
+    for (i, (lead_code, lead_mask)) in LEAD_BYTES.iter().enumerate() {
+        if lead & lead_mask == *lead_code {
+            glyph_len = i + 2;
+            glyph = (lead & !lead_mask) as u32;
+            break;
+        }
+    }
+    if glyph_len == 0 {
+        return Err(UnicodeEncodingError::new(InvalidUtfEbcdicSequence).at_byte(start).with_code_units(&data[start..start+1]));
+    }
+    for i in 1..glyph_len {
+        if start + i >= data.len() {
+            return Err(UnicodeEncodingError::new(MissingEncodedBytes).at_byte(start).with_code_units(&data[start..]));
+        }
+        let byte = EBCDIC_TO_I8[data[start+i] as usize];
+        if byte & CNT_BYTE_MASK != CNT_BYTE_CODE {
+            return Err(UnicodeEncodingError::new(InvalidUtfEbcdicSequence).at_byte(start).with_code_units(&data[start..start+i]));
+        }
+        glyph = (glyph << CNT_BYTE_BITS) | (byte & !CNT_BYTE_MASK) as u32;
+    }
+    let error = |kind| Err(UnicodeEncodingError::new(kind).at_byte(start).with_code_units(&data[start..start+glyph_len]));
+    if glyph < LENGTH_MINIMUMS[glyph_len - 1] {
+        return error(OverlongUtf8Encoding);
+    }
+    if glyph > LAST_CODEPOINT {
+        return error(CodepointAboveUnicodeRange);
+    }
+    if (0xD800..0xE000).contains(&glyph) {
+        return error(EncodedSurrogate);
+    }
+    return Ok((glyph, glyph_len));
+}
+
+/* --------------------------------- Testing -------------------------------- */
+
+#[test]
+fn test_utf_ebcdic_table() {
+    for i in 0..256 {
+        assert_eq!(EBCDIC_TO_I8[I8_TO_EBCDIC[i] as usize] as usize, i);
+    }
+}
+
+#[test]
+fn test_utf_32_to_utf_ebcdic() {
+    fn conv(glyph: u32, bytes: Vec<u8>) {
+        let utf_ebcdic = UtfEbcdic::from_utf_32(&Utf32{data: vec![glyph]});
+        assert_eq!(utf_ebcdic.data, bytes);
+        assert_eq!(UtfEbcdic::from_bytes(&bytes, ()).unwrap().to_utf_32().data, vec![glyph]);
+    }
+    // The invariant characters are encoded as in EBCDIC.
+    conv(0x41, vec![0xC1]);
+    conv(0x61, vec![0x81]);
+    conv(0x30, vec![0xF0]);
+    conv(0x20, vec![0x40]);
+    conv(0x85, vec![0x25]);
+    conv(0xE9, vec![0x8B, 0x4A]);
+    conv(0x3B1, vec![0xB4, 0x58]);
+    conv(0xFEFF, vec![0xDD, 0x73, 0x66, 0x73]);
+    conv(0x1F60E, vec![0xDF, 0x71, 0x57, 0x55]);
+    conv(0x10FFFF, vec![0xEE, 0x42, 0x73, 0x73, 0x73]);
+}
+
+#[test]
+fn test_invalid_utf_ebcdic() {
+    // A continuation byte can't start a glyph.
+    assert_eq!(UtfEbcdic::from_bytes(&[0x41], ()).err().unwrap().kind, InvalidUtfEbcdicSequence);
+    assert_eq!(UtfEbcdic::from_bytes(&[0xB8], ()).err().unwrap().kind, MissingEncodedBytes);
+    assert_eq!(UtfEbcdic::from_bytes(&[0xB8, 0xC1], ()).err().unwrap().kind, InvalidUtfEbcdicSequence);
+    // U+0001 encoded with two bytes.
+    assert_eq!(UtfEbcdic::from_bytes(&[0x74, 0x42], ()).err().unwrap().kind, OverlongUtf8Encoding);
+    // U+110000.
+    assert_eq!(UtfEbcdic::from_bytes(&[0xEE, 0x43, 0x41, 0x41, 0x41], ()).err().unwrap().kind, CodepointAboveUnicodeRange);
+}
+
+#[test]
+fn test_utf_ebcdic_incremental() {
+    let s = "Aé😎";
+    let bytes = UtfEbcdic::from_string(s).unwrap().data;
+    let mut decoder = UtfEbcdic::decoder(());
+    let decoded = crate::incremental::decode_byte_per_byte(decoder.as_mut(), &bytes).unwrap();
+    assert_eq!(decoded.to_string().unwrap(), s);
+}
@@ -0,0 +1,159 @@
+/// The WTF-8 module handles the __Wobbly Transformation Format − 8-bit__,
+/// a superset of UTF-8 that can carry the unpaired surrogates of ill-formed
+/// UTF-16, such as the strings of JavaScript or the file names of Windows.
+
+use crate::unicode_encoding::UnicodeEncodingError;
+use crate::unicode_encoding::UnicodeEncoding;
+use crate::utf_32::Utf32;
+use crate::utf_8::{self, Utf8, Utf8Decoder};
+use crate::cesu_8;
+use crate::incremental::{Decoder, Encoder, SanityChecker};
+use crate::lossy::{self, DecodingPolicy};
+
+/// A wrapper for WTF-8 encoded bytes. The unpaired surrogates are encoded as
+/// three bytes like the other code-points of the BMP, while the pairs of
+/// surrogates are encoded as the supplementary code-point they form, as in
+/// UTF-8. Thus, WTF-8 without unpaired surrogates is valid UTF-8.
+pub struct Wtf8 {
+    pub data: Vec<u8>
+}
+
+impl Wtf8 {
+    /// Converts the content to UTF-8. An error locating the first unpaired
+    /// surrogate is returned if the content is not well-formed.
+    pub fn to_utf_8(&self) -> Result<Utf8, UnicodeEncodingError> {
+        return Utf8::from_bytes(&self.data, ()).map_err(|x| x.in_encoding(Self::NAME));
+    }
+}
+
+impl UnicodeEncoding for Wtf8 {
+    const NAME: &'static str = "WTF-8";
+    type Options = ();
+
+    /// Convert UTF-32 data to WTF-8.
+    fn from_utf_32(data_utf_32: &Utf32) -> Self {
+        // A high surrogate followed by a low surrogate must be written as the
+        // code-point they form.
+        let paired = Utf32{data: cesu_8::pair_surrogates(&data_utf_32.data)};
+        return Wtf8{data: Utf8::from_utf_32(&paired).data};
+    }
+
+    /// Convert WTF-8 data to UTF-32.
+    fn try_to_utf_32(&self) -> Result<Utf32, UnicodeEncodingError> {
+        let mut index: usize = 0;
+        let mut data: Vec<u32> = Vec::new();
+        while index < self.data.len() {
+            let (glyph, len) = wtf_8_glyph_to_utf_32(&self.data, index).map_err(|x| x.in_encoding(Self::NAME))?;
+            data.push(glyph);
+            index += len;
+        }
+        return Ok(Utf32{data: data});
+    }
+
+    /// Convert the instance of `Wtf8` to a vector of bytes.
+    /// No transformation is needed.
+    fn to_bytes(&self, _options: ()) -> Vec<u8> {
+        return self.data.clone();
+    }
+
+    /// Consider a stream of WTF-8 encoded bytes and turn it into a `Wtf8`
+    /// type. The bytes are decoded to ensure they are valid. An encoded high
+    /// surrogate followed by an encoded low surrogate is rejected by
+    /// `check_sanity`, as the pair should have been encoded as a single
+    /// code-point.
+    fn from_bytes_no_check(bytes: &[u8], _options: ()) -> Result<Self, UnicodeEncodingError> {
+        let ret = Wtf8{data: bytes.to_vec()};
+        ret.try_to_utf_32()?;
+        return Ok(ret);
+    }
+
+    /// Decodes WTF-8 glyph by glyph, replacing the maximal subparts of the
+    /// invalid sequences as in UTF-8.
+    fn from_bytes_lossy(bytes: &[u8], _options: (), policy: DecodingPolicy) -> Result<(Self, usize), UnicodeEncodingError> {
+        let (data, count) = lossy::decode_glyphs_lossy(bytes, wtf_8_glyph_to_utf_32, policy).map_err(|x| x.in_encoding(Self::NAME))?;
+        let (data, sanitized) = lossy::sanitize(&data, policy).map_err(|x| x.in_encoding(Self::NAME))?;
+        return Ok((Self::from_utf_32(&Utf32{data: data}), count + sanitized));
+    }
+
+    fn decoder(_options: ()) -> Box<dyn Decoder> {
+        return Box::new(Utf8Decoder::with_glyph_decoder(wtf_8_glyph_to_utf_32, Self::NAME));
+    }
+
+    fn encoder(_options: ()) -> Box<dyn Encoder> {
+        return Box::new(Wtf8Encoder{checker: SanityChecker::new()});
+    }
+}
+
+/* --------------------------- Incremental coding --------------------------- */
+
+/// Encodes WTF-8 data chunk by chunk. As the pairs of surrogates are rejected
+/// by the checks, the glyphs can be encoded one by one.
+pub struct Wtf8Encoder {
+    checker: SanityChecker,
+}
+
+impl Encoder for Wtf8Encoder {
+    fn encode(&mut self, data: &Utf32) -> Result<Vec<u8>, UnicodeEncodingError> {
+        self.checker.push_all(data)?;
+        return Ok(Wtf8::from_utf_32(data).data);
+    }
+
+    fn finish(&mut self) -> Result<Vec<u8>, UnicodeEncodingError> {
+        self.checker.finish()?;
+        return Ok(Vec::new());
+    }
+}
+
+/* ---------------------------- Helper functions ---------------------------- */
+
+/// Decodes the first glyph of some WTF-8 data. The well-formed sequences of
+/// UTF-8 are accepted, as well as the encoded surrogates.
+fn wtf_8_glyph_to_utf_32(data: &[u8], start: usize) -> Result<(u32, usize), UnicodeEncodingError> {
+    if data[start] == 0xED {
+        // The second byte is not restricted to allow the surrogates.
+        return utf_8::utf_8_glyph_to_utf_32(data, start);
+    }
+    return utf_8::utf_8_glyph_to_utf_32_strict(data, start);
+}
+
+/* --------------------------------- Testing -------------------------------- */
+
+#[test]
+fn test_wtf_8_lone_surrogates() {
+    use crate::utf_16::Utf16;
+    // Ill-formed UTF-16, as found in JavaScript strings.
+    let utf16 = Utf16{data: vec![0x41, 0xD800, 0xD800, 0xDC00, 0xDC01, 0xD83D]};
+    assert!(!utf16.is_well_formed());
+    let wtf8 = utf16.convert_to::<Wtf8>().unwrap();
+    assert_eq!(wtf8.data, vec![0x41, 0xED, 0xA0, 0x80, 0xF0, 0x90, 0x80, 0x80, 0xED, 0xB0, 0x81, 0xED, 0xA0, 0xBD]);
+    let back = Wtf8::from_bytes(&wtf8.data, ()).unwrap().convert_to::<Utf16>().unwrap();
+    assert_eq!(back.data, utf16.data);
+    assert!(!wtf8.is_well_formed());
+    assert!(wtf8.to_utf_8().is_err());
+    assert!(wtf8.to_string().is_err());
+}
+
+#[test]
+fn test_wtf_8_well_formed() {
+    let wtf8 = Wtf8::from_string("aé😎").unwrap();
+    assert!(wtf8.is_well_formed());
+    assert_eq!(wtf8.to_utf_8().unwrap().data, "aé😎".as_bytes());
+    assert_eq!(wtf8.to_string().unwrap(), "aé😎");
+}
+
+#[test]
+fn test_invalid_wtf_8() {
+    use crate::unicode_encoding::UnicodeEncodingErrorKind::*;
+    // A pair of surrogates must be encoded as a single code-point.
+    let err = Wtf8::from_bytes(&[0xED, 0xA0, 0x80, 0xED, 0xB0, 0x80], ()).err().unwrap();
+    assert_eq!(err.kind, AmbiguousUnpairedSurrogates);
+    assert_eq!(Wtf8::from_bytes(&[0xC0, 0x80], ()).err().unwrap().kind, OverlongUtf8Encoding);
+}
+
+#[test]
+fn test_wtf_8_incremental() {
+    let bytes: Vec<u8> = vec![0x41, 0xED, 0xA0, 0x80, 0xF0, 0x90, 0x80, 0x80];
+    let mut decoder = Wtf8::decoder(());
+    let decoded = crate::incremental::decode_byte_per_byte(decoder.as_mut(), &bytes).unwrap();
+    assert_eq!(decoded.data, vec![0x41, 0xD800, 0x10000]);
+}